@@ -1,5 +1,4 @@
-use proc_macro2::Span;
-use crate::types::{FieldKind, StrictMode};
+use crate::types::{Cdata, FieldDefault, Nillable, RenameRule, StrictMode};
 use crate::utils::Context;
 use syn::Attribute;
 use syn::Error;
@@ -10,13 +9,35 @@ use syn::NestedMeta;
 
 pub(crate) struct Container {
     pub(crate) tags: Vec<LitStr>,
+    pub(crate) ns: Option<LitStr>,
+    pub(crate) prefix: Option<LitStr>,
+    pub(crate) default_ns: Option<LitStr>,
     pub(crate) strict_mode: StrictMode,
+    pub(crate) declaration: bool,
+    pub(crate) text: bool,
+    pub(crate) rename_all: Option<RenameRule>,
+    pub(crate) discriminator: Option<LitStr>,
+    pub(crate) discriminator_ns: Option<LitStr>,
+    pub(crate) value: Option<LitStr>,
+    pub(crate) value_ns: Option<LitStr>,
+    pub(crate) fields: bool,
 }
 
 impl Container {
     pub(crate) fn parse(ctx: &mut Context, attrs: Vec<Attribute>) -> Self {
         let mut tags = Vec::new();
+        let mut ns = None;
+        let mut prefix = None;
+        let mut default_ns = None;
         let mut strict_mode = StrictMode::empty();
+        let mut declaration = false;
+        let mut text = false;
+        let mut rename_all = None;
+        let mut discriminator = None;
+        let mut discriminator_ns = None;
+        let mut value = None;
+        let mut value_ns = None;
+        let mut fields = false;
 
         for meta in attrs.iter().filter_map(get_xml_meta).flatten() {
             match meta {
@@ -28,6 +49,133 @@ impl Container {
                     }
                 }
 
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("ns") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if ns.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `ns` attribute");
+                        } else {
+                            ns = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("prefix") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if prefix.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `prefix` attribute");
+                        } else {
+                            prefix = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("default_ns") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if default_ns.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `default_ns` attribute");
+                        } else {
+                            default_ns = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("rename_all") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if rename_all.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `rename_all` attribute");
+                        } else if let Some(rule) = RenameRule::parse(&lit.value()) {
+                            rename_all = Some(rule);
+                        } else {
+                            ctx.push_spanned_error(
+                                lit,
+                                "unknown rule, expected one of `lowercase`, `UPPERCASE`, `PascalCase`, \
+                                `camelCase`, `snake_case`, `SCREAMING_SNAKE_CASE`, `kebab-case` \
+                                or `SCREAMING-KEBAB-CASE`",
+                            );
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("discriminator") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if discriminator.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `discriminator` attribute");
+                        } else {
+                            discriminator = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("discriminator_ns") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if discriminator_ns.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `discriminator_ns` attribute");
+                        } else {
+                            discriminator_ns = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("value") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if value.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `value` attribute");
+                        } else {
+                            value = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("value_ns") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if value_ns.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `value_ns` attribute");
+                        } else {
+                            value_ns = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("declaration") => {
+                    if declaration {
+                        ctx.push_spanned_error(p, "duplicate `declaration` attribute");
+                    } else {
+                        declaration = true;
+                    }
+                }
+
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("text") => {
+                    if text {
+                        ctx.push_spanned_error(p, "duplicate `text` attribute");
+                    } else {
+                        text = true;
+                    }
+                }
+
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("fields") => {
+                    if fields {
+                        ctx.push_spanned_error(p, "duplicate `fields` attribute");
+                    } else {
+                        fields = true;
+                    }
+                }
+
                 NestedMeta::Meta(Meta::List(m)) if m.path.is_ident("strict") => {
                     for nested in m.nested {
                         match nested {
@@ -53,7 +201,78 @@ impl Container {
             }
         }
 
-        Self { tags, strict_mode }
+        if let (Some(prefix), None) = (&prefix, &ns) {
+            ctx.push_spanned_error(prefix, "`prefix` attribute requires `ns`");
+        }
+
+        if let (Some(discriminator_ns), None) = (&discriminator_ns, &discriminator) {
+            ctx.push_spanned_error(discriminator_ns, "`discriminator_ns` attribute requires `discriminator`");
+        }
+
+        Self {
+            tags,
+            ns,
+            prefix,
+            default_ns,
+            strict_mode,
+            declaration,
+            text,
+            rename_all,
+            discriminator,
+            discriminator_ns,
+            value,
+            value_ns,
+            fields,
+        }
+    }
+
+    /// Whether `#[xml(fields)]` is among `attrs`, checked before parsing them.
+    pub(crate) fn is_fields(attrs: &[Attribute]) -> bool {
+        attrs
+            .iter()
+            .filter_map(get_xml_meta)
+            .flatten()
+            .any(|meta| matches!(meta, NestedMeta::Meta(Meta::Path(p)) if p.is_ident("fields")))
+    }
+}
+
+/// The attributes of a variant deriving `XmlValue`.
+pub(crate) struct Variant {
+    pub(crate) value: Option<LitStr>,
+    pub(crate) other: bool,
+}
+
+impl Variant {
+    pub(crate) fn parse(ctx: &mut Context, attrs: Vec<Attribute>) -> Self {
+        let mut value = None;
+        let mut other = false;
+
+        for meta in attrs.iter().filter_map(get_xml_meta).flatten() {
+            match meta {
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("value") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if value.is_some() {
+                            ctx.push_spanned_error(m.path, "duplicate `value` attribute");
+                        } else {
+                            value = Some(lit);
+                        }
+                    } else {
+                        ctx.push_spanned_error(m.lit, "expected a string literal");
+                    }
+                }
+
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("other") => {
+                    if other {
+                        ctx.push_spanned_error(p, "duplicate `other` attribute");
+                    } else {
+                        other = true;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Self { value, other }
     }
 }
 
@@ -63,67 +282,119 @@ pub (crate) enum Prefix {
 }
 
 pub(crate) struct Field {
-    pub(crate) default: bool,
+    pub(crate) default: Option<FieldDefault>,
     pub(crate) attr_tag: Option<LitStr>,
     pub(crate) child_tags: Vec<LitStr>,
     pub(crate) is_text: bool,
+    pub(crate) is_mixed: bool,
+    pub(crate) is_comment: bool,
+    pub(crate) pi_target: Option<LitStr>,
+    pub(crate) is_any_attr: bool,
+    pub(crate) is_any_element: bool,
+    pub(crate) is_skip: bool,
+    pub(crate) is_flatten: bool,
+    pub(crate) skip_serializing_if: Option<ExprPath>,
+    pub(crate) skip_default: bool,
     pub(crate) flatten_text_tag: Option<LitStr>,
-    pub(crate) is_cdata: bool,
+    pub(crate) cdata: Option<Cdata>,
+    pub(crate) nillable: Option<Nillable>,
     pub(crate) prefix: Option<Prefix>,
     pub(crate) with: Option<ExprPath>,
+    pub(crate) ns: Option<LitStr>,
 }
 
 impl Field {
-    pub(crate) fn parse(context: &mut Context, attrs: Vec<Attribute>) -> Self {
-        let mut default = false;
+    /// `name` is the tag used by a bare `attr` or `flatten_text`, which is
+    /// only known for named fields.
+    pub(crate) fn parse(context: &mut Context, attrs: Vec<Attribute>, name: Option<LitStr>) -> Self {
+        let mut default = None;
         let mut attr_tag = None;
         let mut child_tags = Vec::new();
         let mut is_text = false;
+        let mut is_mixed = false;
+        let mut is_comment = false;
+        let mut pi_target = None;
+        let mut is_any_attr = false;
+        let mut is_any_element = false;
+        let mut is_skip = false;
+        let mut is_flatten = false;
+        let mut skip_serializing_if = None;
+        let mut skip_default = false;
         let mut flatten_text_tag = None;
-        let mut is_cdata = false;
+        let mut cdata = None;
+        let mut nillable = None;
         let mut prefix = None;
         let mut with = None;
-
+        let mut ns = None;
 
         // TODO can this be handled more cleanly?
         for meta in attrs.iter().filter_map(get_xml_meta).flatten() {
             match meta {
                 NestedMeta::Meta(Meta::Path(p)) if p.is_ident("default") => {
-                    if default {
+                    if default.is_some() {
                         context.push(Error::new_spanned(p, "duplicate `default` attribute"));
                     } else {
-                        default = true;
+                        default = Some(FieldDefault::Trait);
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("default") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if default.is_some() {
+                            context.push(Error::new_spanned(m.path, "duplicate `default` attribute"));
+                        } else {
+                            match lit.parse() {
+                                Ok(path) => default = Some(FieldDefault::Path(path)),
+                                Err(e) => context.push(e),
+                            }
+                        }
+                    } else {
+                        context.push(Error::new_spanned(m.lit, "expected a string literal"));
                     }
                 }
-                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("attr") => {
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("default_str") => {
                     if let Lit::Str(lit) = m.lit {
+                        if default.is_some() {
+                            context.push(Error::new_spanned(
+                                m.path,
+                                "`default_str` attribute and `default` attribute is disjoint",
+                            ));
+                        } else {
+                            default = Some(FieldDefault::Str(lit));
+                        }
+                    } else {
+                        context.push(Error::new_spanned(m.lit, "expected a string literal"));
+                    }
+                }
+                NestedMeta::Meta(meta)
+                    if !matches!(meta, Meta::List(_)) && meta.path().is_ident("attr") =>
+                {
+                    let p = meta.path().clone();
+                    if let Some(lit) = tag_or_name(context, meta, &name) {
                         if attr_tag.is_some() {
-                            context.push(Error::new_spanned(m.path, "duplicate `attr` attribute"));
+                            context.push(Error::new_spanned(p, "duplicate `attr` attribute"));
                         } else if is_text {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`attr` attribute and `text` attribute is disjoint",
                             ));
-                        } else if is_cdata {
+                        } else if cdata.is_some() {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`attr` attribute and `cdata` attribute is disjoint",
                             ))
                         } else if !child_tags.is_empty() {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`attr` attribute and `child` attribute is disjoint",
                             ));
                         } else if flatten_text_tag.is_some() {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`attr` attribute and `flatten_text` attribute is disjoint",
                             ));
                         } else {
                             attr_tag = Some(lit);
                         }
-                    } else {
-                        context.push(Error::new_spanned(m.lit, "expected a string literal"));
                     }
                 }
                 NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("prefix") => {
@@ -135,7 +406,7 @@ impl Field {
                                 m.path,
                                 "`prefix` attribute and `text` attribute is disjoint",
                             ));
-                        } else if is_cdata {
+                        } else if cdata.is_some() {
                             context.push(Error::new_spanned(
                                 m.path,
                                 "`prefix` attribute and `cdata` attribute is disjoint",
@@ -167,7 +438,7 @@ impl Field {
                                 m.path,
                                 "`startswith` attribute and `text` attribute is disjoint",
                             ));
-                        } else if is_cdata {
+                        } else if cdata.is_some() {
                             context.push(Error::new_spanned(
                                 m.path,
                                 "`startswith` attribute and `cdata` attribute is disjoint",
@@ -212,8 +483,108 @@ impl Field {
                         is_text = true;
                     }
                 }
-                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("cdata") => {
-                    if is_cdata {
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("mixed") => {
+                    if is_mixed {
+                        context.push(Error::new_spanned(p, "Duplicate `mixed` attribute."));
+                    } else {
+                        is_mixed = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("comment") => {
+                    if is_comment {
+                        context.push(Error::new_spanned(p, "Duplicate `comment` attribute."));
+                    } else {
+                        is_comment = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("any_attr") => {
+                    if is_any_attr {
+                        context.push(Error::new_spanned(p, "Duplicate `any_attr` attribute."));
+                    } else {
+                        is_any_attr = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("any_element") => {
+                    if is_any_element {
+                        context.push(Error::new_spanned(p, "Duplicate `any_element` attribute."));
+                    } else {
+                        is_any_element = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("flatten") => {
+                    if is_flatten {
+                        context.push(Error::new_spanned(p, "Duplicate `flatten` attribute."));
+                    } else {
+                        is_flatten = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("skip") => {
+                    if is_skip {
+                        context.push(Error::new_spanned(p, "Duplicate `skip` attribute."));
+                    } else {
+                        is_skip = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref p)) if p.is_ident("skip_default") => {
+                    if skip_default {
+                        context.push(Error::new_spanned(p, "Duplicate `skip_default` attribute."));
+                    } else if skip_serializing_if.is_some() {
+                        context.push(Error::new_spanned(
+                            p,
+                            "`skip_default` attribute and `skip_serializing_if` attribute is disjoint.",
+                        ));
+                    } else {
+                        skip_default = true;
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("skip_serializing_if") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if skip_serializing_if.is_some() {
+                            context.push(Error::new_spanned(
+                                m.path,
+                                "Duplicate `skip_serializing_if` attribute.",
+                            ));
+                        } else if skip_default {
+                            context.push(Error::new_spanned(
+                                m.path,
+                                "`skip_serializing_if` attribute and `skip_default` attribute is disjoint.",
+                            ));
+                        } else {
+                            match lit.parse() {
+                                Ok(path) => skip_serializing_if = Some(path),
+                                Err(e) => context.push(e),
+                            }
+                        }
+                    } else {
+                        context.push(Error::new_spanned(m.lit, "Expected a string literal."));
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("pi") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if pi_target.is_some() {
+                            context.push(Error::new_spanned(m.path, "Duplicate `pi` attribute."));
+                        } else {
+                            pi_target = Some(lit);
+                        }
+                    } else {
+                        context.push(Error::new_spanned(m.lit, "Expected a string literal."));
+                    }
+                }
+                NestedMeta::Meta(ref meta)
+                    if !matches!(meta, Meta::List(_)) && meta.path().is_ident("cdata") =>
+                {
+                    let p = meta.path();
+                    let mode = match meta {
+                        Meta::NameValue(m) => match &m.lit {
+                            Lit::Str(lit) if lit.value() == "auto" => Cdata::Auto,
+                            lit => {
+                                context.push(Error::new_spanned(lit, "expected `\"auto\"`"));
+                                Cdata::Auto
+                            }
+                        },
+                        _ => Cdata::Always,
+                    };
+                    if cdata.is_some() {
                         context.push(Error::new_spanned(p, "Duplicate `cdata` attribute."));
                     } else if attr_tag.is_some() {
                         context.push(Error::new_spanned(
@@ -226,7 +597,27 @@ impl Field {
                             "`text` attribute and `child` attribute is disjoint.",
                         ));
                     } else {
-                        is_cdata = true;
+                        cdata = Some(mode);
+                    }
+                }
+                NestedMeta::Meta(meta)
+                    if !matches!(meta, Meta::List(_)) && meta.path().is_ident("nillable") =>
+                {
+                    let p = meta.path().clone();
+                    let mode = match meta {
+                        Meta::NameValue(m) => match &m.lit {
+                            Lit::Str(lit) if lit.value() == "explicit" => Nillable::Explicit,
+                            lit => {
+                                context.push(Error::new_spanned(lit, "expected `\"explicit\"`"));
+                                Nillable::Explicit
+                            }
+                        },
+                        _ => Nillable::Omit,
+                    };
+                    if nillable.is_some() {
+                        context.push(Error::new_spanned(p, "duplicate `nillable` attribute"));
+                    } else {
+                        nillable = Some(mode);
                     }
                 }
                 NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("child") => {
@@ -241,7 +632,7 @@ impl Field {
                                 m.path,
                                 "`child` attribute and `attr` attribute is disjoint.",
                             ));
-                        } else if is_cdata {
+                        } else if cdata.is_some() {
                             context.push(Error::new_spanned(
                                 m.path,
                                 "`child` attribute and `cdata` attribute is disjoint.",
@@ -258,33 +649,34 @@ impl Field {
                         context.push(Error::new_spanned(m.lit, "Expected a string literal."));
                     }
                 }
-                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("flatten_text") => {
-                    if let Lit::Str(lit) = m.lit {
+                NestedMeta::Meta(meta)
+                    if !matches!(meta, Meta::List(_)) && meta.path().is_ident("flatten_text") =>
+                {
+                    let p = meta.path().clone();
+                    if let Some(lit) = tag_or_name(context, meta, &name) {
                         if is_text {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`flatten_text` attribute and `text` attribute is disjoint.",
                             ));
                         } else if !child_tags.is_empty() {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`flatten_text` attribute and `child` attribute is disjoint.",
                             ));
                         } else if attr_tag.is_some() {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "`flatten_text` attribute and `attr` attribute is disjoint.",
                             ));
                         } else if flatten_text_tag.is_some() {
                             context.push(Error::new_spanned(
-                                m.path,
+                                p,
                                 "Duplicate `flatten_text` attribute.",
                             ));
                         } else {
                             flatten_text_tag = Some(lit);
                         }
-                    } else {
-                        context.push(Error::new_spanned(m.lit, "Expected a string literal."));
                     }
                 }
                 NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("with") => {
@@ -297,6 +689,17 @@ impl Field {
                         context.push(Error::new_spanned(m.lit, "Expected a string literal."));
                     }
                 },
+                NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("ns") => {
+                    if let Lit::Str(lit) = m.lit {
+                        if ns.is_some() {
+                            context.push(Error::new_spanned(m.path, "Duplicate `ns` attribute."));
+                        } else {
+                            ns = Some(lit);
+                        }
+                    } else {
+                        context.push(Error::new_spanned(m.lit, "Expected a string literal."));
+                    }
+                }
                 _ => (),
             }
         }
@@ -306,10 +709,43 @@ impl Field {
             attr_tag,
             child_tags,
             is_text,
+            is_mixed,
+            is_comment,
+            pi_target,
+            is_any_attr,
+            is_any_element,
+            is_skip,
+            is_flatten,
+            skip_serializing_if,
+            skip_default,
             flatten_text_tag,
-            is_cdata,
+            cdata,
+            nillable,
             prefix,
             with,
+            ns,
+        }
+    }
+}
+
+/// The tag of `attr = "$tag"`, or the field name for a bare `attr`.
+fn tag_or_name(context: &mut Context, meta: Meta, name: &Option<LitStr>) -> Option<LitStr> {
+    match meta {
+        Meta::NameValue(m) => match m.lit {
+            Lit::Str(lit) => Some(lit),
+            lit => {
+                context.push(Error::new_spanned(lit, "expected a string literal"));
+                None
+            }
+        },
+        meta => {
+            if name.is_none() {
+                context.push(Error::new_spanned(
+                    meta.path(),
+                    "a tag is required on unnamed fields",
+                ));
+            }
+            name.clone()
         }
     }
 }
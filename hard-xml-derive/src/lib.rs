@@ -6,10 +6,11 @@ mod attrs;
 mod read;
 mod types;
 mod utils;
+mod value;
 mod write;
 
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{parse_macro_input, DeriveInput};
 use types::Element;
 
@@ -40,6 +41,42 @@ pub fn derive_xml_read(input: TokenStream) -> TokenStream {
                 .into()
         }
     };
+
+    if let Element::Fields { .. } = element {
+        let vis = &input.vis;
+        let builder = format_ident!("__{}XmlBuilder", name);
+        let (builder_fields, impl_read_fields) = read::impl_read_fields(element, &builder);
+
+        let gen = quote! {
+            #[doc(hidden)]
+            #vis struct #builder <#params>
+                #where_clause
+            {
+                #builder_fields
+                __phantom: std::marker::PhantomData<#name <#params>>,
+            }
+
+            impl <#params_with_input_lifetime> hard_xml::XmlReadFields<'__input> for #name <#params>
+                #where_clause
+            {
+                type Builder = #builder <#params>;
+
+                #impl_read_fields
+            }
+        };
+
+        return gen.into();
+    }
+
+    let impl_mixed = read::impl_mixed(&element).map(|impl_mixed| {
+        quote! {
+            impl <#params_with_input_lifetime> hard_xml::XmlMixed<'__input> for #name <#params>
+                #where_clause
+            {
+                #impl_mixed
+            }
+        }
+    });
     let impl_read = read::impl_read(element);
 
     let gen = quote! {
@@ -51,9 +88,13 @@ pub fn derive_xml_read(input: TokenStream) -> TokenStream {
             ) -> hard_xml::XmlResult<Self> {
                 use hard_xml::xmlparser::{ElementEnd, Token, Tokenizer};
                 use hard_xml::XmlError;
-                #impl_read
+                let __depth = reader.path_depth();
+                let __res: hard_xml::XmlResult<Self> = (|| { #impl_read })();
+                __res.map_err(|err| reader.unwind(err, __depth))
             }
         }
+
+        #impl_mixed
     };
 
     gen.into()
@@ -80,6 +121,36 @@ pub fn derive_xml_write(input: TokenStream) -> TokenStream {
                 .into()
         }
     };
+    let collect_namespaces = write::impl_collect_namespaces(&element);
+
+    let impl_collect_namespaces = if collect_namespaces.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn collect_namespaces(__namespaces: &mut hard_xml::XmlNamespaces) {
+                if __namespaces.visit(std::any::type_name::<Self>()) {
+                    #collect_namespaces
+                }
+            }
+        }
+    };
+
+    if let Element::Fields { .. } = element {
+        let impl_write_fields = write::impl_write_fields(element);
+
+        let gen = quote! {
+            impl <#params> hard_xml::XmlWriteFields for #name <#params>
+                #where_clause
+            {
+                #impl_write_fields
+
+                #impl_collect_namespaces
+            }
+        };
+
+        return gen.into();
+    }
+
     let impl_write = write::impl_write(element);
 
     let gen = quote! {
@@ -94,8 +165,24 @@ pub fn derive_xml_write(input: TokenStream) -> TokenStream {
 
                 Ok(())
             }
+
+            #impl_collect_namespaces
         }
     };
 
     gen.into()
 }
+
+#[proc_macro_derive(XmlValue, attributes(xml))]
+pub fn derive_xml_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match value::impl_value(input) {
+        Ok(gen) => gen.into(),
+        Err(errors) => errors
+            .into_iter()
+            .map(syn::Error::into_compile_error)
+            .collect::<proc_macro2::TokenStream>()
+            .into(),
+    }
+}
@@ -0,0 +1,161 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Ident;
+
+use super::named::{
+    attr_pattern, element_pattern, read_attr_fields, read_child_fields, read_flatten_text_fields,
+    return_fields,
+};
+use crate::types::Field;
+
+/// The fields of the builder of a `#[xml(fields)]` struct, holding what
+/// `read` keeps in local variables for other structs.
+pub fn builder_fields(fields: &[Field], types: &[syn::Type]) -> TokenStream {
+    fields
+        .iter()
+        .zip(types)
+        .map(|(field, ty)| match field {
+            Field::Child { bind, ty: field_ty, .. } | Field::FlattenText { bind, ty: field_ty, .. }
+                if field_ty.is_vec() =>
+            {
+                let count = format_ident!("{}_count", bind);
+                quote! {
+                    #bind: #ty,
+                    #count: usize,
+                }
+            }
+            Field::Attribute { bind, ty: field_ty, .. }
+            | Field::Child { bind, ty: field_ty, .. }
+            | Field::FlattenText { bind, ty: field_ty, .. } => {
+                if field_ty.is_option() {
+                    quote! { #bind: #ty, }
+                } else {
+                    quote! { #bind: Option<#ty>, }
+                }
+            }
+            _ => quote! {},
+        })
+        .collect()
+}
+
+pub fn read_fields(ele_name: &Ident, builder: &Ident, fields: &[Field]) -> TokenStream {
+    let ele_name = quote!(#ele_name);
+
+    let binds = |f: fn(&Field) -> bool| -> Vec<Ident> {
+        fields
+            .iter()
+            .filter(|field| f(field))
+            .flat_map(|field| match field {
+                Field::Child { bind, ty, .. } | Field::FlattenText { bind, ty, .. }
+                    if ty.is_vec() =>
+                {
+                    vec![bind.clone(), format_ident!("{}_count", bind)]
+                }
+                Field::Attribute { bind, .. }
+                | Field::Child { bind, .. }
+                | Field::FlattenText { bind, .. } => vec![bind.clone()],
+                _ => vec![],
+            })
+            .collect()
+    };
+    let all_binds = binds(|_| true);
+    let attr_binds = binds(|field| matches!(field, Field::Attribute { .. }));
+    let child_binds = binds(|field| !matches!(field, Field::Attribute { .. }));
+
+    let attr_patterns = fields.iter().filter_map(|field| match field {
+        Field::Attribute { tag, ns, .. } => Some(attr_pattern(tag, ns)),
+        _ => None,
+    });
+
+    let element_patterns = fields.iter().filter_map(|field| match field {
+        Field::Child { tags, ns, .. } => Some(element_pattern(tags, ns)),
+        Field::FlattenText { tag, ns, .. } => Some(element_pattern(std::slice::from_ref(tag), ns)),
+        _ => None,
+    });
+
+    let read_attr_fields = read_attr_fields(fields, &ele_name);
+    let read_child_fields = read_child_fields(fields, &ele_name);
+    let read_flatten_text_fields = read_flatten_text_fields(fields, &ele_name);
+    let return_fields = return_fields(fields, &ele_name);
+
+    quote! {
+        fn builder() -> Self::Builder {
+            #builder {
+                #( #all_binds: Default::default(), )*
+                __phantom: std::marker::PhantomData,
+            }
+        }
+
+        #[allow(unused_variables)]
+        fn is_attribute(reader: &mut hard_xml::XmlReader<'__input>, __key: &'__input str) -> bool {
+            match __key {
+                #( #attr_patterns => true, )*
+                _ => false,
+            }
+        }
+
+        #[allow(unused_variables)]
+        fn read_attribute(
+            builder: &mut Self::Builder,
+            reader: &mut hard_xml::XmlReader<'__input>,
+            __key: &'__input str,
+            __value: std::borrow::Cow<'__input, str>,
+        ) -> hard_xml::XmlResult<()> {
+            use hard_xml::XmlError;
+
+            #( let mut #attr_binds = std::mem::take(&mut builder.#attr_binds); )*
+
+            match __key {
+                #( #read_attr_fields, )*
+                _ => (),
+            }
+
+            #( builder.#attr_binds = #attr_binds; )*
+
+            Ok(())
+        }
+
+        #[allow(unused_variables)]
+        fn is_element(reader: &mut hard_xml::XmlReader<'__input>, __tag: &'__input str) -> bool {
+            match __tag {
+                #( #element_patterns => true, )*
+                _ => false,
+            }
+        }
+
+        #[allow(unused_variables)]
+        fn read_element(
+            builder: &mut Self::Builder,
+            reader: &mut hard_xml::XmlReader<'__input>,
+            __tag: &'__input str,
+        ) -> hard_xml::XmlResult<()> {
+            use hard_xml::XmlError;
+
+            #( let mut #child_binds = std::mem::take(&mut builder.#child_binds); )*
+
+            match __tag {
+                #( #read_child_fields, )*
+                #( #read_flatten_text_fields, )*
+                _ => (),
+            }
+
+            #( builder.#child_binds = #child_binds; )*
+
+            Ok(())
+        }
+
+        #[allow(unused_variables)]
+        fn finish(
+            builder: Self::Builder,
+            reader: &mut hard_xml::XmlReader<'__input>,
+        ) -> hard_xml::XmlResult<Self> {
+            use hard_xml::XmlError;
+
+            let #builder { #( #all_binds, )* .. } = builder;
+
+            Ok(#ele_name {
+                #( #return_fields, )*
+            })
+        }
+    }
+}
@@ -1,10 +1,12 @@
+mod fields;
 mod named;
 mod newtype;
 
-use crate::types::{Element, Fields};
+use crate::types::{Discriminator, Element, Fields};
 
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::Ident;
 
 pub fn impl_read(element: Element) -> TokenStream {
     match element {
@@ -12,25 +14,103 @@ pub fn impl_read(element: Element) -> TokenStream {
             name: ele_name,
             variants,
         } => {
-            let tags = variants.iter().map(|variant| match variant {
-                Fields::Newtype { tags, .. } => tags.clone(),
-                Fields::Named { tag, .. } => vec![tag.clone()],
-            });
+            // the text variant of mixed content is never read from an element
+            let variants: Vec<_> = variants
+                .iter()
+                .filter(|variant| !matches!(variant, Fields::Text { .. }))
+                .collect();
 
             let read = variants.iter().map(|variant| match variant {
                 Fields::Named {
                     tag,
+                    ns,
                     strict,
+                    discriminator,
                     name,
                     fields,
-                } => named::read(tag, *strict, quote!(#ele_name::#name), fields),
+                    ..
+                } => named::read(tag, ns, *strict, discriminator, quote!(#ele_name::#name), fields),
                 Fields::Newtype { name, ty, .. } => newtype::read(ty, quote!(#ele_name::#name)),
+                Fields::Text { .. } => unreachable!(),
             });
 
+            let arms = match variants.first() {
+                // all variants of a discriminated enum share one tag
+                Some(Fields::Named {
+                    discriminator:
+                        Some(Discriminator {
+                            attr, ns, local, ..
+                        }),
+                    ..
+                }) => {
+                    let pattern = element_pattern(variants[0]);
+                    let discriminators: Vec<_> = variants
+                        .iter()
+                        .filter_map(|variant| match variant {
+                            Fields::Named {
+                                discriminator: Some(discriminator),
+                                ..
+                            } => Some(discriminator),
+                            _ => None,
+                        })
+                        .collect();
+                    let values = discriminators.iter().map(|discriminator| &discriminator.value);
+
+                    let peek = match ns {
+                        Some(ns) => quote! { reader.peek_attribute_ns(#ns, #local)? },
+                        None => quote! { reader.peek_attribute(#attr)? },
+                    };
+
+                    // the start tag is consumed so that the caller can skip
+                    // the element when recovering from the error
+                    // values with a `value_ns` are qualified names, matched
+                    // by the namespace their prefix is bound to
+                    let arms = discriminators.iter().map(|discriminator| {
+                        let Discriminator {
+                            value,
+                            value_ns,
+                            value_local,
+                            ..
+                        } = discriminator;
+                        match value_ns {
+                            Some(ns) => quote! {
+                                Some(__value) if reader.resolve_qname(__value) == (Some(#ns), #value_local)
+                            },
+                            None => quote! { Some(#value) },
+                        }
+                    });
+
+                    quote! {
+                        #pattern => match #peek.as_deref() {
+                            #( #arms => { #read } )*
+                            Some(__value) => {
+                                let __err = XmlError::FromStr(Box::new(hard_xml::UnknownValue {
+                                    value: __value.to_owned(),
+                                    expected: &[#( #values ),*],
+                                }));
+                                reader.next();
+                                return Err(__err);
+                            }
+                            None => {
+                                reader.next();
+                                return Err(XmlError::MissingField {
+                                    name: stringify!(#ele_name).to_owned(),
+                                    field: #attr.to_owned(),
+                                });
+                            }
+                        },
+                    }
+                }
+                _ => {
+                    let patterns = variants.iter().copied().map(element_pattern);
+                    quote! { #( #patterns => { #read } )* }
+                }
+            };
+
             quote! {
-                while let Some(tag) = reader.find_element_start(None)? {
-                    match tag {
-                        #( #( #tags )|* => { #read } )*
+                while let Some(__tag) = reader.find_element_start(None)? {
+                    match __tag {
+                        #arms
                         tag => {
                             hard_xml::log_skip_element!(#ele_name, tag);
                             // skip the start tag
@@ -47,11 +127,82 @@ pub fn impl_read(element: Element) -> TokenStream {
         Element::Struct { fields, .. } => match fields {
             Fields::Named {
                 tag,
+                ns,
                 strict,
                 name,
                 fields,
-            } => named::read(&tag, strict, quote!(#name), &fields),
+                discriminator,
+                ..
+            } => named::read(&tag, &ns, strict, &discriminator, quote!(#name), &fields),
             Fields::Newtype { name, ty, .. } => newtype::read(&ty, quote!(#name)),
+            Fields::Text { .. } => unreachable!(),
         },
+
+        Element::Fields { .. } => unreachable!(),
+    }
+}
+
+/// The fields of the builder of a `#[xml(fields)]` struct named `builder`,
+/// and the `XmlReadFields` methods.
+pub fn impl_read_fields(element: Element, builder: &Ident) -> (TokenStream, TokenStream) {
+    match element {
+        Element::Fields {
+            name,
+            fields,
+            types,
+        } => (
+            fields::builder_fields(&fields, &types),
+            fields::read_fields(&name, builder, &fields),
+        ),
+        _ => unreachable!(),
+    }
+}
+
+/// `XmlMixed` methods for an enum with a text variant, if it has one.
+pub fn impl_mixed(element: &Element) -> Option<TokenStream> {
+    let (ele_name, variants) = match element {
+        Element::Enum { name, variants } => (name, variants),
+        Element::Struct { .. } | Element::Fields { .. } => return None,
+    };
+
+    let (name, ty) = variants.iter().find_map(|variant| match variant {
+        Fields::Text { name, ty } => Some((name, ty)),
+        _ => None,
+    })?;
+
+    let from_str = named::from_str(ty, &None);
+
+    let patterns = variants
+        .iter()
+        .filter(|variant| !matches!(variant, Fields::Text { .. }))
+        .map(element_pattern);
+
+    Some(quote! {
+        fn from_text(
+            __value: std::borrow::Cow<'__input, str>
+        ) -> hard_xml::XmlResult<Self> {
+            use hard_xml::XmlError;
+            let __value = #from_str;
+            Ok(#ele_name::#name(__value))
+        }
+
+        #[allow(unused_variables)]
+        fn is_element(
+            reader: &mut hard_xml::XmlReader<'__input>,
+            __tag: &'__input str
+        ) -> bool {
+            match __tag {
+                #( #patterns => true, )*
+                _ => false,
+            }
+        }
+    })
+}
+
+fn element_pattern(variant: &Fields) -> TokenStream {
+    match variant {
+        Fields::Newtype { tags, ns, .. } => named::element_pattern(tags, ns),
+        Fields::Named { tag, ns, .. } => named::element_pattern(std::slice::from_ref(tag), ns),
+        Fields::Text { .. } => unreachable!(),
     }
 }
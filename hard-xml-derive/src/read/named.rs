@@ -1,105 +1,161 @@
-use std::collections::HashMap;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{ExprPath, Ident, LitStr};
 
-use crate::types::{Field, StrictMode, Type};
+use crate::types::{Discriminator, Field, FieldDefault, StrictMode, Type};
 
 pub fn read(
     tag: &LitStr,
+    ns: &Option<LitStr>,
     strict: StrictMode,
+    discriminator: &Option<Discriminator>,
     ele_name: TokenStream,
     fields: &[Field],
 ) -> TokenStream {
+    // the name the end tag is compared with, which is only known
+    // once the start tag has been found if its prefix can vary
+    let end_tag = if ns.is_some() {
+        quote!(__element)
+    } else {
+        quote!(#tag)
+    };
+
     let init_fields = fields.iter().map(|field| match field {
+        Field::Child { bind, ty, .. }
+        | Field::FlattenText { bind, ty, .. }
+        | Field::Mixed { bind, ty, .. }
+        | Field::AnyElement { bind, ty, .. }
+            if ty.is_vec() =>
+        {
+            let init = init_value(bind, ty);
+            let count = format_ident!("{}_count", bind);
+            quote! {
+                #init
+                let mut #count = 0;
+            }
+        }
         Field::Attribute { bind, ty, .. }
         | Field::Child { bind, ty, .. }
         | Field::FlattenText { bind, ty, .. } 
+        | Field::Comment { bind, ty, .. }
+        | Field::ProcessingInstruction { bind, ty, .. }
+        | Field::AnyAttribute { bind, ty, .. }
         | Field::Prefix { bind, ty, .. }
         | Field::Startswith { bind, ty, .. } => init_value(bind, ty),
-        Field::Text { bind, .. } => quote! { let #bind; },
+        Field::Flatten { bind, ty, .. } => quote! {
+            let mut #bind = <#ty as hard_xml::XmlReadFields<'__input>>::builder();
+        },
+        Field::Text { .. } | Field::Mixed { .. } | Field::AnyElement { .. } | Field::Skip { .. } => {
+            quote! {}
+        }
     });
 
-    let return_fields = fields.iter().map(|field| match field {
-        Field::Attribute {
-            name,
-            bind,
-            ty,
-            default,
-            ..
-        }
-        | Field::Child {
-            name,
-            bind,
-            ty,
-            default,
-            ..
-        }
-        | Field::FlattenText {
-            name,
-            bind,
-            ty,
-            default,
-            ..
-        } => return_value(name, bind, ty, *default, &ele_name),
-        Field::Text { name, bind, ty, .. }
-        | Field::Prefix {
-            name,
-            bind,
-            ty,
-            ..
-        }
-        | Field::Startswith {
-            name,
-            bind,
-            ty,
-            ..
-        }=> return_value(name, bind, ty, false, &ele_name),
-    });
+    let return_fields = return_fields(fields, &ele_name);
+
+    let read_attr_fields = read_attr_fields(fields, &ele_name);
+
+    let read_child_fields = read_child_fields(fields, &ele_name);
 
-    let read_attr_fields = fields.iter().filter_map(|field| match field {
-        Field::Attribute {
-            bind,
-            ty,
-            with,
-            tag,
-            name,
-            ..
-        } => Some(read_attrs(tag, bind, name, ty, with, &ele_name)),
+    let read_flatten_text_fields = read_flatten_text_fields(fields, &ele_name);
+
+    let read_text_fields = fields.iter().filter_map(|field| match field {
+        Field::Text { bind, ty, with, name, .. } => Some(read_text(&end_tag, bind, name, ty, with, &ele_name)),
         _ => None,
     });
 
-    let read_child_fields = fields.iter().filter_map(|field| match field {
-        Field::Child {
-            bind,
-            ty,
-            tags,
-            name,
-            ..
-        } => Some(read_children(tags, bind, name, ty, &ele_name)),
+    // attributes matching a `prefix` or `startswith` field are claimed by it
+    let prefix_tags: Vec<_> = fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Prefix { tag, .. } | Field::Startswith { tag, .. } => Some(tag),
+            _ => None,
+        })
+        .collect();
+
+    let read_any_attr = fields.iter().find_map(|field| match field {
+        Field::AnyAttribute { bind, ty, name } => Some(read_any_attr(bind, name, ty, &ele_name)),
         _ => None,
     });
 
-    let read_flatten_text_fields = fields.iter().filter_map(|field| match field {
-        Field::FlattenText {
-            bind,
-            ty,
-            with,
-            tag,
-            name,
-            ..
-        } => Some(read_flatten_text(tag, bind, name, ty, with, &ele_name)),
+    let read_any_element = fields.iter().find_map(|field| match field {
+        Field::AnyElement { bind, ty, name, positioned } => {
+            Some(read_children(quote!(__tag), bind, name, ty, false, *positioned, &ele_name))
+        }
         _ => None,
     });
 
-    let read_text_fields = fields.iter().filter_map(|field| match field {
-        Field::Text { bind, ty, with, name, .. } => Some(read_text(tag, bind, name, ty, with, &ele_name)),
-        _ => None,
+    let read_mixed_text: Vec<_> = fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Mixed { bind, ty: Type::VecT(item), name } => Some(quote! {
+                if let Some(__value) = reader.read_mixed_text()? {
+                    reader.enter_field(stringify!(#name), None);
+                    match <#item as hard_xml::XmlMixed>::from_text(__value) {
+                        Ok(__value) => #bind.push(__value),
+                        Err(err) => reader.recover(err)?,
+                    }
+                    reader.leave();
+                }
+            }),
+            _ => None,
+        })
+        .collect();
+
+    let read_misc_fields: Vec<_> = fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Comment { bind, ty, with, name, positioned } => {
+                let pattern = quote!(Token::Comment { text, .. });
+                let value = quote!(text.as_str());
+                Some(read_misc(pattern, value, bind, name, ty, with, *positioned, &ele_name))
+            }
+            Field::ProcessingInstruction { bind, ty, with, target, name, positioned } => {
+                let pattern = quote! {
+                    Token::ProcessingInstruction { target, content, .. } if target.as_str() == #target
+                };
+                let value = quote!(content.map_or("", |content| content.as_str()));
+                Some(read_misc(pattern, value, bind, name, ty, with, *positioned, &ele_name))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // the number of child elements read so far, for `Positioned` fields
+    let is_positioned = fields.iter().any(|field| {
+        matches!(
+            field,
+            Field::Comment { positioned: true, .. }
+                | Field::ProcessingInstruction { positioned: true, .. }
+                | Field::AnyElement { positioned: true, .. }
+        )
     });
+    let (init_position, count_child, uncount_child) = if is_positioned {
+        (
+            quote! { let mut __position = 0; },
+            quote! { __position += 1; },
+            // skipped elements aren't written back
+            quote! { __position -= 1; },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {})
+    };
+
+    let read_misc = if read_misc_fields.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            while let Some(__token) = reader.find_misc()? {
+                match __token {
+                    #( #read_misc_fields, )*
+                    _ => (),
+                }
+            }
+        }
+    };
 
     let read_prefix_fields = fields.iter().filter_map(|field| match field {
-        Field::Prefix { bind, ty, name, tag, } => Some(read_prefix(tag, bind, name, ty, &ele_name)),
-        Field::Startswith { bind, ty, name, tag, } => Some(read_starts(tag, bind, name, ty, &ele_name)),
+        Field::Prefix { bind, ty, tag, .. } => Some(read_prefix(tag, bind, ty)),
+        Field::Startswith { bind, ty, tag, .. } => Some(read_starts(tag, bind, ty)),
         _ => None,
     });
 
@@ -112,21 +168,33 @@ pub fn read(
             #( #return_fields, )*
         };
 
+        reader.leave();
         hard_xml::log_finish_reading!(#ele_name);
 
         return Ok(__res);
     };
 
-    let unknown_element_handler = if strict.contains(StrictMode::UNKNOWN_ELEMENT) {
+    let unknown_element_handler = if let Some(read_any_element) = read_any_element {
+        read_any_element
+    } else if strict.contains(StrictMode::UNKNOWN_ELEMENT) {
         quote! {
-            return Err(XmlError::UnknownField { name: stringify!(#ele_name).to_owned(), field: tag.to_owned() });
+            tag => {
+                #uncount_child
+                let __depth = reader.element_depth();
+                // point the error at the start tag
+                reader.next();
+                reader.recover_element(XmlError::UnknownField { name: stringify!(#ele_name).to_owned(), field: tag.to_owned() }, __depth)?;
+            }
         }
     } else {
         quote! {
-            hard_xml::log_skip_element!(#ele_name, tag);
-            // skip the start tag
-            reader.next();
-            reader.read_to_end(tag)?;
+            tag => {
+                #uncount_child
+                hard_xml::log_skip_element!(#ele_name, tag);
+                // skip the start tag
+                reader.next();
+                reader.read_to_end(tag)?;
+            }
         }
     };
 
@@ -141,24 +209,62 @@ pub fn read(
                 #return_fields
             }
 
-            while let Some(__tag) = reader.find_element_start(Some(#tag))? {
+            #( #read_mixed_text )*
+            #init_position
+            #read_misc
+
+            while let Some(__tag) = reader.find_element_start(Some(#end_tag))? {
+                #count_child
                 match __tag {
                     #( #read_child_fields, )*
                     #( #read_flatten_text_fields, )*
-                    tag => {
-                        #unknown_element_handler
-                    },
+                    #unknown_element_handler,
                 }
+
+                #( #read_mixed_text )*
+                #read_misc
             }
 
             #return_fields
         }
     };
 
-    let unknown_attribute_handler = if strict.contains(StrictMode::UNKNOWN_ATTRIBUTE) {
-        quote!(return Err(XmlError::UnknownField { name: stringify!(#ele_name).to_owned(), field: key.to_owned()});)
+    let read_unknown_attribute = if let Some(read_any_attr) = read_any_attr {
+        if prefix_tags.is_empty() {
+            read_any_attr
+        } else {
+            quote! {
+                if #( key.starts_with(#prefix_tags) )||* {
+                    #( #read_prefix_fields )*
+                } else {
+                    #read_any_attr
+                }
+            }
+        }
+    } else if strict.contains(StrictMode::UNKNOWN_ATTRIBUTE) {
+        quote! {
+            #( #read_prefix_fields )*
+
+            reader.recover(XmlError::UnknownField { name: stringify!(#ele_name).to_owned(), field: key.to_owned()})?;
+        }
+    } else {
+        quote! {
+            #( #read_prefix_fields )*
+
+            hard_xml::log_skip_attribute!(#ele_name, key);
+        }
+    };
+
+    // the discriminator has been matched already when picking the variant
+    let read_discriminator = discriminator.iter().map(|Discriminator { ns, local, .. }| {
+        let pattern = attr_pattern(local, ns);
+        quote! { #pattern => {} }
+    });
+
+    let read_start = if let Some(ns) = ns {
+        quote! { let __element = reader.read_till_element_start_ns(#ns, #tag)?; }
     } else {
-        quote!(hard_xml::log_skip_attribute!(#ele_name, key);)
+        quote! { reader.read_till_element_start(#tag)?; }
     };
 
     quote! {
@@ -166,15 +272,15 @@ pub fn read(
 
         #( #init_fields )*
 
-        reader.read_till_element_start(#tag)?;
+        #read_start
+        reader.enter_element(#tag, stringify!(#ele_name));
 
         while let Some((__key, __value)) = reader.find_attribute()? {
             match __key {
+                #( #read_discriminator, )*
                 #( #read_attr_fields, )*
                 key => {
-                    #( #read_prefix_fields )*
-
-                    #unknown_attribute_handler
+                    #read_unknown_attribute
                 },
             }
         }
@@ -183,6 +289,132 @@ pub fn read(
     }
 }
 
+pub(super) fn return_fields(fields: &[Field], ele_name: &TokenStream) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .map(|field| match field {
+            Field::Attribute {
+                name,
+                bind,
+                ty,
+                with,
+                default,
+                ..
+            }
+            | Field::Child {
+                name,
+                bind,
+                ty,
+                with,
+                default,
+                ..
+            }
+            | Field::FlattenText {
+                name,
+                bind,
+                ty,
+                with,
+                default,
+                ..
+            } => return_value(name, bind, ty, with, default, ele_name),
+            Field::Text { name, bind, ty, .. }
+            | Field::Mixed { name, bind, ty }
+            | Field::Comment { name, bind, ty, .. }
+            | Field::ProcessingInstruction { name, bind, ty, .. }
+            | Field::AnyAttribute { name, bind, ty }
+            | Field::AnyElement { name, bind, ty, .. }
+            | Field::Prefix { name, bind, ty, .. }
+            | Field::Startswith { name, bind, ty, .. } => {
+                return_value(name, bind, ty, &None, &None, ele_name)
+            }
+            Field::Skip { name, .. } => quote! { #name: Default::default() },
+            Field::Flatten { name, bind, ty } => quote! {
+                #name: <#ty as hard_xml::XmlReadFields>::finish(#bind, reader)?
+            },
+        })
+        .collect()
+}
+
+/// Match arms reading the attributes of `fields`, given `__key` and `__value`.
+pub(super) fn read_attr_fields(fields: &[Field], ele_name: &TokenStream) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Attribute {
+                bind,
+                ty,
+                with,
+                tag,
+                ns,
+                name,
+                ..
+            } => Some(read_attrs(tag, ns, bind, name, ty, with, ele_name)),
+            Field::Flatten { bind, ty, .. } => Some(quote! {
+                __key if <#ty as hard_xml::XmlReadFields>::is_attribute(reader, __key) => {
+                    <#ty as hard_xml::XmlReadFields>::read_attribute(&mut #bind, reader, __key, __value)?;
+                }
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Match arms reading the child elements of `fields`, given `__tag`.
+pub(super) fn read_child_fields(fields: &[Field], ele_name: &TokenStream) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Child {
+                bind,
+                ty,
+                tags,
+                ns,
+                name,
+                nillable,
+                ..
+            } => Some(read_children(
+                element_pattern(tags, ns),
+                bind,
+                name,
+                ty,
+                nillable.is_some(),
+                false,
+                ele_name,
+            )),
+            Field::Mixed { bind, ty: ty @ Type::VecT(item), name } => {
+                let pattern = quote! {
+                    __tag if <#item as hard_xml::XmlMixed>::is_element(reader, __tag)
+                };
+                Some(read_children(pattern, bind, name, ty, false, false, ele_name))
+            }
+            Field::Flatten { bind, ty, .. } => Some(quote! {
+                __tag if <#ty as hard_xml::XmlReadFields>::is_element(reader, __tag) => {
+                    <#ty as hard_xml::XmlReadFields>::read_element(&mut #bind, reader, __tag)?;
+                }
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+pub(super) fn read_flatten_text_fields(fields: &[Field], ele_name: &TokenStream) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::FlattenText {
+                bind,
+                ty,
+                with,
+                tag,
+                ns,
+                name,
+                ..
+            } => Some(read_flatten_text(tag, ns, bind, name, ty, with, ele_name)),
+            _ => None,
+        })
+        .collect()
+}
+
 fn init_value(name: &Ident, ty: &Type) -> TokenStream {
     if ty.is_vec() {
         quote! { let mut #name = Vec::new(); }
@@ -208,7 +440,8 @@ fn return_value(
     name: &TokenStream,
     bind: &Ident,
     ty: &Type,
-    default: bool,
+    with: &Option<ExprPath>,
+    default: &Option<FieldDefault>,
     ele_name: &TokenStream,
 ) -> TokenStream {
     if ty.is_map() {
@@ -223,20 +456,48 @@ fn return_value(
         }
     } else if ty.is_vec() || ty.is_option() {
         quote! { #name: #bind }
-    } else if default {
-        quote! { #name: #bind.unwrap_or_default() }
+    } else if let Some(default) = default {
+        match default {
+            FieldDefault::Trait => quote! { #name: #bind.unwrap_or_default() },
+            FieldDefault::Path(path) => quote! {
+                #name: match #bind {
+                    Some(__value) => __value,
+                    None => #path(),
+                }
+            },
+            FieldDefault::Str(lit) => {
+                let from_str = from_str(ty, with);
+                quote! {
+                    #name: match #bind {
+                        Some(__value) => __value,
+                        None => {
+                            let __value = std::borrow::Cow::Borrowed(#lit);
+                            #from_str
+                        }
+                    }
+                }
+            }
+        }
     } else {
+        let default = lenient_default(ty);
         quote! {
-            #name: #bind.ok_or(XmlError::MissingField {
-                name: stringify!(#ele_name).to_owned(),
-                field: stringify!(#name).to_owned(),
-            })?
+            #name: match #bind {
+                Some(__value) => __value,
+                None => reader.recover_missing(
+                    XmlError::MissingField {
+                        name: stringify!(#ele_name).to_owned(),
+                        field: stringify!(#name).to_owned(),
+                    },
+                    #default,
+                )?,
+            }
         }
     }
 }
 
 fn read_attrs(
     tag: &LitStr,
+    ns: &Option<LitStr>,
     bind: &Ident,
     name: &TokenStream,
     ty: &Type,
@@ -244,16 +505,33 @@ fn read_attrs(
     ele_name: &TokenStream,
 ) -> TokenStream {
     let from_str = from_str(ty, with);
+    let default = lenient_default(ty);
+
+    let pattern = attr_pattern(tag, ns);
 
     if ty.is_vec() {
         panic!("`attr` attribute doesn't support Vec.");
     } else {
         quote! {
-            #tag => {
+            #pattern => {
                 hard_xml::log_start_reading_field!(#ele_name, #name);
+                reader.enter_field(stringify!(#name), None);
+                reader.enter_attribute(__key);
 
-                #bind = Some(#from_str);
+                let __res: hard_xml::XmlResult<_> = (|| {
+                    let __value = #from_str;
+                    Ok(__value)
+                })();
+                match __res {
+                    Ok(__value) => #bind = Some(__value),
+                    Err(err) => {
+                        reader.recover(err)?;
+                        #bind = #default;
+                    }
+                }
 
+                reader.leave();
+                reader.leave();
                 hard_xml::log_finish_reading_field!(#ele_name, #name);
             }
         }
@@ -261,7 +539,7 @@ fn read_attrs(
 }
 
 fn read_text(
-    tag: &LitStr,
+    end_tag: &TokenStream,
     bind: &Ident,
     name: &TokenStream,
     ty: &Type,
@@ -269,216 +547,352 @@ fn read_text(
     ele_name: &TokenStream,
 ) -> TokenStream {
     let from_str = from_str(ty, with);
+    let default = lenient_default(ty);
 
     if ty.is_vec() {
         panic!("`text` attribute doesn't support Vec.");
     } else {
         quote! {
             hard_xml::log_start_reading_field!(#ele_name, #name);
+            reader.enter_field(stringify!(#name), None);
 
-            let __value = reader.read_text(#tag)?;
-            #bind = Some(#from_str);
+            let __depth = reader.element_depth().saturating_sub(1);
+            let #bind = match reader.read_text(#end_tag).and_then(|__value| {
+                let __value = #from_str;
+                Ok(__value)
+            }) {
+                Ok(__value) => Some(__value),
+                Err(err) => {
+                    reader.recover_element(err, __depth)?;
+                    #default
+                }
+            };
 
+            reader.leave();
             hard_xml::log_finish_reading_field!(#ele_name, #name);
         }
     }
 }
 
-
-
-fn read_prefix(
-    tag: &LitStr,
-    bind: &Ident,
-    name: &TokenStream,
-    ty: &Type,
-    ele_name: &TokenStream,
-) -> TokenStream {
+fn read_prefix(tag: &LitStr, bind: &Ident, ty: &Type) -> TokenStream {
     let len = tag.value().len() + 1;
     if !ty.is_map() {
         panic!("`prefix` attribute only support Map.");
-    } else {
-        if let Type::OptionMap(arg1, arg2) = ty {
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key.starts_with(#tag) {
-                    #bind.insert(
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    );
-                }
+    } else if let Type::OptionMap(arg1, arg2) = ty {
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key.starts_with(#tag) {
+                #bind.insert(
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                );
             }
-        } else if let Type::Map(arg1, arg2) = ty {
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key.starts_with(#tag) {
-                    #bind.insert(
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    );
-                }
+        }
+    } else if let Type::Map(arg1, arg2) = ty {
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key.starts_with(#tag) {
+                #bind.insert(
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                );
             }
-        } else if let Type::VecTuple(arg1, arg2) = ty {
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key.starts_with(#tag) {
-                    #bind.insert((
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    ));
-                }
+        }
+    } else if let Type::VecTuple(arg1, arg2) = ty {
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key.starts_with(#tag) {
+                #bind.insert((
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                ));
             }
-        } else if let Type::OptionVecTuple(arg1, arg2) = ty {
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key.starts_with(#tag) {
-                    #bind.push((
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    ));
-                }
+        }
+    } else if let Type::OptionVecTuple(arg1, arg2) = ty {
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key.starts_with(#tag) {
+                #bind.push((
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                ));
             }
-        } else {
-            quote! {}
         }
+    } else {
+        quote! {}
     }
 }
 
-fn read_starts(
-    tag: &LitStr,
-    bind: &Ident,
-    name: &TokenStream,
-    ty: &Type,
-    ele_name: &TokenStream,
-) -> TokenStream {
+fn read_starts(tag: &LitStr, bind: &Ident, ty: &Type) -> TokenStream {
     let len = tag.value().len() + 1;
     if !ty.is_map() {
         panic!("`startswith` attribute only support Map.");
-    } else {
-        if let Type::OptionMap(arg1, arg2) = ty {
-            let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key == #tag {
-                    #bind.insert(
-                        #key_default,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    );
-                } else if key.starts_with(#tag) {
-                    #bind.insert(
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    );
-                }
+    } else if let Type::OptionMap(arg1, arg2) = ty {
+        let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key == #tag {
+                #bind.insert(
+                    #key_default,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                );
+            } else if key.starts_with(#tag) {
+                #bind.insert(
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                );
             }
-        } else if let Type::Map(arg1, arg2) = ty {
-            let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key == #tag {
-                    #bind.insert(
-                        #key_default,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    );
-                } else if key.starts_with(#tag) {
-                    #bind.insert(
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    );
-                }
+        }
+    } else if let Type::Map(arg1, arg2) = ty {
+        let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key == #tag {
+                #bind.insert(
+                    #key_default,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                );
+            } else if key.starts_with(#tag) {
+                #bind.insert(
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                );
             }
-        } else if let Type::VecTuple(arg1, arg2) = ty {
-            let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key == #tag {
-                    #bind.push((
-                        #key_default,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    ));
-                } else if key.starts_with(#tag) {
-                    #bind.push((
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    ));
-                }
+        }
+    } else if let Type::VecTuple(arg1, arg2) = ty {
+        let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key == #tag {
+                #bind.push((
+                    #key_default,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                ));
+            } else if key.starts_with(#tag) {
+                #bind.push((
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                ));
             }
-        } else if let Type::OptionVecTuple(arg1, arg2) = ty {
-            let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
-            let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
-            let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
-            quote! {
-                if key == #tag {
-                    #bind.push((
-                        #key_default,
-                        // key[#len..].to_string(),
-                        #value_default,
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    ));
-                } else if key.starts_with(#tag) {
-                    #bind.push((
-                        #key_other,
-                        // key[#len..].to_string(),
-                        #value_default
-                        // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
-                    ));
-                }
+        }
+    } else if let Type::OptionVecTuple(arg1, arg2) = ty {
+        let key_default = map_from(&Type::parse(arg1.clone()), quote! { "" });
+        let key_other = map_from(&Type::parse(arg1.clone()), quote! { &key[#len..] });
+        let value_default = map_from(&Type::parse(arg2.clone()), quote! { __value });
+        quote! {
+            if key == #tag {
+                #bind.push((
+                    #key_default,
+                    // key[#len..].to_string(),
+                    #value_default,
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                ));
+            } else if key.starts_with(#tag) {
+                #bind.push((
+                    #key_other,
+                    // key[#len..].to_string(),
+                    #value_default
+                    // __value.parse::<#arg2>().map_err(|e| XmlError::FromStr(e.into()))?
+                ));
             }
-        } else {
-            quote! {}
         }
+    } else {
+        quote! {}
     }
 }
 
 fn read_children(
-    tags: &[LitStr],
+    pattern: TokenStream,
     bind: &Ident,
     name: &TokenStream,
     ty: &Type,
+    nillable: bool,
+    positioned: bool,
     ele_name: &TokenStream,
 ) -> TokenStream {
+    let default = lenient_default(ty);
+
+    // `__position` counts the element being read already
+    let push = if positioned {
+        quote! {
+            #bind.push(hard_xml::Positioned {
+                position: __position - 1,
+                value: __value,
+            })
+        }
+    } else {
+        quote! { #bind.push(__value) }
+    };
+
     let from_reader = match &ty {
         Type::VecT(ty) => quote! {
-            #bind.push(<#ty as hard_xml::XmlRead>::from_reader(reader)?);
+            match <#ty as hard_xml::XmlRead>::from_reader(reader) {
+                Ok(__value) => #push,
+                Err(err) => reader.recover_element(err, __depth)?,
+            }
         },
         Type::OptionT(ty) | Type::T(ty) => quote! {
-            #bind = Some(<#ty as hard_xml::XmlRead>::from_reader(reader)?);
+            match <#ty as hard_xml::XmlRead>::from_reader(reader) {
+                Ok(__value) => #bind = Some(__value),
+                Err(err) => {
+                    reader.recover_element(err, __depth)?;
+                    #bind = #default;
+                }
+            }
         },
         _ => panic!("`child` attribute only supports Vec<T>, Option<T> and T."),
     };
 
+    // an element with `xsi:nil="true"` is read as `None`
+    let from_reader = if nillable {
+        quote! {
+            if reader.peek_nil() {
+                // skip the start tag
+                reader.next();
+                reader.read_to_end(__tag)?;
+                #bind = None;
+            } else {
+                #from_reader
+            }
+        }
+    } else {
+        from_reader
+    };
+
+    let index = element_index(bind, ty);
+
     quote! {
-        #( #tags )|* => {
+        #pattern => {
             hard_xml::log_start_reading_field!(#ele_name, #name);
+            reader.enter_field(stringify!(#name), #index);
 
+            let __depth = reader.element_depth();
             #from_reader
 
+            reader.leave();
+            hard_xml::log_finish_reading_field!(#ele_name, #name);
+        }
+    }
+}
+
+fn read_any_attr(bind: &Ident, name: &TokenStream, ty: &Type, ele_name: &TokenStream) -> TokenStream {
+    let (key_ty, value_ty) = match ty {
+        Type::Map(key_ty, value_ty) | Type::VecTuple(key_ty, value_ty) => (key_ty, value_ty),
+        _ => panic!("`any_attr` attribute only supports HashMap<K, V> and Vec<(K, V)>."),
+    };
+    let key_from_str = from_str(&Type::parse(key_ty.clone()), &None);
+    let value_from_str = from_str(&Type::parse(value_ty.clone()), &None);
+
+    let store = if let Type::Map(..) = ty {
+        quote! { #bind.insert(__key, __value); }
+    } else {
+        quote! { #bind.push((__key, __value)); }
+    };
+
+    quote! {
+        hard_xml::log_start_reading_field!(#ele_name, #name);
+        reader.enter_field(stringify!(#name), None);
+        reader.enter_attribute(key);
+
+        let __res: hard_xml::XmlResult<_> = (|| {
+            let __key = {
+                let __value = std::borrow::Cow::Borrowed(key);
+                #key_from_str
+            };
+            let __value = #value_from_str;
+            Ok((__key, __value))
+        })();
+        match __res {
+            Ok((__key, __value)) => {
+                #store
+            }
+            Err(err) => reader.recover(err)?,
+        }
+
+        reader.leave();
+        reader.leave();
+        hard_xml::log_finish_reading_field!(#ele_name, #name);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_misc(
+    pattern: TokenStream,
+    value: TokenStream,
+    bind: &Ident,
+    name: &TokenStream,
+    ty: &Type,
+    with: &Option<ExprPath>,
+    positioned: bool,
+    ele_name: &TokenStream,
+) -> TokenStream {
+    let from_str = from_str(ty, with);
+
+    let from_str = if positioned {
+        quote! {
+            hard_xml::Positioned {
+                position: __position,
+                value: #from_str,
+            }
+        }
+    } else {
+        from_str
+    };
+
+    // an `Option` keeps the first one
+    let store = if ty.is_vec() {
+        quote! { #bind.push(__value) }
+    } else {
+        quote! {
+            if #bind.is_none() {
+                #bind = Some(__value);
+            }
+        }
+    };
+
+    quote! {
+        #pattern => {
+            hard_xml::log_start_reading_field!(#ele_name, #name);
+            reader.enter_field(stringify!(#name), None);
+
+            let __value = std::borrow::Cow::Borrowed(#value);
+            let __res: hard_xml::XmlResult<_> = (|| {
+                let __value = #from_str;
+                Ok(__value)
+            })();
+            match __res {
+                Ok(__value) => #store,
+                Err(err) => reader.recover(err)?,
+            }
+
+            reader.leave();
             hard_xml::log_finish_reading_field!(#ele_name, #name);
         }
     }
@@ -486,6 +900,7 @@ fn read_children(
 
 fn read_flatten_text(
     tag: &LitStr,
+    ns: &Option<LitStr>,
     bind: &Ident,
     name: &TokenStream,
     ty: &Type,
@@ -494,33 +909,108 @@ fn read_flatten_text(
 ) -> TokenStream {
     let from_str = from_str(ty, with);
 
+    let default = lenient_default(ty);
+
     let read_text = if ty.is_vec() {
         quote! {
-            let __value = reader.read_text(#tag)?;
-            #bind.push(#from_str);
+            match reader.read_text(__tag).and_then(|__value| {
+                let __value = #from_str;
+                Ok(__value)
+            }) {
+                Ok(__value) => #bind.push(__value),
+                Err(err) => reader.recover_element(err, __depth)?,
+            }
         }
     } else {
         quote! {
-            let __value = reader.read_text(#tag)?;
-            #bind = Some(#from_str);
+            match reader.read_text(__tag).and_then(|__value| {
+                let __value = #from_str;
+                Ok(__value)
+            }) {
+                Ok(__value) => #bind = Some(__value),
+                Err(err) => {
+                    reader.recover_element(err, __depth)?;
+                    #bind = #default;
+                }
+            }
         }
     };
 
+    let index = element_index(bind, ty);
+
+    let pattern = element_pattern(std::slice::from_ref(tag), ns);
+
     quote! {
-        #tag => {
+        #pattern => {
+            let __depth = reader.element_depth();
             // skip element start
             reader.next();
 
             hard_xml::log_start_reading_field!(#ele_name, #name);
+            reader.enter_field(stringify!(#name), #index);
+            reader.enter_text(__tag);
 
             #read_text
 
+            reader.leave();
+            reader.leave();
             hard_xml::log_finish_reading_field!(#ele_name, #name);
         }
     }
 }
 
-fn from_str(ty: &Type, with: &Option<ExprPath>) -> TokenStream {
+/// Match arm pattern for an attribute found by `find_attribute`.
+pub(super) fn attr_pattern(tag: &LitStr, ns: &Option<LitStr>) -> TokenStream {
+    if let Some(ns) = ns {
+        quote!(__key if reader.resolve_attribute(__key) == (Some(#ns), #tag))
+    } else {
+        quote!(#tag)
+    }
+}
+
+/// Match arm pattern for a child element found by `find_element_start`.
+pub(crate) fn element_pattern(tags: &[LitStr], ns: &Option<LitStr>) -> TokenStream {
+    if let Some(ns) = ns {
+        quote! {
+            __tag if matches!(reader.resolve_element(__tag), (Some(#ns), #( #tags )|*))
+        }
+    } else {
+        quote!( #( #tags )|* )
+    }
+}
+
+/// The position of the element among those read into the same field, counting
+/// the ones which failed to parse in lenient mode.
+fn element_index(bind: &Ident, ty: &Type) -> TokenStream {
+    if ty.is_vec() {
+        let count = format_ident!("{}_count", bind);
+        quote! {
+            {
+                #count += 1;
+                Some(#count)
+            }
+        }
+    } else {
+        quote!(None)
+    }
+}
+
+/// `Some(Default::default())` if the type of a required field implements
+/// `Default`, `None` otherwise.
+fn lenient_default(ty: &Type) -> TokenStream {
+    match ty {
+        Type::CowStr | Type::Bool => quote!(Some(Default::default())),
+        Type::T(ty) => quote! {
+            {
+                use hard_xml::lenient::{NoDefault as _, WithDefault as _};
+                (&hard_xml::lenient::DefaultOf::<#ty>::new()).get()
+            }
+        },
+        _ => quote!(None),
+    }
+}
+
+pub(crate) fn from_str(ty: &Type, with: &Option<ExprPath>) -> TokenStream {
     if let Some(with_mod) = with {
         return quote! {
             {
@@ -1,6 +1,6 @@
 use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote};
-use syn::{spanned::Spanned, *};
+use syn::{ext::IdentExt, spanned::Spanned, *};
 
 use crate::{
     attrs,
@@ -14,13 +14,27 @@ type Result<T, E = Vec<syn::Error>> = std::result::Result<T, E>;
 pub enum Element {
     Struct { name: Ident, fields: Fields },
     Enum { name: Ident, variants: Vec<Fields> },
+    /// Fields without an element of their own, flattened into their parent
+    ///
+    /// ```ignore
+    /// #[xml(fields)]
+    /// struct Foo {
+    ///     $( $field )*
+    /// }
+    /// ```
+    Fields {
+        name: Ident,
+        fields: Vec<Field>,
+        // the declared types of `fields`
+        types: Vec<syn::Type>,
+    },
 }
 
 pub enum Fields {
     /// Named fields of a struct or struct variant
     ///
     /// ```ignore
-    /// #[xml(tag = "$tag")]
+    /// #[xml(tag = "$tag", $( ns = "$ns", $( prefix = "$prefix", )? )? $( default_ns = "$default_ns" )?)]
     /// struct $name {
     ///     $( $fields )*
     /// }
@@ -28,7 +42,7 @@ pub enum Fields {
     ///
     /// ```ignore
     /// enum Foo {
-    ///     #[xml(tag = "$tag")]
+    ///     #[xml(tag = "$tag", $( ns = "$ns", $( prefix = "$prefix", )? )? $( default_ns = "$default_ns" )?)]
     ///     $name {
     ///         $( $fields )*
     ///     }
@@ -36,28 +50,44 @@ pub enum Fields {
     /// ```
     Named {
         tag: LitStr,
+        ns: Option<LitStr>,
+        prefix: Option<LitStr>,
+        default_ns: Option<LitStr>,
         strict: StrictMode,
+        declaration: bool,
+        discriminator: Option<Discriminator>,
         name: Ident,
         fields: Vec<Field>,
     },
     /// Newtype struct or newtype variant
     ///
     /// ```ignore
-    /// #[xml($(tag = "$tags",)*)]
+    /// #[xml($(tag = "$tags",)* $( ns = "$ns" )?)]
     /// struct $name($ty);
     /// ```
     ///
     /// ```ignore
     /// enum Foo {
-    ///     #[xml($(tag = "$tags",)*)]
+    ///     #[xml($(tag = "$tags",)* $( ns = "$ns" )?)]
     ///     $name($ty)
     /// }
     /// ```
     Newtype {
         tags: Vec<LitStr>,
+        ns: Option<LitStr>,
+        declaration: bool,
         name: Ident,
         ty: Box<Type>,
     },
+    /// Text variant, holding the text between the elements of mixed content
+    ///
+    /// ```ignore
+    /// enum Foo {
+    ///     #[xml(text)]
+    ///     $name($ty)
+    /// }
+    /// ```
+    Text { name: Ident, ty: Type },
 }
 
 pub enum Field {
@@ -65,7 +95,7 @@ pub enum Field {
     ///
     /// ```ignore
     /// struct Foo {
-    ///     #[xml(attr = "$tag", $default)]
+    ///     #[xml(attr = "$tag", $( ns = "$ns", )? $default $( skip_serializing_if = "$skip_if" )?)]
     ///     $name: $ty,
     /// }
     /// ```
@@ -75,13 +105,15 @@ pub enum Field {
         ty: Type,
         with: Option<ExprPath>,
         tag: LitStr,
-        default: bool,
+        ns: Option<LitStr>,
+        default: Option<FieldDefault>,
+        skip_if: Option<SkipIf>,
     },
     /// Child(ren) Field
     ///
     /// ```ignore
     /// struct Foo {
-    ///     #[xml(child = "$tag", child = "$tag", $default)]
+    ///     #[xml(child = "$tag", child = "$tag", $( ns = "$ns", )? $default $( skip_serializing_if = "$skip_if" )?)]
     ///     $name: $ty,
     /// }
     /// ```
@@ -90,8 +122,11 @@ pub enum Field {
         bind: Ident,
         ty: Type,
         with: Option<ExprPath>,
-        default: bool,
+        default: Option<FieldDefault>,
         tags: Vec<LitStr>,
+        ns: Option<LitStr>,
+        skip_if: Option<SkipIf>,
+        nillable: Option<Nillable>,
     },
     /// Text Field
     ///
@@ -106,13 +141,13 @@ pub enum Field {
         bind: Ident,
         ty: Type,
         with: Option<ExprPath>,
-        is_cdata: bool,
+        cdata: Option<Cdata>,
     },
     /// Flatten Text
     ///
     /// ```ignore
     /// struct Foo {
-    ///     #[xml(flatten_text = "$tag", $default)]
+    ///     #[xml(flatten_text = "$tag", $( ns = "$ns", )? $default $( skip_serializing_if = "$skip_if" )?)]
     ///     $name: $ty,
     /// }
     /// ```
@@ -121,9 +156,83 @@ pub enum Field {
         bind: Ident,
         ty: Type,
         with: Option<ExprPath>,
-        default: bool,
+        default: Option<FieldDefault>,
         tag: LitStr,
-        is_cdata: bool,
+        ns: Option<LitStr>,
+        cdata: Option<Cdata>,
+        skip_if: Option<SkipIf>,
+    },
+    /// Mixed Content Field
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(mixed)]
+    ///     $name: Vec<$ty>,
+    /// }
+    /// ```
+    Mixed {
+        name: TokenStream,
+        bind: Ident,
+        ty: Type,
+    },
+    /// Comment Field
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(comment)]
+    ///     $name: $ty,
+    /// }
+    /// ```
+    Comment {
+        name: TokenStream,
+        bind: Ident,
+        ty: Type,
+        with: Option<ExprPath>,
+        // the items are `Positioned`, `ty` names the type of their value
+        positioned: bool,
+    },
+    /// Processing Instruction Field
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(pi = "$target")]
+    ///     $name: $ty,
+    /// }
+    /// ```
+    ProcessingInstruction {
+        name: TokenStream,
+        bind: Ident,
+        ty: Type,
+        with: Option<ExprPath>,
+        target: LitStr,
+        positioned: bool,
+    },
+    /// Catch-all Attribute Field
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(any_attr)]
+    ///     $name: $ty,
+    /// }
+    /// ```
+    AnyAttribute {
+        name: TokenStream,
+        bind: Ident,
+        ty: Type,
+    },
+    /// Catch-all Element Field
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(any_element)]
+    ///     $name: Vec<$ty>,
+    /// }
+    /// ```
+    AnyElement {
+        name: TokenStream,
+        bind: Ident,
+        ty: Type,
+        positioned: bool,
     },
     /// Prefix Field
     ///
@@ -153,6 +262,28 @@ pub enum Field {
         ty: Type,
         tag: LitStr,
     },
+    /// Skipped Field, neither read nor written
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(skip)]
+    ///     $name: $ty,
+    /// }
+    /// ```
+    Skip { name: TokenStream, bind: Ident },
+    /// Flattened Field, whose attributes and children belong to this element
+    ///
+    /// ```ignore
+    /// struct Foo {
+    ///     #[xml(flatten)]
+    ///     $name: $ty,
+    /// }
+    /// ```
+    Flatten {
+        name: TokenStream,
+        bind: Ident,
+        ty: syn::Type,
+    },
 }
 
 pub enum Type {
@@ -189,20 +320,60 @@ impl Element {
         let mut ctx = Context::default();
 
         let element = match input.data {
-            Data::Struct(data) => Element::Struct {
-                name: input.ident.clone(),
-                fields: Fields::parse(&mut ctx, data.fields, input.attrs, input.ident),
-            },
-            Data::Enum(data) => Element::Enum {
-                name: input.ident,
-                variants: data
+            Data::Struct(data) if attrs::Container::is_fields(&input.attrs) => {
+                Element::parse_fields(&mut ctx, data.fields, input.attrs, input.ident)
+            }
+            Data::Struct(data) => {
+                let fields =
+                    Fields::parse(&mut ctx, data.fields, input.attrs, input.ident.clone(), None);
+                if let Fields::Text { name, .. } = &fields {
+                    ctx.push_spanned_error(name, "`text` is only supported on enum variants");
+                }
+                Element::Struct {
+                    name: input.ident,
+                    fields,
+                }
+            }
+            Data::Enum(data) => {
+                let discriminated = Discriminated::parse(&mut ctx, input.attrs, &input.ident);
+                let variants: Vec<_> = data
                     .variants
                     .into_iter()
                     .map(|variant| {
-                        Fields::parse(&mut ctx, variant.fields, variant.attrs, variant.ident)
+                        Fields::parse(
+                            &mut ctx,
+                            variant.fields,
+                            variant.attrs,
+                            variant.ident,
+                            discriminated.as_ref(),
+                        )
                     })
-                    .collect(),
-            },
+                    .collect();
+                let mut values = Vec::new();
+                for variant in &variants {
+                    if let Fields::Named {
+                        discriminator: Some(Discriminator { value, .. }),
+                        ..
+                    } = variant
+                    {
+                        if values.contains(&value.value()) {
+                            ctx.push_spanned_error(value, "duplicate `value`");
+                        }
+                        values.push(value.value());
+                    }
+                }
+                let mut text_variants = variants.iter().filter_map(|variant| match variant {
+                    Fields::Text { name, .. } => Some(name),
+                    _ => None,
+                });
+                if let (Some(_), Some(name)) = (text_variants.next(), text_variants.next()) {
+                    ctx.push_spanned_error(name, "duplicate `text` variant");
+                }
+                Element::Enum {
+                    name: input.ident,
+                    variants,
+                }
+            }
             Data::Union(_) => {
                 return Err(vec![syn::Error::new_spanned(
                     input,
@@ -213,6 +384,70 @@ impl Element {
 
         ctx.check().map(|_| element)
     }
+
+    fn parse_fields(
+        ctx: &mut Context,
+        fields: syn::Fields,
+        attrs: Vec<Attribute>,
+        name: Ident,
+    ) -> Element {
+        let attrs::Container {
+            tags,
+            ns,
+            prefix,
+            default_ns,
+            strict_mode,
+            declaration,
+            text,
+            rename_all,
+            discriminator,
+            value,
+            value_ns,
+            ..
+        } = attrs::Container::parse(ctx, attrs);
+
+        let lits = tags.iter().chain(&ns).chain(&prefix).chain(&default_ns);
+        for lit in lits.chain(&discriminator).chain(&value).chain(&value_ns) {
+            ctx.push_spanned_error(lit, "`fields` structs only support `rename_all`");
+        }
+        if !strict_mode.is_empty() || declaration || text {
+            ctx.push_spanned_error(&name, "`fields` structs only support `rename_all`");
+        }
+
+        if !matches!(fields, syn::Fields::Named(_)) {
+            ctx.push_spanned_error(&name, "`fields` is only supported on structs with named fields");
+        }
+
+        let (fields, types) = fields
+            .into_iter()
+            .filter_map(|field| {
+                let ty = field.ty.clone();
+                let name = field.ident.clone()?;
+                let bind = format_ident!("__self_{}", name);
+
+                let field = Field::parse(ctx, quote!(#name), bind, field, rename_all)?;
+                match field {
+                    Field::Attribute { .. }
+                    | Field::Child { .. }
+                    | Field::FlattenText { .. }
+                    | Field::Skip { .. } => Some((field, ty)),
+                    _ => {
+                        ctx.push_spanned_error(
+                            &name,
+                            "`fields` structs only support `attr`, `child`, `flatten_text` and `skip` fields",
+                        );
+                        None
+                    }
+                }
+            })
+            .unzip();
+
+        Element::Fields {
+            name,
+            fields,
+            types,
+        }
+    }
 }
 
 impl Fields {
@@ -221,13 +456,84 @@ impl Fields {
         mut fields: syn::Fields,
         attrs: Vec<Attribute>,
         name: Ident,
+        discriminated: Option<&Discriminated>,
     ) -> Fields {
         // Finding `tag` attribute
         let attrs::Container {
             mut tags,
+            mut ns,
+            prefix,
+            default_ns,
             strict_mode,
+            declaration,
+            text,
+            rename_all,
+            discriminator,
+            discriminator_ns: _,
+            value,
+            value_ns,
+            fields: fields_only,
         } = attrs::Container::parse(ctx, attrs);
 
+        if let Some(discriminator) = discriminator {
+            ctx.push_spanned_error(discriminator, "`discriminator` is only supported on enums");
+        }
+
+        if fields_only {
+            ctx.push_spanned_error(&name, "`fields` is only supported on structs");
+        }
+
+        // the variants of a discriminated enum share its tag
+        let discriminator = match discriminated {
+            Some(discriminated) => {
+                if text {
+                    ctx.push_spanned_error(&name, "`text` variants can't be discriminated");
+                }
+                for tag in &tags {
+                    ctx.push_spanned_error(
+                        tag,
+                        "the variants of a discriminated enum take its tag",
+                    );
+                }
+                tags = vec![discriminated.tag.clone()];
+                ns = discriminated.ns.clone();
+                let value = value.unwrap_or_else(|| LitStr::new(&name.to_string(), name.span()));
+                let (value_prefix, value_local) = match value.value().split_once(':') {
+                    Some((prefix, local)) => (
+                        Some(LitStr::new(prefix, value.span())),
+                        LitStr::new(local, value.span()),
+                    ),
+                    None => (None, value.clone()),
+                };
+                if let (Some(value_ns), None) = (&value_ns, &value_prefix) {
+                    ctx.push_spanned_error(value_ns, "`value_ns` attribute requires a prefixed `value`");
+                }
+                Some(Discriminator {
+                    attr: discriminated.attr.clone(),
+                    ns: discriminated.attr_ns.clone(),
+                    prefix: discriminated.attr_prefix.clone(),
+                    local: discriminated.attr_local.clone(),
+                    value,
+                    value_ns,
+                    value_prefix,
+                    value_local,
+                })
+            }
+            None => {
+                if let Some(value) = value {
+                    ctx.push_spanned_error(value, "`value` requires a `discriminator` on the enum");
+                }
+                if let Some(value_ns) = value_ns {
+                    ctx.push_spanned_error(value_ns, "`value_ns` requires a `discriminator` on the enum");
+                }
+                None
+            }
+        };
+
+        if text {
+            return Fields::parse_text(ctx, fields, tags, name);
+        }
+
         if tags.is_empty() {
             ctx.push_spanned_error(&name, "missing `tag` attribute");
         }
@@ -238,7 +544,26 @@ impl Fields {
                 let ty = fields.unnamed.pop().unwrap().into_value().ty;
                 let ty = Box::new(Type::parse(ty));
 
-                return Fields::Newtype { tags, name, ty };
+                for lit in prefix.iter().chain(&default_ns) {
+                    ctx.push_spanned_error(
+                        lit,
+                        "`prefix` and `default_ns` attributes are not supported on newtypes",
+                    );
+                }
+                if discriminator.is_some() {
+                    ctx.push_spanned_error(
+                        &name,
+                        "discriminated enums don't support newtype variants",
+                    );
+                }
+
+                return Fields::Newtype {
+                    tags,
+                    ns,
+                    declaration,
+                    name,
+                    ty,
+                };
             }
         }
 
@@ -253,7 +578,7 @@ impl Fields {
                     let index = syn::Index::from(index);
                     let bind = format_ident!("__self_{}", index);
 
-                    Field::parse(ctx, quote!(#index), bind, field)
+                    Field::parse(ctx, quote!(#index), bind, field, rename_all)
                 })
                 .collect(),
 
@@ -263,7 +588,7 @@ impl Fields {
                     let name = field.ident.clone().unwrap();
                     let bind = format_ident!("__self_{}", name);
 
-                    Field::parse(ctx, quote!(#name), bind, field)
+                    Field::parse(ctx, quote!(#name), bind, field, rename_all)
                 })
                 .collect(),
         };
@@ -275,13 +600,75 @@ impl Fields {
             LitStr::new("", Span::call_site())
         };
 
+        // the text between child elements can only go to one field
+        let count = |f: fn(&Field) -> bool| fields.iter().filter(|field| f(field)).count();
+        let mixed = count(|field| matches!(field, Field::Mixed { .. }));
+        let text = count(|field| matches!(field, Field::Text { .. }));
+        if mixed > 1 || (mixed > 0 && text > 0) {
+            ctx.push_spanned_error(
+                &name,
+                "a `mixed` field can't be combined with other `mixed` or `text` fields",
+            );
+        }
+
+        let comment = count(|field| matches!(field, Field::Comment { .. }));
+        let pi = count(|field| matches!(field, Field::ProcessingInstruction { .. }));
+        if comment > 1 {
+            ctx.push_spanned_error(&name, "duplicate `comment` field");
+        }
+        if (comment > 0 || pi > 0) && (mixed > 0 || text > 0) {
+            ctx.push_spanned_error(
+                &name,
+                "`comment` and `pi` fields can't be combined with `mixed` or `text` fields",
+            );
+        }
+
+        let any_attr = count(|field| matches!(field, Field::AnyAttribute { .. }));
+        let any_element = count(|field| matches!(field, Field::AnyElement { .. }));
+        if any_attr > 1 {
+            ctx.push_spanned_error(&name, "duplicate `any_attr` field");
+        }
+        if any_element > 1 {
+            ctx.push_spanned_error(&name, "duplicate `any_element` field");
+        }
+        if any_element > 0 && text > 0 {
+            ctx.push_spanned_error(&name, "an `any_element` field can't be combined with a `text` field");
+        }
+
         Fields::Named {
             tag,
+            ns,
+            prefix,
+            default_ns,
             strict: strict_mode,
+            declaration,
+            discriminator,
             name,
             fields,
         }
     }
+
+    fn parse_text(ctx: &mut Context, fields: syn::Fields, tags: Vec<LitStr>, name: Ident) -> Fields {
+        for tag in &tags {
+            ctx.push_spanned_error(tag, "`text` variants don't have a tag");
+        }
+
+        let ty = match fields {
+            syn::Fields::Unnamed(mut fields) if fields.unnamed.len() == 1 => {
+                Type::parse(fields.unnamed.pop().unwrap().into_value().ty)
+            }
+            _ => {
+                ctx.push_spanned_error(&name, "`text` is only supported on newtype variants");
+                Type::CowStr
+            }
+        };
+
+        if !matches!(ty, Type::CowStr | Type::T(_)) {
+            ctx.push_spanned_error(&name, "`text` variants only support Cow<str> and T");
+        }
+
+        Fields::Text { name, ty }
+    }
 }
 
 fn is_new_type(fields: &FieldsUnnamed) -> bool {
@@ -298,34 +685,74 @@ impl Field {
         name: TokenStream,
         bind: Ident,
         field: syn::Field,
+        rename_all: Option<RenameRule>,
     ) -> Option<Field> {
         let span = field.span();
 
-        let mut attrs = attrs::Field::parse(ctx, field.attrs);
+        // the tag of a bare `attr` or `flatten_text`
+        let tag = field.ident.as_ref().map(|ident| {
+            let name = ident.unraw().to_string();
+            let name = match rename_all {
+                Some(rule) => rule.apply(&name),
+                None => name,
+            };
+            LitStr::new(&name, ident.span())
+        });
+
+        let mut attrs = attrs::Field::parse(ctx, field.attrs, tag);
         let with = attrs.with.take();
+        let ns = attrs.ns.take();
+
+        // `skip_default` leaves out the values `default` would fill in
+        let skip_if = if attrs.skip_default {
+            match &attrs.default {
+                Some(default) => Some(SkipIf::Default(default.clone())),
+                None => {
+                    ctx.push_new_error(span, "`skip_default` attribute requires `default`");
+                    None
+                }
+            }
+        } else {
+            attrs.skip_serializing_if.take().map(SkipIf::Path)
+        };
+
         let kind = FieldKind::from_attributes(ctx, attrs, span)?;
 
         let span = field.ty.span();
-        let ty = Type::parse(field.ty);
+        let (ty, positioned) = match kind {
+            FieldKind::Comment | FieldKind::ProcessingInstruction(_) | FieldKind::AnyElement => {
+                peel_positioned(field.ty)
+            }
+            _ => (field.ty, false),
+        };
+        let ty = Type::parse(ty);
 
-        kind.into_field(ctx, name, bind, ty, with, span)
+        kind.into_field(ctx, name, bind, ty, with, ns, skip_if, positioned, span)
     }
 }
 
 pub(crate) enum FieldKind {
-    Attribute(LitStr, bool),
-    Child(Vec<LitStr>, bool),
+    Attribute(LitStr, Option<FieldDefault>),
+    Child(Vec<LitStr>, Option<FieldDefault>, Option<Nillable>),
     FlattenText {
         tag: LitStr,
-        cdata: bool,
-        default: bool,
+        cdata: Option<Cdata>,
+        default: Option<FieldDefault>,
     },
-    Text(bool),
+    Text(Option<Cdata>),
+    Mixed,
+    Comment,
+    ProcessingInstruction(LitStr),
+    AnyAttribute,
+    AnyElement,
+    Skip,
+    Flatten,
     Prefix(LitStr),
     Startswith(LitStr),
 }
 
 impl FieldKind {
+    #[allow(clippy::too_many_arguments)]
     fn into_field(
         self,
         ctx: &mut Context,
@@ -333,24 +760,40 @@ impl FieldKind {
         bind: Ident,
         ty: Type,
         with: Option<ExprPath>,
+        ns: Option<LitStr>,
+        skip_if: Option<SkipIf>,
+        positioned: bool,
         span: Span,
     ) -> Option<Field> {
-        self.verify_type(ctx, &ty, span).then(|| match self {
+        if !self.verify_type(ctx, &ty, span)
+            || !self.verify_ns(ctx, &ns)
+            || !self.verify_default(ctx, &ty, span)
+            || !self.verify_skip_if(ctx, &skip_if, span)
+        {
+            return None;
+        }
+
+        Some(match self {
             FieldKind::Attribute(tag, default) => Field::Attribute {
                 name,
                 bind,
                 ty,
                 with,
                 tag,
+                ns,
                 default,
+                skip_if,
             },
-            FieldKind::Child(tags, default) => Field::Child {
+            FieldKind::Child(tags, default, nillable) => Field::Child {
                 name,
                 bind,
                 ty,
                 with,
                 default,
                 tags,
+                ns,
+                skip_if,
+                nillable,
             },
             FieldKind::FlattenText {
                 tag,
@@ -363,14 +806,44 @@ impl FieldKind {
                 with,
                 default,
                 tag,
-                is_cdata: cdata,
+                ns,
+                cdata,
+                skip_if,
             },
             FieldKind::Text(cdata) => Field::Text {
                 name,
                 bind,
                 ty,
                 with,
-                is_cdata: cdata,
+                cdata,
+            },
+            FieldKind::Mixed => Field::Mixed { name, bind, ty },
+            FieldKind::Comment => Field::Comment {
+                name,
+                bind,
+                ty,
+                with,
+                positioned,
+            },
+            FieldKind::ProcessingInstruction(target) => Field::ProcessingInstruction {
+                name,
+                bind,
+                ty,
+                with,
+                target,
+                positioned,
+            },
+            FieldKind::AnyAttribute => Field::AnyAttribute { name, bind, ty },
+            FieldKind::AnyElement => Field::AnyElement {
+                name,
+                bind,
+                ty,
+                positioned,
+            },
+            FieldKind::Skip => Field::Skip { name, bind },
+            FieldKind::Flatten => match ty {
+                Type::T(ty) => Field::Flatten { name, bind, ty },
+                _ => unreachable!(),
             },
             FieldKind::Prefix(tag) => Field::Prefix {
                 name,
@@ -393,46 +866,152 @@ impl FieldKind {
             child_tags,
             flatten_text_tag,
             is_text,
+            is_mixed,
+            is_comment,
+            pi_target,
+            is_any_attr,
+            is_any_element,
+            is_skip,
+            is_flatten,
+            nillable,
             prefix,
             ..
         } = attrs;
 
-        match (attr_tag, child_tags.as_slice(), flatten_text_tag, is_text, prefix) {
-            (Some(tag), &[], None, false, None) => Some(Self::Attribute(tag, attrs.default)),
-            (None, &[_, ..], None, false, None) => Some(Self::Child(child_tags, attrs.default)),
-            (None, &[], Some(tag), false, None) => Some(Self::FlattenText {
+        if nillable.is_some() && child_tags.is_empty() {
+            ctx.push_new_error(span, "`nillable` attribute is only supported with `child`");
+            return None;
+        }
+
+        // the kinds which don't take any other attribute
+        let mut others = Vec::new();
+        if is_comment {
+            others.push(Self::Comment);
+        }
+        if let Some(target) = pi_target {
+            others.push(Self::ProcessingInstruction(target));
+        }
+        if is_any_attr {
+            others.push(Self::AnyAttribute);
+        }
+        if is_any_element {
+            others.push(Self::AnyElement);
+        }
+        if is_skip {
+            others.push(Self::Skip);
+        }
+        if is_flatten {
+            others.push(Self::Flatten);
+        }
+        if others.len() > 1 {
+            ctx.push_new_error(
+                span,
+                "the attributes `comment`, `pi`, `any_attr`, `any_element`, `skip` and `flatten` are mutually exclusive",
+            );
+            return None;
+        }
+        let other = others.pop();
+
+        match (attr_tag, child_tags.as_slice(), flatten_text_tag, is_text, is_mixed, other, prefix) {
+            (Some(tag), &[], None, false, false, None, None) => Some(Self::Attribute(tag, attrs.default.clone())),
+            (None, &[_, ..], None, false, false, None, None) => Some(Self::Child(child_tags, attrs.default.clone(), nillable)),
+            (None, &[], Some(tag), false, false, None, None) => Some(Self::FlattenText {
                 tag,
-                cdata: attrs.is_cdata,
-                default: attrs.default,
+                cdata: attrs.cdata,
+                default: attrs.default.clone(),
             }),
-            (None, &[], None, true, None) => Some(Self::Text(attrs.is_cdata)),
-            (Some(tag), &[], None, false, Some(Prefix::Prefix)) => Some(Self::Prefix(tag)),
-            (Some(tag), &[], None, false, Some(Prefix::Startswith)) => Some(Self::Startswith(tag)),
+            (None, &[], None, true, false, None, None) => Some(Self::Text(attrs.cdata)),
+            (None, &[], None, false, true, None, None) => Some(Self::Mixed),
+            (None, &[], None, false, false, Some(other), None) => Some(other),
+            (Some(tag), &[], None, false, false, None, Some(Prefix::Prefix)) => Some(Self::Prefix(tag)),
+            (Some(tag), &[], None, false, false, None, Some(Prefix::Startswith)) => Some(Self::Startswith(tag)),
 
-            (None, &[], None, false, None) => {
+            (None, &[], None, false, false, None, None) => {
                 ctx.push_new_error(
                     span,
-                    "field should have one of `attr`, `child`, `prefix`, `text` or `flatten_text` attribute",
+                    "field should have one of `attr`, `child`, `prefix`, `text`, `mixed`, `comment`, `pi`, `any_attr`, `any_element`, `skip`, `flatten` or `flatten_text` attribute",
                 );
                 None
             }
             _ => {
                 ctx.push_new_error(
                     span,
-                    "the attributes `attr`, `child`, `text` and `flatten_text` are mutually exclusive",
+                    "the attributes `attr`, `child`, `text`, `mixed`, `comment`, `pi`, `any_attr`, `any_element`, `skip`, `flatten` and `flatten_text` are mutually exclusive",
                 );
                 None
             }
         }
     }
 
+    fn verify_ns(&self, ctx: &mut Context, ns: &Option<LitStr>) -> bool {
+        match (self, ns) {
+            (
+                FieldKind::Attribute(..) | FieldKind::Child(..) | FieldKind::FlattenText { .. },
+                _,
+            )
+            | (_, None) => true,
+            (_, Some(ns)) => {
+                ctx.push_spanned_error(
+                    ns,
+                    "`ns` attribute is only supported with `attr`, `child` and `flatten_text`",
+                );
+                false
+            }
+        }
+    }
+
+    fn verify_default(&self, ctx: &mut Context, ty: &Type, span: Span) -> bool {
+        let default = match self {
+            FieldKind::Attribute(_, default)
+            | FieldKind::Child(_, default, _)
+            | FieldKind::FlattenText { default, .. } => default,
+            _ => return true,
+        };
+
+        match default {
+            Some(FieldDefault::Path(_) | FieldDefault::Str(_)) if ty.is_vec() || ty.is_option() => {
+                ctx.push_new_error(
+                    span,
+                    "`default = \"\"` and `default_str` attributes don't support Vec and Option",
+                );
+                false
+            }
+            Some(FieldDefault::Str(lit)) if matches!(self, FieldKind::Child(..)) => {
+                ctx.push_spanned_error(lit, "`default_str` attribute isn't supported with `child`");
+                false
+            }
+            _ => true,
+        }
+    }
+
+    fn verify_skip_if(&self, ctx: &mut Context, skip_if: &Option<SkipIf>, span: Span) -> bool {
+        match (self, skip_if) {
+            (
+                FieldKind::Attribute(..) | FieldKind::Child(..) | FieldKind::FlattenText { .. },
+                _,
+            )
+            | (_, None) => true,
+            (_, Some(_)) => {
+                ctx.push_new_error(
+                    span,
+                    "`skip_serializing_if` and `skip_default` attributes are only supported with `attr`, `child` and `flatten_text`",
+                );
+                false
+            }
+        }
+    }
+
     fn verify_type(&self, ctx: &mut Context, ty: &Type, span: Span) -> bool {
         match self {
             FieldKind::Attribute(_, _) if ty.is_vec() => {
                 ctx.push_new_error(span, "`attr` attribute doesn't support Vec");
                 false
             }
-            FieldKind::Child(_, _)
+            FieldKind::Child(_, _, Some(_)) if !matches!(ty, Type::OptionT(_)) => {
+                ctx.push_new_error(span, "`nillable` attribute only supports Option<T>");
+                false
+            }
+            FieldKind::Child(..)
                 if !matches!(ty, Type::OptionT(_) | Type::T(_) | Type::VecT(_)) =>
             {
                 ctx.push_new_error(
@@ -445,12 +1024,75 @@ impl FieldKind {
                 ctx.push_new_error(span, "`text` attribute doesn't support Vec");
                 false
             }
+            FieldKind::Mixed if !matches!(ty, Type::VecT(_)) => {
+                ctx.push_new_error(span, "`mixed` attribute only supports Vec<T>");
+                false
+            }
+            FieldKind::Comment | FieldKind::ProcessingInstruction(_)
+                if ty.is_map() || !(ty.is_vec() || ty.is_option()) =>
+            {
+                ctx.push_new_error(
+                    span,
+                    "`comment` and `pi` attributes only support Vec<T> and Option<T>",
+                );
+                false
+            }
+            FieldKind::AnyAttribute if !matches!(ty, Type::Map(..) | Type::VecTuple(..)) => {
+                ctx.push_new_error(
+                    span,
+                    "`any_attr` attribute only supports HashMap<K, V> and Vec<(K, V)>",
+                );
+                false
+            }
+            FieldKind::AnyElement if !matches!(ty, Type::VecT(_)) => {
+                ctx.push_new_error(span, "`any_element` attribute only supports Vec<T>");
+                false
+            }
+            FieldKind::Flatten if !matches!(ty, Type::T(_)) => {
+                ctx.push_new_error(span, "`flatten` attribute only supports T");
+                false
+            }
 
             _ => true,
         }
     }
 }
 
+/// Turns `Vec<Positioned<T>>` and `Option<Positioned<T>>` into `Vec<T>` and
+/// `Option<T>`, returning whether the items were `Positioned`.
+fn peel_positioned(mut ty: syn::Type) -> (syn::Type, bool) {
+    fn single_arg<'t>(ty: &'t mut syn::Type, names: &[&str]) -> Option<&'t mut syn::Type> {
+        let seg = match ty {
+            syn::Type::Path(ty) => ty.path.segments.last_mut()?,
+            _ => return None,
+        };
+        if !names.iter().any(|name| seg.ident == name) {
+            return None;
+        }
+        match &mut seg.arguments {
+            PathArguments::AngleBracketed(bracketed) if bracketed.args.len() == 1 => {
+                match &mut bracketed.args[0] {
+                    GenericArgument::Type(arg) => Some(arg),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    let item = match single_arg(&mut ty, &["Vec", "Option"]) {
+        Some(item) => item,
+        None => return (ty, false),
+    };
+    match single_arg(item, &["Positioned"]) {
+        Some(value) => {
+            *item = value.clone();
+            (ty, true)
+        }
+        None => (ty, false),
+    }
+}
+
 impl Type {
     pub fn is_option(&self) -> bool {
         matches!(
@@ -632,3 +1274,169 @@ bitflags! {
         const UNKNOWN_ELEMENT = 0b0000_0010;
     }
 }
+
+/// The tag and the attribute telling the variants of an enum apart, from
+/// `#[xml(tag = "$tag", $( ns = "$ns", )? discriminator = "$attr")]`
+pub struct Discriminated {
+    pub tag: LitStr,
+    pub ns: Option<LitStr>,
+    pub attr: LitStr,
+    pub attr_ns: Option<LitStr>,
+    pub attr_prefix: Option<LitStr>,
+    pub attr_local: LitStr,
+}
+
+impl Discriminated {
+    fn parse(ctx: &mut Context, attrs: Vec<Attribute>, name: &Ident) -> Option<Self> {
+        let attrs::Container {
+            mut tags,
+            ns,
+            discriminator,
+            discriminator_ns,
+            ..
+        } = attrs::Container::parse(ctx, attrs);
+
+        let attr = discriminator?;
+        if tags.len() != 1 {
+            ctx.push_spanned_error(name, "a discriminated enum needs exactly one `tag`");
+            return None;
+        }
+
+        let (attr_prefix, attr_local) = match attr.value().split_once(':') {
+            Some((prefix, local)) => (
+                Some(LitStr::new(prefix, attr.span())),
+                LitStr::new(local, attr.span()),
+            ),
+            None => (None, attr.clone()),
+        };
+
+        // `xsi:type` needs no namespace, as the `xsi` prefix is well known
+        let attr_ns = match (discriminator_ns, &attr_prefix) {
+            (Some(ns), _) => Some(ns),
+            (None, Some(prefix)) if prefix.value() == "xsi" => Some(LitStr::new(
+                "http://www.w3.org/2001/XMLSchema-instance",
+                attr.span(),
+            )),
+            (None, Some(_)) => {
+                ctx.push_spanned_error(
+                    &attr,
+                    "a prefixed `discriminator` other than `xsi:` requires `discriminator_ns`",
+                );
+                return None;
+            }
+            (None, None) => None,
+        };
+
+        Some(Discriminated {
+            tag: tags.remove(0),
+            ns,
+            attr,
+            attr_ns,
+            attr_prefix,
+            attr_local,
+        })
+    }
+}
+
+/// The discriminating attribute of an enum variant, with
+/// `#[xml(value = "$value" $(, value_ns = "$value_ns")?)]`
+pub struct Discriminator {
+    // the name as written in `discriminator`
+    pub attr: LitStr,
+    pub ns: Option<LitStr>,
+    pub prefix: Option<LitStr>,
+    pub local: LitStr,
+    pub value: LitStr,
+    // the value is a qualified name matched by namespace with `value_ns`
+    pub value_ns: Option<LitStr>,
+    pub value_prefix: Option<LitStr>,
+    pub value_local: LitStr,
+}
+
+/// Case conversion of field names, from `#[xml(rename_all = "$rule")]`
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    pub fn parse(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    /// Converts a snake_case field name.
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => name.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => name.to_ascii_uppercase(),
+            RenameRule::Pascal | RenameRule::Camel => {
+                let mut pascal = String::with_capacity(name.len());
+                let mut capitalize = matches!(self, RenameRule::Pascal);
+                for ch in name.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Kebab => name.replace('_', "-"),
+            RenameRule::ScreamingKebab => name.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
+
+/// The value of a field missing from the document
+#[derive(Clone)]
+pub enum FieldDefault {
+    // `default`
+    Trait,
+    // `default = "$path"`
+    Path(ExprPath),
+    // `default_str = "$lit"`, parsed like the document's values
+    Str(LitStr),
+}
+
+/// When to leave a field out on writing
+pub enum SkipIf {
+    // `skip_serializing_if = "$path"`
+    Path(ExprPath),
+    // `skip_default`, compared with the field's default
+    Default(FieldDefault),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Nillable {
+    // `None` isn't written
+    Omit,
+    // `None` is written as an element with `xsi:nil="true"`
+    Explicit,
+}
+
+#[derive(Clone, Copy)]
+pub enum Cdata {
+    Always,
+    // only if the text contains markup characters
+    Auto,
+}
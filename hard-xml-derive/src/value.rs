@@ -0,0 +1,85 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+use crate::{attrs, utils::Context};
+
+pub fn impl_value(input: DeriveInput) -> Result<TokenStream, Vec<syn::Error>> {
+    let mut ctx = Context::default();
+
+    let variants = match input.data {
+        Data::Enum(data) => data.variants,
+        _ => {
+            return Err(vec![syn::Error::new_spanned(
+                &input.ident,
+                "`XmlValue` can only be derived for enums",
+            )]);
+        }
+    };
+
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    let mut other = None;
+
+    for variant in variants {
+        let attrs = attrs::Variant::parse(&mut ctx, variant.attrs);
+
+        if !matches!(variant.fields, Fields::Unit) {
+            ctx.push_spanned_error(&variant.fields, "`XmlValue` only supports unit variants");
+        }
+
+        if attrs.other {
+            if other.is_some() {
+                ctx.push_spanned_error(&variant.ident, "duplicate `other` variant");
+            }
+            other = Some(variant.ident.clone());
+        }
+
+        // the variant name is used as is without a `value`
+        let value = attrs
+            .value
+            .unwrap_or_else(|| LitStr::new(&variant.ident.to_string(), variant.ident.span()));
+        if values.iter().any(|other: &LitStr| other.value() == value.value()) {
+            ctx.push_spanned_error(&value, "duplicate value");
+        }
+
+        names.push(variant.ident);
+        values.push(value);
+    }
+
+    ctx.check()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fallback = match other {
+        Some(other) => quote! { Ok(#name::#other) },
+        None => quote! {
+            Err(hard_xml::UnknownValue {
+                value: __value.to_owned(),
+                expected: &[#( #values ),*],
+            })
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics std::str::FromStr for #name #ty_generics #where_clause {
+            type Err = hard_xml::UnknownValue;
+
+            fn from_str(__value: &str) -> Result<Self, Self::Err> {
+                match __value {
+                    #( #values => Ok(#name::#names), )*
+                    _ => #fallback,
+                }
+            }
+        }
+
+        impl #impl_generics std::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(match self {
+                    #( #name::#names => #values, )*
+                })
+            }
+        }
+    })
+}
@@ -14,24 +14,44 @@ pub fn impl_write(element: Element) -> TokenStream {
         } => {
             let branches = variants.iter().map(|variant| match variant {
                 Fields::Named { name, fields, .. } => {
-                    let bindings = fields.iter().map(|field| match field {
-                        Field::Attribute { bind, name, .. }
-                        | Field::Prefix { bind, name, .. }
-                        | Field::Startswith { bind, name, .. }
-                        | Field::Child { bind, name, .. }
-                        | Field::Text { bind, name, .. }
-                        | Field::FlattenText { bind, name, .. } => quote!( #name: #bind ),
-                    });
+                    let bindings = bindings(fields);
                     quote!( #ele_name::#name { #( #bindings ),* } )
                 }
-                Fields::Newtype { name, .. } => quote!( #ele_name::#name(__inner) ),
+                Fields::Newtype { name, .. } | Fields::Text { name, .. } => {
+                    quote!( #ele_name::#name(__inner) )
+                }
             });
 
             let read = variants.iter().map(|variant| match variant {
                 Fields::Named {
-                    tag, name, fields, ..
-                } => named::write(tag, quote!( #ele_name::#name ), fields),
-                Fields::Newtype { name, .. } => newtype::write(quote!( #ele_name::#name )),
+                    tag,
+                    ns,
+                    prefix,
+                    default_ns,
+                    declaration,
+                    discriminator,
+                    name,
+                    fields,
+                    ..
+                } => {
+                    let declaration = write_declaration(*declaration);
+                    let write = named::write(
+                        tag,
+                        ns,
+                        prefix,
+                        default_ns,
+                        discriminator,
+                        quote!( #ele_name::#name ),
+                        fields,
+                    );
+                    quote! { #declaration #write }
+                }
+                Fields::Newtype { declaration, name, .. } => {
+                    let declaration = write_declaration(*declaration);
+                    let write = newtype::write(quote!( #ele_name::#name ));
+                    quote! { #declaration #write }
+                }
+                Fields::Text { name, ty } => named::write_mixed_text(ty, quote!( #ele_name::#name )),
             });
 
             quote! {
@@ -46,34 +66,146 @@ pub fn impl_write(element: Element) -> TokenStream {
             fields,
         } => match fields {
             Fields::Named {
-                tag, name, fields, ..
+                tag,
+                ns,
+                prefix,
+                default_ns,
+                declaration,
+                discriminator,
+                name,
+                fields,
+                ..
             } => {
-                let bindings = fields.iter().map(|field| match field {
-                    Field::Attribute { bind, name, .. }
-                    | Field::Prefix { bind, name, .. }
-                    | Field::Startswith { bind, name, .. }
-                    | Field::Child { bind, name, .. }
-                    | Field::Text { bind, name, .. }
-                    | Field::FlattenText { bind, name, .. } => quote!( #name: #bind ),
-                });
+                let bindings = bindings(&fields);
 
-                let read = named::write(&tag, quote!(#name), &fields);
+                let declaration = write_declaration(declaration);
+                let read = named::write(
+                    &tag,
+                    &ns,
+                    &prefix,
+                    &default_ns,
+                    &discriminator,
+                    quote!(#name),
+                    &fields,
+                );
 
                 quote! {
                     let #ele_name { #( #bindings ),* } = self;
 
+                    #declaration
                     #read
                 }
             }
-            Fields::Newtype { name, .. } => {
+            Fields::Newtype { declaration, name, .. } => {
+                let declaration = write_declaration(declaration);
                 let read = newtype::write(quote!(#name));
 
                 quote! {
                     let __inner = &self.0;
 
+                    #declaration
                     #read
                 }
             }
+            Fields::Text { .. } => unreachable!(),
         },
+
+        Element::Fields { .. } => unreachable!(),
+    }
+}
+
+/// The `XmlWriteFields` methods of a `#[xml(fields)]` struct.
+pub fn impl_write_fields(element: Element) -> TokenStream {
+    let (ele_name, fields) = match element {
+        Element::Fields { name, fields, .. } => (name, fields),
+        _ => unreachable!(),
+    };
+
+    let bindings = bindings(&fields);
+    let write_attributes = named::write_attributes(&fields, &quote!(#ele_name));
+    let write_content = named::write_content(&fields, &quote!(#ele_name));
+    let content_is_empty = named::content_is_empty(&fields);
+
+    quote! {
+        fn write_attributes<W: std::io::Write>(
+            &self,
+            mut writer: &mut hard_xml::XmlWriter<W>
+        ) -> hard_xml::XmlResult<()> {
+            let #ele_name { #( #bindings ),* } = self;
+
+            #( #write_attributes )*
+
+            Ok(())
+        }
+
+        fn write_children<W: std::io::Write>(
+            &self,
+            mut writer: &mut hard_xml::XmlWriter<W>
+        ) -> hard_xml::XmlResult<()> {
+            let #ele_name { #( #bindings ),* } = self;
+
+            #write_content
+
+            Ok(())
+        }
+
+        fn has_children(&self) -> bool {
+            let #ele_name { #( #bindings ),* } = self;
+
+            !(#content_is_empty)
+        }
+    }
+}
+
+/// Binds the fields of a struct or struct variant by reference.
+fn bindings(fields: &[Field]) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .map(|field| match field {
+            Field::Attribute { bind, name, .. }
+            | Field::Prefix { bind, name, .. }
+            | Field::Startswith { bind, name, .. }
+            | Field::Child { bind, name, .. }
+            | Field::Text { bind, name, .. }
+            | Field::Mixed { bind, name, .. }
+            | Field::Comment { bind, name, .. }
+            | Field::ProcessingInstruction { bind, name, .. }
+            | Field::AnyAttribute { bind, name, .. }
+            | Field::AnyElement { bind, name, .. }
+            | Field::FlattenText { bind, name, .. }
+            | Field::Flatten { bind, name, .. } => quote!( #name: #bind ),
+            Field::Skip { name, .. } => quote!( #name: _ ),
+        })
+        .collect()
+}
+
+fn write_declaration(declaration: bool) -> TokenStream {
+    if declaration {
+        quote! { writer.write_root_declaration()?; }
+    } else {
+        quote! {}
+    }
+}
+
+pub fn impl_collect_namespaces(element: &Element) -> TokenStream {
+    let collect = |fields: &Fields| match fields {
+        Fields::Named {
+            ns,
+            prefix,
+            default_ns,
+            discriminator,
+            fields,
+            ..
+        } => named::collect_namespaces(ns, prefix, default_ns, discriminator, fields),
+        Fields::Newtype { ty, .. } => named::collect_child_namespaces(ty).unwrap_or_default(),
+        Fields::Text { .. } => TokenStream::new(),
+    };
+
+    match element {
+        Element::Enum { variants, .. } => variants.iter().map(collect).collect(),
+        Element::Struct { fields, .. } => collect(fields),
+        Element::Fields { fields, .. } => {
+            named::collect_namespaces(&None, &None, &None, &None, fields)
+        }
     }
 }
@@ -2,41 +2,79 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{ExprPath, Ident, LitStr};
 
-use crate::types::{Field, Type};
+use crate::types::{Cdata, Discriminator, Field, FieldDefault, Nillable, SkipIf, Type};
 
-pub fn write(tag: &LitStr, ele_name: TokenStream, fields: &[Field]) -> TokenStream {
-    let write_attributes = fields.iter().filter_map(|field| match field {
-        Field::Attribute { tag, bind, ty, with, .. } => Some(write_attrs(tag, bind, ty, with, &ele_name)),
-        _ => None,
-    });
+pub fn write(
+    tag: &LitStr,
+    ns: &Option<LitStr>,
+    prefix: &Option<LitStr>,
+    default_ns: &Option<LitStr>,
+    discriminator: &Option<Discriminator>,
+    ele_name: TokenStream,
+    fields: &[Field],
+) -> TokenStream {
+    let write_start = match (ns, prefix) {
+        (Some(ns), Some(prefix)) => quote! { writer.write_element_start_ns(#ns, Some(#prefix), #tag)?; },
+        (Some(ns), None) => quote! { writer.write_element_start_ns(#ns, None, #tag)?; },
+        (None, _) => quote! { writer.write_element_start(#tag)?; },
+    };
 
-    let write_text = fields.iter().filter_map(|field| match field {
-        Field::Text {
-            bind, ty, with, is_cdata, ..
-        } => Some(write_text(tag, bind, ty, with, &ele_name, *is_cdata)),
-        _ => None,
-    });
+    let write_default_ns = default_ns.iter().map(|uri| quote! { writer.write_namespace("", #uri)?; });
 
-    let write_flatten_text = fields.iter().filter_map(|field| match field {
-        Field::FlattenText {
-            tag,
-            bind,
-            ty,
-            with,
-            is_cdata,
+    let collect = collect_namespaces(ns, prefix, default_ns, discriminator, fields);
+
+    let write_namespaces = if collect.is_empty() {
+        quote! {}
+    } else {
+        quote! { writer.write_namespace_declarations(|__namespaces| { #collect })?; }
+    };
+
+    let write_discriminator = discriminator.iter().map(|discriminator| {
+        let Discriminator {
+            attr,
+            ns,
+            prefix,
+            local,
+            value,
+            value_ns,
+            value_prefix,
             ..
-        } => Some(write_flatten_text(tag, bind, ty, with, &ele_name, *is_cdata)),
-        _ => None,
+        } = discriminator;
+        // declares the prefixes here unless they're in scope already
+        let write_value_ns = match (value_ns, value_prefix) {
+            (Some(ns), Some(prefix)) => quote! { writer.write_namespace(#prefix, #ns)?; },
+            _ => quote! {},
+        };
+        match (ns, prefix) {
+            (Some(ns), Some(prefix)) => quote! {
+                writer.write_namespace(#prefix, #ns)?;
+                #write_value_ns
+                writer.write_attribute_ns(#ns, #local, #value)?;
+            },
+            (Some(ns), None) => quote! {
+                #write_value_ns
+                writer.write_attribute_ns(#ns, #local, #value)?;
+            },
+            (None, _) => quote! {
+                #write_value_ns
+                writer.write_attribute(#attr, #value)?;
+            },
+        }
     });
 
-    let write_child = fields.iter().filter_map(|field| match field {
-        Field::Child { bind, ty, .. } => Some(write_child(bind, ty, &ele_name)),
+    let write_attributes = write_attributes(fields, &ele_name);
+
+    let write_text = fields.iter().filter_map(|field| match field {
+        Field::Text {
+            bind, ty, with, cdata, ..
+        } => Some(write_text(tag, bind, ty, with, &ele_name, *cdata)),
         _ => None,
     });
 
     let write_maps = fields.iter().filter_map(|field| match field {
         Field::Prefix { tag, bind, ty, .. } => Some(write_prefix(tag, bind, ty, &ele_name)),
         Field::Startswith { tag, bind, ty, .. } => Some(write_starts(tag, bind, ty, &ele_name)),
+        Field::AnyAttribute { bind, .. } => Some(write_any_attr(bind, &ele_name)),
         _ => None,
     });
 
@@ -48,23 +86,9 @@ pub fn write(tag: &LitStr, ele_name: TokenStream, fields: &[Field]) -> TokenStre
         .iter()
         .any(|field| matches!(field, Field::Text { .. }));
 
-    let can_self_close = fields.iter().all(|field| match field {
-        Field::Child { ty, .. } | Field::FlattenText { ty, .. } => ty.is_vec() || ty.is_option(),
-        _ => true,
-    });
+    let content_is_empty = content_is_empty(fields);
 
-    let content_is_empty = fields.iter().filter_map(|field| match field {
-        Field::Child { ty, bind, .. } | Field::FlattenText { ty, bind, .. } => {
-            if ty.is_vec() {
-                Some(quote! { #bind.is_empty() })
-            } else if ty.is_option() {
-                Some(quote! { #bind.is_none() })
-            } else {
-                None
-            }
-        }
-        _ => None,
-    });
+    let write_content = write_content(fields, &ele_name);
 
     let write_element_end = if is_leaf_element {
         quote! { writer.write_element_end_empty()?; }
@@ -72,12 +96,11 @@ pub fn write(tag: &LitStr, ele_name: TokenStream, fields: &[Field]) -> TokenStre
         quote! { #( #write_text )* }
     } else {
         quote! {
-            if #can_self_close #( && #content_is_empty )* {
+            if #content_is_empty {
                 writer.write_element_end_empty()?;
             } else {
                 writer.write_element_end_open()?;
-                #( #write_child )*
-                #( #write_flatten_text )*
+                #write_content
                 writer.write_element_end_close(#tag)?;
             }
         }
@@ -86,7 +109,13 @@ pub fn write(tag: &LitStr, ele_name: TokenStream, fields: &[Field]) -> TokenStre
     quote! {
         hard_xml::log_start_writing!(#ele_name);
 
-        writer.write_element_start(#tag)?;
+        #write_start
+
+        #( #write_default_ns )*
+
+        #write_namespaces
+
+        #( #write_discriminator )*
 
         #( #write_attributes )*
 
@@ -98,8 +127,228 @@ pub fn write(tag: &LitStr, ele_name: TokenStream, fields: &[Field]) -> TokenStre
     }
 }
 
-fn write_attrs(tag: &LitStr, name: &Ident, ty: &Type, with: &Option<ExprPath>, ele_name: &TokenStream) -> TokenStream {
+pub(super) fn write_attributes(fields: &[Field], ele_name: &TokenStream) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .filter_map(|field| match field {
+            Field::Attribute { tag, ns, bind, ty, with, skip_if, .. } => {
+                let write = write_attrs(tag, ns, bind, ty, with, ele_name);
+                let skip = skip_if.as_ref().map(|skip_if| skip_condition(skip_if, bind, ty, with, true));
+                Some(write_unless(write, skip))
+            }
+            Field::Flatten { bind, .. } => Some(quote! {
+                hard_xml::XmlWriteFields::write_attributes(#bind, &mut writer)?;
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Writes everything between the start and end tags, except text.
+pub(super) fn write_content(fields: &[Field], ele_name: &TokenStream) -> TokenStream {
+    let write_flatten_text = fields.iter().filter_map(|field| match field {
+        Field::FlattenText {
+            tag,
+            ns,
+            bind,
+            ty,
+            with,
+            cdata,
+            skip_if,
+            ..
+        } => {
+            let write = write_flatten_text(tag, ns, bind, ty, with, ele_name, *cdata);
+            let skip = skip_if.as_ref().map(|skip_if| skip_condition(skip_if, bind, ty, with, false));
+            Some(write_unless(write, skip))
+        }
+        _ => None,
+    });
+
+    // `Positioned` items are held back by the writer until their place
+    let write_misc = fields.iter().filter_map(|field| match field {
+        Field::Comment { bind, ty, with, positioned: false, .. } => {
+            let to_str = to_str(ty, with, false);
+            let write = quote! { writer.write_comment(#to_str)?; };
+            Some(write_misc(write, bind, ty, false, ele_name))
+        }
+        Field::Comment { bind, ty, with, positioned: true, .. } => {
+            let to_str = to_str(ty, with, false);
+            let write = quote! { writer.write_comment_at(__item.position, #to_str)?; };
+            Some(write_misc(write, bind, ty, true, ele_name))
+        }
+        Field::ProcessingInstruction { bind, ty, with, target, positioned: false, .. } => {
+            let to_str = to_str(ty, with, false);
+            let write = quote! { writer.write_processing_instruction(#target, #to_str)?; };
+            Some(write_misc(write, bind, ty, false, ele_name))
+        }
+        Field::ProcessingInstruction { bind, ty, with, target, positioned: true, .. } => {
+            let to_str = to_str(ty, with, false);
+            let write = quote! {
+                writer.write_processing_instruction_at(__item.position, #target, #to_str)?;
+            };
+            Some(write_misc(write, bind, ty, true, ele_name))
+        }
+        Field::AnyElement { bind, positioned: true, .. } => Some(quote! {
+            hard_xml::log_start_writing_field!(#ele_name, #bind);
+
+            for __item in #bind {
+                writer.write_element_at(__item.position, &__item.value)?;
+            }
+
+            hard_xml::log_finish_writing_field!(#ele_name, #bind);
+        }),
+        _ => None,
+    });
+
+    let write_any_element = fields.iter().filter_map(|field| match field {
+        Field::AnyElement { bind, ty, positioned: false, .. } => Some(write_child(bind, ty, ele_name)),
+        _ => None,
+    });
+
+    let write_child = fields.iter().filter_map(|field| match field {
+        Field::Child { bind, ty, with, skip_if, nillable: Some(Nillable::Explicit), tags, ns, .. } => {
+            let skip = skip_if.as_ref().map(|skip_if| skip_condition(skip_if, bind, ty, with, false));
+            Some(write_unless(write_nillable_child(bind, &tags[0], ns, ele_name), skip))
+        }
+        Field::Child { bind, ty, with, skip_if, .. } => {
+            let skip = skip_if.as_ref().map(|skip_if| skip_condition(skip_if, bind, ty, with, false));
+            Some(write_unless(write_child(bind, ty, ele_name), skip))
+        }
+        Field::Mixed { bind, .. } => Some(write_mixed(bind, ele_name)),
+        Field::Flatten { bind, .. } => Some(quote! {
+            hard_xml::XmlWriteFields::write_children(#bind, &mut writer)?;
+        }),
+        _ => None,
+    });
+
+    quote! {
+        #( #write_misc )*
+        #( #write_child )*
+        #( #write_flatten_text )*
+        #( #write_any_element )*
+    }
+}
+
+/// Whether `write_content` writes nothing, so that the element can be closed
+/// right away.
+pub(super) fn content_is_empty(fields: &[Field]) -> TokenStream {
+    let can_self_close = fields.iter().all(|field| match field {
+        // `None` is written as a nil element
+        Field::Child { nillable: Some(Nillable::Explicit), skip_if, .. } => skip_if.is_some(),
+        Field::Child { ty, skip_if, .. } | Field::FlattenText { ty, skip_if, .. } => {
+            ty.is_vec() || ty.is_option() || skip_if.is_some()
+        }
+        _ => true,
+    });
+
+    let is_empty = |ty: &Type, bind: &Ident| {
+        if ty.is_vec() {
+            Some(quote! { #bind.is_empty() })
+        } else if ty.is_option() {
+            Some(quote! { #bind.is_none() })
+        } else {
+            None
+        }
+    };
+
+    let content_is_empty = fields.iter().filter_map(|field| match field {
+        Field::Child { ty, bind, with, skip_if, nillable: Some(Nillable::Explicit), .. } => skip_if
+            .as_ref()
+            .map(|skip_if| skip_condition(skip_if, bind, ty, with, false)),
+        Field::Child { ty, bind, with, skip_if: Some(skip_if), .. }
+        | Field::FlattenText { ty, bind, with, skip_if: Some(skip_if), .. } => {
+            let skip = skip_condition(skip_if, bind, ty, with, false);
+            match is_empty(ty, bind) {
+                Some(is_empty) => Some(quote! { (#skip || #is_empty) }),
+                None => Some(skip),
+            }
+        }
+        Field::Child { ty, bind, .. }
+        | Field::FlattenText { ty, bind, .. }
+        | Field::Mixed { ty, bind, .. }
+        | Field::Comment { ty, bind, .. }
+        | Field::ProcessingInstruction { ty, bind, .. }
+        | Field::AnyElement { ty, bind, .. } => is_empty(ty, bind),
+        Field::Flatten { bind, .. } => Some(quote! { !hard_xml::XmlWriteFields::has_children(#bind) }),
+        _ => None,
+    });
+
+    quote! { #can_self_close #( && #content_is_empty )* }
+}
+
+/// Namespaces used by the element and its descendants, see `XmlWrite::collect_namespaces`.
+pub fn collect_namespaces(
+    ns: &Option<LitStr>,
+    prefix: &Option<LitStr>,
+    default_ns: &Option<LitStr>,
+    discriminator: &Option<Discriminator>,
+    fields: &[Field],
+) -> TokenStream {
+    let declare = ns.iter().map(|ns| match prefix {
+        Some(prefix) => quote! { __namespaces.declare(#prefix, #ns); },
+        None => quote! { __namespaces.require_element(#ns); },
+    });
+
+    let declare_default = default_ns.iter().map(|uri| quote! { __namespaces.declare("", #uri); });
+
+    let declare_discriminator = discriminator.iter().filter_map(|discriminator| {
+        match (&discriminator.ns, &discriminator.prefix) {
+            (Some(ns), Some(prefix)) => Some(quote! { __namespaces.declare(#prefix, #ns); }),
+            (Some(ns), None) => Some(quote! { __namespaces.require_attribute(#ns); }),
+            (None, _) => None,
+        }
+    });
+
+    let declare_value = discriminator.iter().filter_map(|discriminator| {
+        match (&discriminator.value_ns, &discriminator.value_prefix) {
+            (Some(ns), Some(prefix)) => Some(quote! { __namespaces.declare(#prefix, #ns); }),
+            _ => None,
+        }
+    });
+
+    let fields = fields.iter().filter_map(|field| match field {
+        Field::Attribute { ns: Some(ns), .. } => Some(quote! { __namespaces.require_attribute(#ns); }),
+        Field::FlattenText { ns: Some(ns), .. } => Some(quote! { __namespaces.require_element(#ns); }),
+        Field::Child { ty, .. } | Field::Mixed { ty, .. } | Field::AnyElement { ty, .. } => {
+            collect_child_namespaces(ty)
+        }
+        Field::Flatten { ty, .. } => Some(quote! {
+            <#ty as hard_xml::XmlWriteFields>::collect_namespaces(__namespaces);
+        }),
+        _ => None,
+    });
+
+    quote! {
+        #( #declare )*
+        #( #declare_default )*
+        #( #declare_discriminator )*
+        #( #declare_value )*
+        #( #fields )*
+    }
+}
+
+pub fn collect_child_namespaces(ty: &Type) -> Option<TokenStream> {
+    match ty {
+        Type::T(ty) | Type::OptionT(ty) | Type::VecT(ty) => Some(quote! {
+            <#ty as hard_xml::XmlWrite>::collect_namespaces(__namespaces);
+        }),
+        _ => None,
+    }
+}
+
+fn write_attrs(
+    tag: &LitStr,
+    ns: &Option<LitStr>,
+    name: &Ident,
+    ty: &Type,
+    with: &Option<ExprPath>,
+    ele_name: &TokenStream,
+) -> TokenStream {
     let to_str = to_str(ty, with, true);
+    let write_attribute = match ns {
+        Some(ns) => quote! { writer.write_attribute_ns(#ns, #tag, #to_str)?; },
+        None => quote! { writer.write_attribute(#tag, #to_str)?; },
+    };
 
     if ty.is_vec() {
         panic!("`attr` attribute doesn't support Vec.");
@@ -108,7 +357,7 @@ fn write_attrs(tag: &LitStr, name: &Ident, ty: &Type, with: &Option<ExprPath>, e
             hard_xml::log_start_writing_field!(#ele_name, #name);
 
             if let Some(__value) = #name {
-                writer.write_attribute(#tag, #to_str)?;
+                #write_attribute
             }
 
             hard_xml::log_finish_writing_field!(#ele_name, #name);
@@ -118,13 +367,49 @@ fn write_attrs(tag: &LitStr, name: &Ident, ty: &Type, with: &Option<ExprPath>, e
             hard_xml::log_start_writing_field!(#ele_name, #name);
 
             let __value = #name;
-            writer.write_attribute(#tag, #to_str)?;
+            #write_attribute
 
             hard_xml::log_finish_writing_field!(#ele_name, #name);
         }
     }
 }
 
+/// Whether the field is left out, see `SkipIf`.
+fn skip_condition(
+    skip_if: &SkipIf,
+    name: &Ident,
+    ty: &Type,
+    with: &Option<ExprPath>,
+    convert: bool,
+) -> TokenStream {
+    match skip_if {
+        SkipIf::Path(path) => quote! { #path(#name) },
+        SkipIf::Default(FieldDefault::Trait) => quote! { hard_xml::is_default(#name) },
+        SkipIf::Default(FieldDefault::Path(path)) => quote! { *#name == #path() },
+        // compare what would be written, as the literal is in its written form
+        SkipIf::Default(FieldDefault::Str(lit)) => {
+            let to_str = to_str(ty, with, convert);
+            quote! {
+                {
+                    let __value = #name;
+                    std::convert::AsRef::<str>::as_ref(#to_str) == #lit
+                }
+            }
+        }
+    }
+}
+
+fn write_unless(write: TokenStream, skip: Option<TokenStream>) -> TokenStream {
+    match skip {
+        Some(skip) => quote! {
+            if !(#skip) {
+                #write
+            }
+        },
+        None => write,
+    }
+}
+
 fn write_child(name: &Ident, ty: &Type, ele_name: &TokenStream) -> TokenStream {
     match ty {
         Type::OptionT(_) => quote! {
@@ -156,19 +441,114 @@ fn write_child(name: &Ident, ty: &Type, ele_name: &TokenStream) -> TokenStream {
     }
 }
 
+fn write_nillable_child(
+    name: &Ident,
+    tag: &LitStr,
+    ns: &Option<LitStr>,
+    ele_name: &TokenStream,
+) -> TokenStream {
+    let write_start = match ns {
+        Some(ns) => quote! { writer.write_element_start_ns(#ns, None, #tag)?; },
+        None => quote! { writer.write_element_start(#tag)?; },
+    };
+
+    quote! {
+        hard_xml::log_start_writing_field!(#ele_name, #name);
+
+        if let Some(ref ele) = #name {
+            ele.to_writer(&mut writer)?;
+        } else {
+            #write_start
+            writer.write_nil()?;
+            writer.write_element_end_empty()?;
+        }
+
+        hard_xml::log_finish_writing_field!(#ele_name, #name);
+    }
+}
+
+fn write_misc(
+    write: TokenStream,
+    name: &Ident,
+    ty: &Type,
+    positioned: bool,
+    ele_name: &TokenStream,
+) -> TokenStream {
+    let write = if positioned {
+        quote! {
+            let __value = &__item.value;
+            #write
+        }
+    } else {
+        quote! {
+            let __value = __item;
+            #write
+        }
+    };
+
+    if ty.is_vec() {
+        quote! {
+            hard_xml::log_start_writing_field!(#ele_name, #name);
+
+            for __item in #name {
+                #write
+            }
+
+            hard_xml::log_finish_writing_field!(#ele_name, #name);
+        }
+    } else {
+        quote! {
+            hard_xml::log_start_writing_field!(#ele_name, #name);
+
+            if let Some(__item) = #name {
+                #write
+            }
+
+            hard_xml::log_finish_writing_field!(#ele_name, #name);
+        }
+    }
+}
+
+fn write_mixed(name: &Ident, ele_name: &TokenStream) -> TokenStream {
+    quote! {
+        hard_xml::log_start_writing_field!(#ele_name, #name);
+
+        writer.preserve_whitespace();
+        for ele in #name {
+            ele.to_writer(&mut writer)?;
+        }
+
+        hard_xml::log_finish_writing_field!(#ele_name, #name);
+    }
+}
+
+/// Writes the text variant of a mixed content enum.
+pub fn write_mixed_text(ty: &Type, ele_name: TokenStream) -> TokenStream {
+    let to_str = to_str(ty, &None, false);
+
+    quote! {
+        hard_xml::log_start_writing!(#ele_name);
+
+        let __value = __inner;
+        writer.write_text(#to_str)?;
+
+        hard_xml::log_finish_writing!(#ele_name);
+    }
+}
+
 fn write_text(
     tag: &LitStr,
     name: &Ident,
     ty: &Type,
     with: &Option<ExprPath>,
     ele_name: &TokenStream,
-    is_cdata: bool,
+    cdata: Option<Cdata>,
 ) -> TokenStream {
     let to_str = to_str(ty, with, false);
-    let write_fn = if is_cdata {
-        quote!(write_cdata_text)
-    } else {
-        quote!(write_text)
+    let write_fn = match cdata {
+        Some(Cdata::Always) => quote!(write_cdata_text),
+        Some(Cdata::Auto) => quote!(write_text_or_cdata),
+        None => quote!(write_text),
     };
 
     quote! {
@@ -188,20 +568,38 @@ fn write_text(
 
 fn write_flatten_text(
     tag: &LitStr,
+    ns: &Option<LitStr>,
     name: &Ident,
     ty: &Type,
     with: &Option<ExprPath>,
     ele_name: &TokenStream,
-    is_cdata: bool,
+    cdata: Option<Cdata>,
 ) -> TokenStream {
     let to_str = to_str(ty, with, false);
+    let is_cdata = matches!(cdata, Some(Cdata::Always));
+    let write_flatten_text = match (ns, cdata) {
+        (Some(ns), Some(Cdata::Auto)) => quote! {
+            writer.write_element_start_ns(#ns, None, #tag)?;
+            writer.write_element_end_open()?;
+            writer.write_text_or_cdata(#to_str)?;
+            writer.write_element_end_close(#tag)?;
+        },
+        (None, Some(Cdata::Auto)) => quote! {
+            writer.write_element_start(#tag)?;
+            writer.write_element_end_open()?;
+            writer.write_text_or_cdata(#to_str)?;
+            writer.write_element_end_close(#tag)?;
+        },
+        (Some(ns), _) => quote! { writer.write_flatten_text_ns(#ns, #tag, #to_str, #is_cdata)?; },
+        (None, _) => quote! { writer.write_flatten_text(#tag, #to_str, #is_cdata)?; },
+    };
 
     if ty.is_vec() {
         quote! {
             hard_xml::log_finish_writing_field!(#ele_name, #name);
 
             for __value in #name {
-                writer.write_flatten_text(#tag, #to_str, #is_cdata)?;
+                #write_flatten_text
             }
 
             hard_xml::log_finish_writing_field!(#ele_name, #name);
@@ -211,7 +609,7 @@ fn write_flatten_text(
             hard_xml::log_finish_writing_field!(#ele_name, #name);
 
             if let Some(__value) = #name {
-                writer.write_flatten_text(#tag, #to_str, #is_cdata)?;
+                #write_flatten_text
             }
 
             hard_xml::log_finish_writing_field!(#ele_name, #name);
@@ -221,7 +619,7 @@ fn write_flatten_text(
             hard_xml::log_finish_writing_field!(#ele_name, #name);
 
             let __value = &#name;
-            writer.write_flatten_text(#tag, #to_str, #is_cdata)?;
+            #write_flatten_text
 
             hard_xml::log_finish_writing_field!(#ele_name, #name);
         }
@@ -258,6 +656,18 @@ fn write_prefix(tag: &LitStr, name: &Ident, ty: &Type, ele_name: &TokenStream) -
     }
 }
 
+fn write_any_attr(name: &Ident, ele_name: &TokenStream) -> TokenStream {
+    quote! {
+        hard_xml::log_start_writing_field!(#ele_name, #name);
+
+        for (k, v) in #name {
+            writer.write_attribute_or_namespace(&k.to_string(), &v.to_string())?;
+        }
+
+        hard_xml::log_finish_writing_field!(#ele_name, #name);
+    }
+}
+
 fn write_starts(tag: &LitStr, name: &Ident, ty: &Type, ele_name: &TokenStream) -> TokenStream {
     if !ty.is_map() {
         panic!("`startswith` attribute only support Map.");
@@ -0,0 +1,285 @@
+//! An untyped element tree, for documents whose type isn't known up front
+//! or parts of them nobody has modelled.
+//!
+//! ```rust
+//! use hard_xml::dom::{Element, Node};
+//! use hard_xml::{XmlRead, XmlWrite};
+//!
+//! let mut root = Element::from_str(r#"<root><item id="1">one</item></root>"#).unwrap();
+//!
+//! let item = root.child_mut("item").unwrap();
+//! assert_eq!(item.attribute("id"), Some("1"));
+//! item.set_attribute("id", "2");
+//!
+//! root.push_child(Element::new("empty"));
+//!
+//! assert_eq!(
+//!     root.to_string().unwrap(),
+//!     r#"<root><item id="2">one</item><empty/></root>"#
+//! );
+//! ```
+
+use std::io::Write;
+
+use crate::xmlparser::{ElementEnd, Token};
+use crate::xml_reader::{is_name, qualified_name};
+use crate::{XmlError, XmlRead, XmlReader, XmlResult, XmlWrite, XmlWriter};
+
+/// An element with its attributes and content.
+///
+/// Names are kept as written, including their prefix, and namespace
+/// declarations are kept as attributes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+/// A node in the content of an [`Element`].
+///
+/// Text is unescaped, and processing instructions are dropped when reading.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+    CData(String),
+    Comment(String),
+}
+
+impl Element {
+    /// Creates an element without attributes or content.
+    pub fn new(name: impl Into<String>) -> Self {
+        Element {
+            name: name.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Returns the value of the attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the attribute `name`, replacing its value if it exists already.
+    pub fn set_attribute(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.attributes.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, old)) => *old = value,
+            None => self.attributes.push((name, value)),
+        }
+    }
+
+    /// Removes the attribute `name`, returning its value.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|(key, _)| key == name)?;
+        Some(self.attributes.remove(index).1)
+    }
+
+    /// Returns the child elements, skipping text and comments.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Returns the child elements, skipping text and comments.
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.children.iter_mut().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Returns the child elements named `name`.
+    pub fn children_named<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s Element> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    /// Returns the first child element named `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Returns the first child element named `name`.
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut Element> {
+        self.elements_mut().find(|element| element.name == name)
+    }
+
+    /// Appends `node` to the content.
+    pub fn push_child(&mut self, node: impl Into<Node>) {
+        self.children.push(node.into());
+    }
+
+    /// Removes the first child element named `name` and returns it.
+    pub fn remove_child(&mut self, name: &str) -> Option<Element> {
+        let index = self
+            .children
+            .iter()
+            .position(|node| matches!(node, Node::Element(element) if element.name == name))?;
+        match self.children.remove(index) {
+            Node::Element(element) => Some(element),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the text and CDATA directly inside the element, concatenated.
+    pub fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) | Node::CData(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn read(reader: &mut XmlReader) -> XmlResult<Self> {
+        let tag = reader
+            .find_element_start(None)?
+            .ok_or(XmlError::UnexpectedEof)?;
+        // skip the start tag
+        reader.next();
+        reader.enter_element(tag, "Element");
+
+        let mut element = Element::new(tag);
+
+        while let Some((key, value)) = reader.find_attribute()? {
+            element.attributes.push((key.to_owned(), value.into_owned()));
+        }
+
+        if let Some(Token::ElementEnd {
+            end: ElementEnd::Empty,
+            ..
+        }) = reader.next().transpose()?
+        {
+            reader.leave();
+            return Ok(element);
+        }
+
+        loop {
+            if let Some(Ok(Token::ElementStart { .. })) = reader.peek() {
+                element.children.push(Node::Element(Element::from_reader(reader)?));
+                continue;
+            }
+
+            let node = match reader.next().ok_or(XmlError::UnexpectedEof)?? {
+                Token::Text { text } => Node::Text(reader.unescape(text.as_str())?.into_owned()),
+                Token::Cdata { text, .. } => Node::CData(text.as_str().to_owned()),
+                Token::Comment { text, .. } => Node::Comment(text.as_str().to_owned()),
+                Token::ProcessingInstruction { .. } => continue,
+                Token::ElementEnd {
+                    end: ElementEnd::Close(prefix, local),
+                    ..
+                } => {
+                    if is_name(prefix, local, tag) {
+                        break;
+                    } else {
+                        return Err(XmlError::TagMismatch {
+                            expected: tag.to_owned(),
+                            found: qualified_name(prefix, local),
+                        });
+                    }
+                }
+                token => {
+                    return Err(XmlError::UnexpectedToken {
+                        token: format!("{:?}", token),
+                    })
+                }
+            };
+            element.children.push(node);
+        }
+
+        reader.leave();
+        Ok(element)
+    }
+}
+
+impl<'a> XmlRead<'a> for Element {
+    fn from_reader(reader: &mut XmlReader<'a>) -> XmlResult<Self> {
+        let depth = reader.path_depth();
+        Element::read(reader).map_err(|err| reader.unwind(err, depth))
+    }
+}
+
+impl XmlWrite for Element {
+    fn to_writer<W: Write>(&self, writer: &mut XmlWriter<W>) -> XmlResult<()> {
+        // an unprefixed element declaring the default namespace starts in it
+        match self.attribute("xmlns") {
+            Some(uri) if !self.name.contains(':') => {
+                writer.write_element_start_ns(uri, Some(""), &self.name)?
+            }
+            _ => writer.write_element_start(&self.name)?,
+        }
+
+        for (key, value) in &self.attributes {
+            writer.write_attribute_or_namespace(key, value)?;
+        }
+
+        if self.children.is_empty() {
+            writer.write_element_end_empty()?;
+            return Ok(());
+        }
+
+        writer.write_element_end_open()?;
+        for node in &self.children {
+            match node {
+                Node::Element(element) => element.to_writer(writer)?,
+                Node::Text(text) => writer.write_text(text)?,
+                Node::CData(text) => writer.write_cdata_text(text)?,
+                Node::Comment(text) => writer.write_comment(text)?,
+            }
+        }
+        writer.write_element_end_close(&self.name)?;
+
+        Ok(())
+    }
+}
+
+impl From<Element> for Node {
+    fn from(element: Element) -> Self {
+        Node::Element(element)
+    }
+}
+
+impl From<String> for Node {
+    fn from(text: String) -> Self {
+        Node::Text(text)
+    }
+}
+
+impl From<&str> for Node {
+    fn from(text: &str) -> Self {
+        Node::Text(text.to_owned())
+    }
+}
+
+#[test]
+fn read_write() -> XmlResult<()> {
+    let xml = r#"<a:root xmlns:a="urn:a" x="1 &amp; 2"><!--c--><b>t&lt;<![CDATA[<c>]]></b><?pi?><e/></a:root>"#;
+    let root = Element::from_str(xml)?;
+
+    assert_eq!(root.name, "a:root");
+    assert_eq!(root.attribute("x"), Some("1 & 2"));
+    assert_eq!(root.children.len(), 3);
+    assert_eq!(root.children[0], Node::Comment("c".into()));
+    assert_eq!(root.child("b").unwrap().text(), "t<<c>");
+    assert_eq!(root.children_named("e").count(), 1);
+    assert_eq!(
+        root.to_string()?,
+        r#"<a:root xmlns:a="urn:a" x="1 &amp; 2"><!--c--><b>t&lt;<![CDATA[<c>]]></b><e/></a:root>"#
+    );
+
+    assert!(matches!(
+        Element::from_str("<a><b></a>").unwrap_err().error(),
+        XmlError::TagMismatch { .. }
+    ));
+
+    Ok(())
+}
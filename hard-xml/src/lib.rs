@@ -35,6 +35,256 @@
 //! );
 //! ```
 //!
+//! ## Pretty Printing
+//!
+//! `XmlWrite::to_string_pretty` indents nested elements, while
+//! `XmlWriter::with_format` takes an [`XmlFormat`] to choose the indentation,
+//! the line break and how text-only elements are laid out. Whitespace is never
+//! added to elements that contain text.
+//!
+//! ```rust
+//! # use std::borrow::Cow;
+//! # use hard_xml::XmlWrite;
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "parent")]
+//! struct Parent<'a> {
+//!     #[xml(child = "child")]
+//!     child: Vec<Child<'a>>,
+//! }
+//!
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "child")]
+//! struct Child<'a> {
+//!     #[xml(text)]
+//!     text: Cow<'a, str>,
+//! }
+//!
+//! assert_eq!(
+//!     (Parent { child: vec![Child { text: "a".into() }] }).to_string_pretty().unwrap(),
+//!     "<parent>\n  <child>a</child>\n</parent>"
+//! );
+//! ```
+//!
+//! ## XML Declaration
+//!
+//! `XmlWrite::to_string` writes no declaration. `to_string_with_declaration`
+//! and `XmlWriter::write_declaration` take an [`XmlDeclaration`] with the
+//! version, encoding and standalone flag to write one before the root element.
+//!
+//! ```rust
+//! # use hard_xml::{XmlDeclaration, XmlWrite};
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "feed")]
+//! struct Feed {}
+//!
+//! let declaration = XmlDeclaration {
+//!     standalone: Some(true),
+//!     ..XmlDeclaration::default()
+//! };
+//!
+//! assert_eq!(
+//!     (Feed {}).to_string_with_declaration(&declaration).unwrap(),
+//!     r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><feed/>"#
+//! );
+//! ```
+//!
+//! `XmlWriter::set_encoding` writes ISO-8859-1 or US-ASCII instead of UTF-8,
+//! with characters outside of it in text and attributes written as character
+//! references. `XmlWrite::to_bytes_with_encoding` adds a matching declaration.
+//!
+//! ```rust
+//! # use hard_xml::{XmlEncoding, XmlWrite};
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "price")]
+//! struct Price(#[xml(text)] String);
+//!
+//! assert_eq!(
+//!     Price("5 \u{20AC}".into()).to_bytes_with_encoding(XmlEncoding::Ascii).unwrap(),
+//!     br#"<?xml version="1.0" encoding="US-ASCII"?><price>5 &#x20AC;</price>"#
+//! );
+//! ```
+//!
+//! ## Escaping
+//!
+//! Text and attribute values are escaped so that they read back exactly,
+//! including carriage returns and, in attributes, tabs and line feeds.
+//! Characters XML 1.0 doesn't allow, like `\u{0}`, fail with
+//! [`XmlError::InvalidChar`] unless `XmlWriter::set_invalid_chars` asks for
+//! them to be replaced.
+//!
+//! ## Streaming
+//!
+//! Types that don't borrow from the input implement `XmlReadOwned` and can be
+//! parsed from any `BufRead` with `XmlReadOwned::from_stream`, which only keeps
+//! the unparsed part of the input in memory. `from_stream_with_progress` also
+//! reports the number of bytes consumed so far.
+//!
+//! ```rust
+//! # use hard_xml::{XmlRead, XmlReadOwned};
+//! #[derive(XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "parent")]
+//! struct Parent {
+//!     #[xml(flatten_text = "child")]
+//!     child: Vec<String>,
+//! }
+//!
+//! let input = std::io::Cursor::new(b"<parent><child>a</child><child>b</child></parent>");
+//!
+//! assert_eq!(
+//!     Parent::from_stream(input).unwrap(),
+//!     Parent { child: vec!["a".into(), "b".into()] }
+//! );
+//! ```
+//!
+//! With the `tokio` feature, `XmlReadOwned::from_async_read` parses a tokio
+//! `AsyncRead` as it is read, without holding the whole document in memory,
+//! and `XmlWrite::to_async_writer` writes to an `AsyncWrite`.
+//!
+//! ## Encodings
+//!
+//! `XmlRead::from_bytes` picks the encoding from the byte order mark or the
+//! XML declaration. UTF-8 input is parsed in place, while UTF-16 and legacy
+//! encodings such as ISO-8859-1 are transcoded with the `encoding` feature.
+//!
+//! ```rust
+//! # use hard_xml::XmlRead;
+//! #[derive(XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "name")]
+//! struct Name(#[xml(text)] String);
+//!
+//! let mut buffer = String::new();
+//!
+//! assert_eq!(
+//!     Name::from_bytes(b"<?xml version=\"1.0\"?><name>Zo\xC3\xAB</name>", &mut buffer).unwrap(),
+//!     Name("Zo\u{EB}".into())
+//! );
+//! ```
+//!
+//! ## Entities
+//!
+//! Besides the predefined entities and character references, text and
+//! attribute values may refer to entities declared in the internal subset of
+//! the document type declaration. Other names can be supplied by an
+//! [`EntityResolver`], e.g. the HTML table `HtmlEntities` of the
+//! `html-entities` feature, while [`XmlEntityLimits`] caps the expansion.
+//!
+//! ```rust
+//! # use hard_xml::XmlRead;
+//! #[derive(XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "vendor")]
+//! struct Vendor(#[xml(text)] String);
+//!
+//! assert_eq!(
+//!     Vendor::from_str(r#"<!DOCTYPE vendor [<!ENTITY company "Acme">]><vendor>&company;</vendor>"#).unwrap(),
+//!     Vendor("Acme".into())
+//! );
+//! ```
+//!
+//! ## DOM
+//!
+//! [`dom::Element`] holds an element tree without a type, for peeking at a
+//! document or keeping parts of it nobody has modelled, e.g. as a `child`
+//! field. `XmlWrite::to_element` and `XmlRead::from_element` convert between
+//! typed values and elements without going through text. Reading an element
+//! borrows from it like reading a string does, with an [`XmlArena`] for what
+//! can't be borrowed.
+//!
+//! ```rust
+//! # use hard_xml::{dom::Element, XmlArena, XmlRead, XmlWrite};
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "item")]
+//! struct Item {
+//!     #[xml(attr = "id")]
+//!     id: u32,
+//! }
+//!
+//! let mut element = Item { id: 1 }.to_element().unwrap();
+//! element.set_attribute("id", "2");
+//!
+//! let arena = XmlArena::new();
+//! assert_eq!(Item::from_element(&element, &arena).unwrap(), Item { id: 2 });
+//! ```
+//!
+//! ## Enum Values
+//!
+//! `#[derive(XmlValue)]` implements `FromStr` and `Display` for an enum of unit
+//! variants, so it can be used in `attr`, `text` and `flatten_text` fields.
+//! Each variant is written as its `value`, or its name without one. Unknown
+//! values go to the `other` variant if there is one, and fail with an
+//! [`UnknownValue`] listing the expected ones otherwise.
+//!
+//! ```rust
+//! # use hard_xml::{XmlRead, XmlValue, XmlWrite};
+//! #[derive(XmlValue, PartialEq, Debug)]
+//! enum Mode {
+//!     #[xml(value = "fast")]
+//!     Fast,
+//!     #[xml(value = "agile")]
+//!     Agile,
+//! }
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "spaceship")]
+//! struct Spaceship {
+//!     #[xml(attr = "mode")]
+//!     mode: Mode,
+//! }
+//!
+//! assert_eq!(
+//!     Spaceship::from_str(r#"<spaceship mode="agile"/>"#).unwrap(),
+//!     Spaceship { mode: Mode::Agile }
+//! );
+//! assert_eq!(
+//!     Spaceship::from_str(r#"<spaceship mode="slow"/>"#).unwrap_err().to_string(),
+//!     "error parsing XML value: unknown value \"slow\", expected \"fast\" or \"agile\" \
+//!         at line 1, column 12 in /spaceship/@mode (field Spaceship.mode)"
+//! );
+//! ```
+//!
+//! ## Errors
+//!
+//! Entry points such as `XmlRead::from_str` return an `XmlReadError`, which
+//! wraps the `XmlError` with the position of the offending token, available
+//! from `XmlReadError::position`, and the elements and fields being read at
+//! the time, available from `XmlReadError::path`. `XmlReadError::error`
+//! returns the error for matching, and `?` converts it into an `XmlError`.
+//!
+//! ```rust
+//! # use hard_xml::{XmlError, XmlRead};
+//! #[derive(XmlRead, Debug)]
+//! #[xml(tag = "item")]
+//! struct Item {
+//!     #[xml(attr = "id")]
+//!     id: usize,
+//! }
+//!
+//! let err = Item::from_str("<item\n  id=\"one\"/>").unwrap_err();
+//!
+//! assert!(matches!(err.error(), XmlError::FromStr(_)));
+//! assert_eq!(err.position().map(|pos| (pos.line, pos.column)), Some((2, 3)));
+//! assert_eq!(err.to_string(), "error parsing XML value: invalid digit found in string \
+//!     at line 2, column 3 in /item/@id (field Item.id)");
+//! ```
+//!
+//! `XmlRead::from_str_lenient` keeps going after an error instead: it skips
+//! child elements which fail to parse, falls back to `Default` for invalid or
+//! missing fields where possible and returns all the errors it came across.
+//!
+//! ```rust
+//! # use hard_xml::XmlRead;
+//! #[derive(XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "list")]
+//! struct List {
+//!     #[xml(flatten_text = "item")]
+//!     items: Vec<usize>,
+//! }
+//!
+//! let (list, errors) = List::from_str_lenient("<list><item>1</item><item>x</item><item>3</item></list>");
+//!
+//! assert_eq!(list, Some(List { items: vec![1, 3] }));
+//! assert_eq!(errors[0].path().unwrap().elements, "/list/item[2]");
+//! ```
+//!
 //! ## Attributes
 //!
 //! ### `#[xml(strict(...))]`
@@ -66,7 +316,7 @@
 //!
 //! assert_eq!(
 //!     Ex::from_str("<ex foo='bar'/>").unwrap_err().to_string(),
-//!     r#"unknown field "foo" in element "Ex""#);
+//!     r#"unknown field "foo" in element "Ex" at line 1, column 5 in /ex (field Ex)"#);
 //! ```
 //!
 //! ##### `strict(unknown_element)`
@@ -83,7 +333,7 @@
 //!
 //! assert_eq!(
 //!     Ex::from_str("<ex><unknown/></ex>").unwrap_err().to_string(),
-//!     r#"unknown field "unknown" in element "Ex""#);
+//!     r#"unknown field "unknown" in element "Ex" at line 1, column 5 in /ex (field Ex)"#);
 //! ```
 //!
 //! ### `#[xml(tag = "")]`
@@ -136,6 +386,100 @@
 //! );
 //! ```
 //!
+//! ### `#[xml(discriminator = "")]`
+//!
+//! Reads and writes all struct variants of an enum as the same element, telling
+//! them apart by the value of an attribute. The tag goes on the enum, and each
+//! variant picks its value with `#[xml(value = "")]`, defaulting to its name.
+//! The attribute is written before the fields of the variant.
+//!
+//! ```rust
+//! # use hard_xml::{XmlRead, XmlWrite};
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "shape", discriminator = "type")]
+//! enum Shape {
+//!     #[xml(value = "circle")]
+//!     Circle {
+//!         #[xml(attr = "radius")]
+//!         radius: u32,
+//!     },
+//!     #[xml(value = "square")]
+//!     Square {
+//!         #[xml(attr = "side")]
+//!         side: u32,
+//!     },
+//! }
+//!
+//! assert_eq!(
+//!     (Shape::Circle { radius: 2 }).to_string().unwrap(),
+//!     r#"<shape type="circle" radius="2"/>"#
+//! );
+//!
+//! assert_eq!(
+//!     Shape::from_str(r#"<shape side="3" type="square"/>"#).unwrap(),
+//!     Shape::Square { side: 3 }
+//! );
+//! ```
+//!
+//! An unknown value is reported as an [`UnknownValue`] error, and a missing
+//! attribute as [`XmlError::MissingField`].
+//!
+//! A prefixed attribute such as `xsi:type` is matched by its namespace, so
+//! any prefix bound to it works. The `xsi` prefix stands for the XML Schema
+//! instance namespace, others need `#[xml(discriminator_ns = "")]`. The
+//! prefix is declared on the root element.
+//!
+//! Values such as `xsi:type` ones are qualified names themselves. With
+//! `#[xml(value_ns = "")]` a prefixed value is matched by the namespace its
+//! prefix is bound to, and the prefix is declared when writing.
+//!
+//! ```rust
+//! # use hard_xml::{XmlRead, XmlWrite};
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "shape", discriminator = "xsi:type")]
+//! enum Shape {
+//!     #[xml(value = "geo:Circle", value_ns = "urn:geo")]
+//!     Circle {
+//!         #[xml(attr = "radius")]
+//!         radius: u32,
+//!     },
+//! }
+//!
+//! assert_eq!(
+//!     (Shape::Circle { radius: 2 }).to_string().unwrap(),
+//!     r#"<shape xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:geo="urn:geo" xsi:type="geo:Circle" radius="2"/>"#
+//! );
+//!
+//! assert_eq!(
+//!     Shape::from_str(
+//!         r#"<shape xmlns:i="http://www.w3.org/2001/XMLSchema-instance" xmlns:g="urn:geo" i:type="g:Circle" radius="2"/>"#
+//!     )
+//!     .unwrap(),
+//!     Shape::Circle { radius: 2 }
+//! );
+//! ```
+//!
+//! ### `#[xml(declaration)]`
+//!
+//! Writes the default XML declaration before the element when it is the root
+//! of the document. Nested in another element, or written after an explicit
+//! declaration, it is left out.
+//!
+//! ```rust
+//! # use hard_xml::XmlWrite;
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "feed", declaration)]
+//! struct Feed {
+//!     #[xml(child = "feed")]
+//!     feeds: Vec<Feed>,
+//! }
+//!
+//! assert_eq!(
+//!     (Feed { feeds: vec![Feed { feeds: vec![] }] }).to_string().unwrap(),
+//!     r#"<?xml version="1.0" encoding="UTF-8"?><feed><feed/></feed>"#
+//! );
+//! ```
+//!
 //! ### `#[xml(attr = "")]`
 //!
 //! Specifies that a struct field is attribute. Support
@@ -163,6 +507,32 @@
 //! );
 //! ```
 //!
+//! ### `#[xml(rename_all = "")]`
+//!
+//! A bare `#[xml(attr)]` or `#[xml(flatten_text)]` takes its name from the
+//! field. `rename_all` on the struct or enum variant converts those names to
+//! `lowercase`, `UPPERCASE`, `PascalCase`, `camelCase`, `snake_case`,
+//! `SCREAMING_SNAKE_CASE`, `kebab-case` or `SCREAMING-KEBAB-CASE`, while
+//! explicit names are kept as written.
+//!
+//! ```rust
+//! use hard_xml::{XmlRead, XmlWrite};
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "server", rename_all = "camelCase")]
+//! struct Server {
+//!     #[xml(attr)]
+//!     host_name: String,
+//!     #[xml(flatten_text)]
+//!     admin_email: Option<String>,
+//! }
+//!
+//! assert_eq!(
+//!     (Server { host_name: "a".into(), admin_email: None }).to_string().unwrap(),
+//!     r#"<server hostName="a"/>"#
+//! );
+//! ```
+//!
 //! ### `#[xml(attr = "" with = "")]`
 //!
 //! Parse and serialize the field with the module specified as an argument. That module must provide the following interface given that the fields is of type `T`.
@@ -268,6 +638,37 @@
 //! );
 //! ```
 //!
+//! ### `#[xml(nillable)]`
+//!
+//! Reads a child element marked with `xsi:nil="true"` as `None`. Only
+//! supported on `Option<T>` child fields.
+//!
+//! `None` isn't written by default. With `nillable = "explicit"` it is written
+//! as an empty element marked with `xsi:nil="true"`, which declares the `xsi`
+//! prefix unless one is in scope already.
+//!
+//! ```rust
+//! # use hard_xml::{XmlRead, XmlWrite};
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "price")]
+//! struct Price {
+//!     #[xml(attr = "amount")]
+//!     amount: u32,
+//! }
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "order")]
+//! struct Order {
+//!     #[xml(child = "price", nillable = "explicit")]
+//!     price: Option<Price>,
+//! }
+//!
+//! let xml = r#"<order><price xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:nil="true"/></order>"#;
+//!
+//! assert_eq!(Order::from_str(xml).unwrap(), Order { price: None });
+//! assert_eq!((Order { price: None }).to_string().unwrap(), xml);
+//! ```
+//!
 //! ### `#[xml(text)]`
 //!
 //! Specifies that a struct field is text content.
@@ -324,6 +725,188 @@
 //! );
 //! ```
 //!
+//! ### `#[xml(mixed)]`
+//!
+//! Specifies that a struct field holds mixed content, text interleaved with
+//! child elements, in document order. Only supports `Vec<T>` where `T` is an
+//! enum with a `#[xml(text)]` newtype variant for the text, holding a
+//! `Cow<str>` or a `T: FromStr + Display`, and element variants for the
+//! children. Elements which don't match any variant are skipped.
+//!
+//! No line breaks or indentation are added to mixed content when pretty
+//! printing.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::{XmlRead, XmlWrite};
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "p")]
+//! struct Para<'a> {
+//!     #[xml(mixed)]
+//!     content: Vec<Inline<'a>>,
+//! }
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! enum Inline<'a> {
+//!     #[xml(text)]
+//!     Text(Cow<'a, str>),
+//!     #[xml(tag = "b")]
+//!     Bold {
+//!         #[xml(text)]
+//!         text: Cow<'a, str>,
+//!     },
+//! }
+//!
+//! let para = Para::from_str("<p>Hello <b>world</b>!</p>").unwrap();
+//!
+//! assert_eq!(
+//!     para.content,
+//!     vec![
+//!         Inline::Text("Hello ".into()),
+//!         Inline::Bold { text: "world".into() },
+//!         Inline::Text("!".into()),
+//!     ]
+//! );
+//!
+//! assert_eq!(para.to_string().unwrap(), "<p>Hello <b>world</b>!</p>");
+//! ```
+//!
+//! ### `#[xml(comment)]`
+//!
+//! Specifies that a struct field holds the comments between child elements.
+//! Only supports `Vec<T>` and `Option<T>`, where `Option<T>` keeps the first
+//! comment. Comments are written right after the start tag, unless the items
+//! are [`Positioned`], which remember the number of child elements before
+//! them and are written back at the same place.
+//!
+//! Comments inside text content are skipped.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::{Positioned, XmlRead, XmlWrite};
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "server")]
+//! struct Server<'a> {
+//!     #[xml(comment)]
+//!     comments: Vec<Positioned<Cow<'a, str>>>,
+//!     #[xml(flatten_text = "host")]
+//!     host: Cow<'a, str>,
+//!     #[xml(flatten_text = "timeout")]
+//!     timeout: u32,
+//! }
+//!
+//! let xml = "<server><host>a</host><!-- in seconds --><timeout>5</timeout></server>";
+//! let server = Server::from_str(xml).unwrap();
+//!
+//! assert_eq!(
+//!     server.comments,
+//!     vec![Positioned { position: 1, value: " in seconds ".into() }]
+//! );
+//! assert_eq!(server.to_string().unwrap(), xml);
+//! ```
+//!
+//! ### `#[xml(pi = "")]`
+//!
+//! Specifies that a struct field holds the content of the processing
+//! instructions with the given target between child elements, like
+//! `#[xml(comment)]`.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::{XmlRead, XmlWrite};
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "config")]
+//! struct Config<'a> {
+//!     #[xml(comment)]
+//!     comments: Vec<Cow<'a, str>>,
+//!     #[xml(pi = "app")]
+//!     app: Option<Cow<'a, str>>,
+//!     #[xml(flatten_text = "name")]
+//!     name: Cow<'a, str>,
+//! }
+//!
+//! let config = Config::from_str(
+//!     "<config><!-- edit with care --><?app v2?><name>a<!-- ! --></name></config>",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(
+//!     config,
+//!     Config {
+//!         comments: vec![" edit with care ".into()],
+//!         app: Some("v2".into()),
+//!         name: "a".into(),
+//!     }
+//! );
+//!
+//! assert_eq!(
+//!     config.to_string().unwrap(),
+//!     "<config><!-- edit with care --><?app v2?><name>a</name></config>"
+//! );
+//! ```
+//!
+//! ### `#[xml(any_attr)]`
+//!
+//! Specifies that a struct field collects the attributes no other field
+//! claims, keyed by their name as written. Should be a map or a `Vec` of
+//! pairs. Namespace declarations are collected too, and all of them are
+//! written back after the declared attributes.
+//!
+//! ### `#[xml(any_element)]`
+//!
+//! Specifies that a struct field collects the child elements no other field
+//! claims, as [`dom::Element`]s. Should be a `Vec`. They're written back
+//! after the declared children, unless the items are [`Positioned`] like
+//! those of a `#[xml(comment)]` field, which puts each one back at its
+//! place among the children.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::dom::Element;
+//! use hard_xml::{Positioned, XmlRead, XmlWrite};
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "item")]
+//! struct Item<'a> {
+//!     #[xml(attr = "id")]
+//!     id: u32,
+//!     #[xml(any_attr)]
+//!     other_attrs: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+//!     #[xml(flatten_text = "name")]
+//!     name: Cow<'a, str>,
+//!     #[xml(any_element)]
+//!     other_elements: Vec<Element>,
+//! }
+//!
+//! let item = Item::from_str(r#"<item id="1" x="y"><name>a</name><extra/></item>"#).unwrap();
+//!
+//! assert_eq!(item.other_attrs, vec![("x".into(), "y".into())]);
+//! assert_eq!(item.other_elements, vec![Element::new("extra")]);
+//!
+//! assert_eq!(
+//!     item.to_string().unwrap(),
+//!     r#"<item id="1" x="y"><name>a</name><extra/></item>"#
+//! );
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "item")]
+//! struct OrderedItem<'a> {
+//!     #[xml(flatten_text = "name")]
+//!     name: Cow<'a, str>,
+//!     #[xml(any_element)]
+//!     other_elements: Vec<Positioned<Element>>,
+//! }
+//!
+//! let xml = r#"<item><extra/><name>a</name></item>"#;
+//! let item = OrderedItem::from_str(xml).unwrap();
+//!
+//! assert_eq!(item.other_elements[0].position, 0);
+//! assert_eq!(item.to_string().unwrap(), xml);
+//! ```
+//!
 //! ### `#[xml(cdata)]`
 //!
 //! Specifies a CDATA text. Should be used together with `text` or `flatten_text`.
@@ -365,6 +948,26 @@
 //! );
 //! ```
 //!
+//! Text containing `]]>` is split into several CDATA sections. With
+//! `#[xml(cdata = "auto")]`, CDATA is only used for text containing markup
+//! characters, and escaped text is written where a single section can't hold
+//! it.
+//!
+//! ```rust
+//! # use hard_xml::XmlWrite;
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "parent")]
+//! struct Parent {
+//!     #[xml(flatten_text = "code", cdata = "auto")]
+//!     code: Vec<String>,
+//! }
+//!
+//! assert_eq!(
+//!     (Parent { code: vec!["a".into(), "a < b".into(), "]]>".into()] }).to_string().unwrap(),
+//!     r#"<parent><code>a</code><code><![CDATA[a < b]]></code><code>]]&gt;</code></parent>"#
+//! );
+//! ```
+//!
 //! ### `#[xml(default)]`
 //!
 //! Use `Default::default()` if the value is not present when reading.
@@ -390,6 +993,188 @@
 //!     Root { attr: true }
 //! );
 //! ```
+//!
+//! ### `#[xml(default = "")]` and `#[xml(default_str = "")]`
+//!
+//! Call the given function, or parse the given string like a value found in
+//! the document, if the value is not present when reading.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::XmlRead;
+//!
+//! #[derive(XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "root")]
+//! struct Root<'a> {
+//!     #[xml(default_str = "30", attr = "timeout")]
+//!     timeout: u32,
+//!     #[xml(default = "default_unit", attr = "unit")]
+//!     unit: Cow<'a, str>,
+//! }
+//!
+//! fn default_unit() -> Cow<'static, str> {
+//!     "px".into()
+//! }
+//!
+//! assert_eq!(
+//!     Root::from_str(r#"<root/>"#).unwrap(),
+//!     Root { timeout: 30, unit: "px".into() }
+//! );
+//! ```
+//!
+//! ### `#[xml(skip_default)]` and `#[xml(skip_serializing_if = "")]`
+//!
+//! Leave out an `attr`, `child` or `flatten_text` field when writing, if it
+//! equals its default or the given function returns `true` for it. `skip_default`
+//! goes together with one of the `default` attributes, so the value still reads
+//! back. With `default_str`, the value is left out if it's written as the string.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::XmlWrite;
+//!
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "root")]
+//! struct Root<'a> {
+//!     #[xml(default, skip_default, attr = "enabled")]
+//!     enabled: bool,
+//!     #[xml(skip_serializing_if = "str::is_empty", flatten_text = "note")]
+//!     note: Cow<'a, str>,
+//! }
+//!
+//! assert_eq!(
+//!     (Root { enabled: false, note: "".into() }).to_string().unwrap(),
+//!     r#"<root/>"#
+//! );
+//! ```
+//!
+//! ### `#[xml(skip)]`
+//!
+//! Neither read nor write the field, and fill it in with `Default::default()`
+//! when reading.
+//!
+//! ```rust
+//! use hard_xml::{XmlRead, XmlWrite};
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "root")]
+//! struct Root {
+//!     #[xml(attr = "attr")]
+//!     attr: u32,
+//!     #[xml(skip)]
+//!     cache: Option<String>,
+//! }
+//!
+//! let root = Root { attr: 1, cache: Some("a".into()) };
+//!
+//! assert_eq!(root.to_string().unwrap(), r#"<root attr="1"/>"#);
+//! assert_eq!(Root::from_str(r#"<root attr="1"/>"#).unwrap(), Root { attr: 1, cache: None });
+//! ```
+//!
+//! ### `#[xml(flatten)]` and `#[xml(fields)]`
+//!
+//! Reads and writes the fields of a struct with `#[xml(fields)]` as part of
+//! the element containing it, for attributes and child elements shared by
+//! several elements. Such a struct has no tag of its own, and only supports
+//! `attr`, `child`, `flatten_text` and `skip` fields.
+//!
+//! ```rust
+//! # use hard_xml::{XmlRead, XmlWrite};
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(fields)]
+//! struct Common {
+//!     #[xml(attr = "id")]
+//!     id: u32,
+//!     #[xml(flatten_text = "note")]
+//!     note: Option<String>,
+//! }
+//!
+//! #[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "item")]
+//! struct Item {
+//!     #[xml(flatten)]
+//!     common: Common,
+//!     #[xml(attr = "price")]
+//!     price: u32,
+//! }
+//!
+//! let item = Item {
+//!     common: Common { id: 1, note: Some("sale".into()) },
+//!     price: 10,
+//! };
+//! let xml = r#"<item id="1" price="10"><note>sale</note></item>"#;
+//!
+//! assert_eq!(item.to_string().unwrap(), xml);
+//! assert_eq!(Item::from_str(xml).unwrap(), item);
+//! ```
+//!
+//! ### `#[xml(ns = "")]`
+//!
+//! Match the element or attribute by namespace URI and local name instead of
+//! its literal name, so that whichever prefix the document binds is accepted.
+//! Can be used together with `tag`, `attr`, `child` and `flatten_text`.
+//!
+//! Unprefixed attributes never belong to a namespace, even when a default
+//! namespace is in scope.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::XmlRead;
+//!
+//! #[derive(XmlRead, PartialEq, Debug)]
+//! #[xml(tag = "feed", ns = "http://www.w3.org/2005/Atom")]
+//! struct Feed<'a> {
+//!     #[xml(attr = "lang", ns = "http://www.w3.org/XML/1998/namespace")]
+//!     lang: Option<Cow<'a, str>>,
+//!     #[xml(flatten_text = "title", ns = "http://www.w3.org/2005/Atom")]
+//!     title: Cow<'a, str>,
+//! }
+//!
+//! assert_eq!(
+//!     Feed::from_str(r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>a</title></feed>"#).unwrap(),
+//!     Feed { lang: None, title: "a".into() }
+//! );
+//!
+//! assert_eq!(
+//!     Feed::from_str(r#"<a:feed xmlns:a="http://www.w3.org/2005/Atom" xml:lang="en"><a:title>a</a:title></a:feed>"#).unwrap(),
+//!     Feed { lang: Some("en".into()), title: "a".into() }
+//! );
+//! ```
+//!
+//! ### `#[xml(prefix = "", default_ns = "")]`
+//!
+//! When writing, an element with `ns` uses `prefix` if given, otherwise any
+//! prefix already bound to its namespace, otherwise it declares the namespace
+//! as the default one. `default_ns` declares a default namespace for unprefixed
+//! descendants. Without it, descendants without `ns` are written with
+//! `xmlns=""` so they don't end up in the namespace of their parent.
+//!
+//! The root element declares every namespace used by the type and its children,
+//! so nested elements don't need to repeat them.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use hard_xml::XmlWrite;
+//!
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "document", ns = "urn:doc", prefix = "w")]
+//! struct Document<'a> {
+//!     #[xml(child = "p")]
+//!     paragraphs: Vec<Paragraph<'a>>,
+//! }
+//!
+//! #[derive(XmlWrite)]
+//! #[xml(tag = "p", ns = "urn:doc")]
+//! struct Paragraph<'a> {
+//!     #[xml(text)]
+//!     text: Cow<'a, str>,
+//! }
+//!
+//! assert_eq!(
+//!     (Document { paragraphs: vec![Paragraph { text: "a".into() }] }).to_string().unwrap(),
+//!     r#"<w:document xmlns:w="urn:doc"><w:p>a</w:p></w:document>"#
+//! );
+//! ```
 
 #[cfg(feature = "log")]
 mod log;
@@ -402,25 +1187,46 @@ pub mod lib {
     pub use log;
 }
 
+#[doc(hidden)]
+pub mod lenient {
+    pub use super::xml_read::{DefaultOf, NoDefault, WithDefault};
+}
+
+pub mod dom;
+#[cfg(feature = "tokio")]
+mod xml_async;
+mod xml_decode;
+mod xml_entity;
 mod xml_error;
 mod xml_escape;
 mod xml_read;
 mod xml_reader;
+mod xml_stream;
+mod xml_tree;
 mod xml_unescape;
 mod xml_write;
 mod xml_writer;
 
-pub use self::xml_error::{XmlError, XmlResult};
-pub use self::xml_read::{XmlRead, XmlReadOwned};
-pub use self::xml_reader::XmlReader;
-pub use self::xml_write::XmlWrite;
-pub use self::xml_writer::XmlWriter;
+#[cfg(feature = "tokio")]
+pub use self::xml_async::{XmlAsyncRead, XmlAsyncWrite};
+#[cfg(feature = "html-entities")]
+pub use self::xml_entity::HtmlEntities;
+pub use self::xml_entity::{EntityResolver, XmlEntityLimits};
+pub use self::xml_error::{UnknownValue, XmlError, XmlPath, XmlPosition, XmlReadError, XmlResult};
+pub use self::xml_read::{Positioned, XmlMixed, XmlRead, XmlReadFields, XmlReadOwned};
+pub use self::xml_reader::{XmlReader, XMLNS_NAMESPACE, XML_NAMESPACE, XSI_NAMESPACE};
+pub use self::xml_stream::XmlArena;
+pub use self::xml_write::{is_default, XmlWrite, XmlWriteFields};
+pub use self::xml_writer::{
+    XmlDeclaration, XmlEncoding, XmlFormat, XmlInvalidChars, XmlNamespaces, XmlWriter,
+};
 
-pub use hard_xml_derive::{XmlRead, XmlWrite};
+pub use hard_xml_derive::{XmlRead, XmlValue, XmlWrite};
 
 pub use xmlparser;
 
 pub mod utils {
-    pub use super::xml_escape::xml_escape;
+    pub use super::xml_decode::decode;
+    pub use super::xml_escape::{xml_escape, xml_escape_attribute, xml_escape_text};
     pub use super::xml_unescape::xml_unescape;
 }
@@ -0,0 +1,173 @@
+use std::future::Future;
+use std::io::{BufRead, Error as IOError, ErrorKind, Read, Result as IOResult};
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::task::{spawn, spawn_blocking};
+
+use crate::{XmlError, XmlReadError, XmlReadOwned, XmlResult, XmlWrite, XmlWriter};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+// number of chunks read ahead of the parser
+const CHUNK_COUNT: usize = 4;
+
+/// Future returned by [`XmlReadOwned::from_async_read`].
+///
+/// The value is parsed by [`XmlReadOwned::from_stream`] on a blocking thread,
+/// which is fed the input chunk by chunk while it is read.
+pub struct XmlAsyncRead<T> {
+    future: Pin<Box<dyn Future<Output = Result<T, XmlReadError>> + Send>>,
+}
+
+impl<T: XmlReadOwned + Send + 'static> XmlAsyncRead<T> {
+    pub(crate) fn new<R: AsyncRead + Unpin + Send + 'static>(read: R) -> Self {
+        XmlAsyncRead {
+            future: Box::pin(read_async(read)),
+        }
+    }
+}
+
+impl<T> Future for XmlAsyncRead<T> {
+    type Output = Result<T, XmlReadError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+async fn read_async<R, T>(read: R) -> Result<T, XmlReadError>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    T: XmlReadOwned + Send + 'static,
+{
+    let (sender, receiver) = channel(CHUNK_COUNT);
+    let parse = spawn_blocking(move || T::from_stream(ChunkRead::new(receiver)));
+    let reading = spawn(read_chunks(read, sender));
+
+    // stops reading as soon as the parser is done, even if the input isn't
+    let res = parse.await;
+    reading.abort();
+
+    match res {
+        Ok(res) => res,
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => resume_unwind(payload),
+            Err(err) => Err(XmlError::from(IOError::new(ErrorKind::Other, err)).into()),
+        },
+    }
+}
+
+async fn read_chunks<R: AsyncRead + Unpin>(mut read: R, sender: Sender<IOResult<Vec<u8>>>) {
+    loop {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        let res = match read.read_buf(&mut chunk).await {
+            Ok(0) => return,
+            Ok(_) => Ok(chunk),
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => Err(err),
+        };
+        let failed = res.is_err();
+        if sender.send(res).await.is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// The input of [`read_async`] on the parser's side, blocking until the next
+/// chunk has been read.
+struct ChunkRead {
+    receiver: Receiver<IOResult<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChunkRead {
+    fn new(receiver: Receiver<IOResult<Vec<u8>>>) -> Self {
+        ChunkRead {
+            receiver,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChunkRead {
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for ChunkRead {
+    fn fill_buf(&mut self) -> IOResult<&[u8]> {
+        while self.pos == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                None => break,
+            }
+        }
+        Ok(&self.chunk[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+/// Future returned by [`XmlWrite::to_async_writer`].
+///
+/// The value is written into memory when the future is first polled, and the
+/// output is then handed to the writer without blocking.
+pub struct XmlAsyncWrite<'w, T: ?Sized, W> {
+    value: &'w T,
+    write: &'w mut W,
+    // the output and how much of it has been written
+    output: Option<(Vec<u8>, usize)>,
+}
+
+impl<'w, T: ?Sized, W> XmlAsyncWrite<'w, T, W> {
+    pub(crate) fn new(value: &'w T, write: &'w mut W) -> Self {
+        XmlAsyncWrite {
+            value,
+            write,
+            output: None,
+        }
+    }
+}
+
+impl<T: XmlWrite + ?Sized, W: AsyncWrite + Unpin> Future for XmlAsyncWrite<'_, T, W> {
+    type Output = XmlResult<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let (output, written) = match &mut this.output {
+            Some(output) => output,
+            None => {
+                let mut writer = XmlWriter::new(Vec::new());
+                this.value.to_writer(&mut writer)?;
+                this.output.insert((writer.into_inner(), 0))
+            }
+        };
+
+        while *written < output.len() {
+            match ready!(Pin::new(&mut *this.write).poll_write(cx, &output[*written..]))? {
+                0 => return Poll::Ready(Err(IOError::from(ErrorKind::WriteZero).into())),
+                len => *written += len,
+            }
+        }
+
+        ready!(Pin::new(&mut *this.write).poll_flush(cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,129 @@
+use std::borrow::Cow;
+
+use crate::{XmlError, XmlResult};
+
+/// Decodes `bytes` into text that can be parsed.
+///
+/// The encoding is taken from the byte order mark, or else from the
+/// `encoding` pseudo-attribute of the XML declaration, and defaults to UTF-8.
+/// UTF-8 input is borrowed without its byte order mark. Other encodings are
+/// transcoded and require the `encoding` feature, failing with
+/// [`XmlError::UnsupportedEncoding`] otherwise.
+pub fn decode(bytes: &[u8]) -> XmlResult<Cow<'_, str>> {
+    let (label, declared, bytes) = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => ("UTF-8", false, rest),
+        [0xFF, 0xFE, rest @ ..] => ("UTF-16LE", false, rest),
+        [0xFE, 0xFF, rest @ ..] => ("UTF-16BE", false, rest),
+        // `<?` without a byte order mark
+        [0x3C, 0x00, 0x3F, 0x00, ..] => ("UTF-16LE", false, bytes),
+        [0x00, 0x3C, 0x00, 0x3F, ..] => ("UTF-16BE", false, bytes),
+        _ => (declared_encoding(bytes).unwrap_or("UTF-8"), true, bytes),
+    };
+
+    if label.eq_ignore_ascii_case("UTF-8") {
+        Ok(Cow::Borrowed(std::str::from_utf8(bytes)?))
+    } else {
+        transcode(label, declared, bytes)
+    }
+}
+
+// finds the value of `encoding` in the XML declaration of ASCII-compatible input
+fn declared_encoding(bytes: &[u8]) -> Option<&str> {
+    let declaration = bytes.strip_prefix(b"<?xml")?;
+    let end = memchr::memmem::find(declaration, b"?>")?;
+    let declaration = std::str::from_utf8(&declaration[..end]).ok()?;
+
+    let (_, value) = declaration.split_once("encoding")?;
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let (value, _) = value[1..].split_once(quote)?;
+
+    Some(value)
+}
+
+#[cfg(feature = "encoding")]
+fn transcode<'a>(label: &str, declared: bool, bytes: &'a [u8]) -> XmlResult<Cow<'a, str>> {
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+        XmlError::UnsupportedEncoding {
+            encoding: label.to_owned(),
+        }
+    })?;
+
+    // a declared UTF-16 encoding can't be right for ASCII-compatible input,
+    // which has most likely been converted to UTF-8 without updating it
+    let encoding = if declared {
+        encoding.output_encoding()
+    } else {
+        encoding
+    };
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .ok_or_else(|| XmlError::MalformedInput {
+            encoding: encoding.name().to_owned(),
+        })
+}
+
+#[cfg(not(feature = "encoding"))]
+fn transcode<'a>(label: &str, _declared: bool, _bytes: &'a [u8]) -> XmlResult<Cow<'a, str>> {
+    Err(XmlError::UnsupportedEncoding {
+        encoding: label.to_owned(),
+    })
+}
+
+#[test]
+fn decode_utf8() {
+    assert!(matches!(
+        decode(b"\xEF\xBB\xBF<root/>"),
+        Ok(Cow::Borrowed("<root/>"))
+    ));
+    assert!(matches!(
+        decode(br#"<?xml version="1.0" encoding='utf-8'?><root/>"#),
+        Ok(Cow::Borrowed(r#"<?xml version="1.0" encoding='utf-8'?><root/>"#))
+    ));
+    assert!(matches!(decode(b"<root>\xFF</root>"), Err(XmlError::Utf8(_))));
+}
+
+#[test]
+fn declared_encodings() {
+    assert_eq!(
+        declared_encoding(br#"<?xml version="1.0" encoding = "ISO-8859-1"?><root/>"#),
+        Some("ISO-8859-1")
+    );
+    assert_eq!(declared_encoding(br#"<?xml version="1.0"?><root/>"#), None);
+    assert_eq!(declared_encoding(br#"<root encoding="latin1"/>"#), None);
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn decode_legacy() {
+    assert_eq!(
+        decode(b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\xE9</a>").unwrap(),
+        "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\u{E9}</a>"
+    );
+    assert_eq!(
+        decode(b"\xFF\xFE<\0a\0/\0>\0").unwrap(),
+        "<a/>"
+    );
+    assert_eq!(
+        decode(b"\0<\0?\0x\0m\0l\0?\0>\0<\0a\0/\0>").unwrap(),
+        "<?xml?><a/>"
+    );
+    assert!(matches!(
+        decode(b"\xFE\xFF\0<\xD8\0"),
+        Err(XmlError::MalformedInput { .. })
+    ));
+    assert!(matches!(
+        decode(br#"<?xml version="1.0" encoding="EBCDIC"?><a/>"#),
+        Err(XmlError::UnsupportedEncoding { .. })
+    ));
+}
+
+#[cfg(not(feature = "encoding"))]
+#[test]
+fn decode_without_feature() {
+    assert!(matches!(
+        decode(b"\xFF\xFE<\0a\0/\0>\0"),
+        Err(XmlError::UnsupportedEncoding { encoding }) if encoding == "UTF-16LE"
+    ));
+}
@@ -13,11 +13,143 @@ pub enum XmlError {
     UnknownField { name: String, field: String },
     UnterminatedEntity { entity: String },
     UnrecognizedSymbol { symbol: String },
+    EntityLimit { entity: String },
+    InvalidChar { character: char },
+    UnsupportedEncoding { encoding: String },
+    MalformedInput { encoding: String },
     FromStr(Box<dyn Error + Send + Sync>),
 }
 
+/// An [`XmlError`] raised while reading, together with where it was raised.
+///
+/// Returned by entry points such as [`XmlRead::from_str`](crate::XmlRead::from_str),
+/// while `from_reader` returns the bare error. `?` converts it into the bare
+/// error.
+#[derive(Debug)]
+pub struct XmlReadError {
+    error: XmlError,
+    // boxed to keep `Result<T, XmlReadError>` small
+    location: Option<Box<(Option<XmlPosition>, Option<XmlPath>)>>,
+}
+
+/// A location in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XmlPosition {
+    /// Byte offset from the start of the input.
+    pub offset: usize,
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column in characters, starting at 1.
+    pub column: usize,
+}
+
+impl XmlPosition {
+    pub(crate) const START: XmlPosition = XmlPosition {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    /// Moves the position past `text`.
+    pub(crate) fn advance(&mut self, text: &str) {
+        let bytes = text.as_bytes();
+        self.offset += bytes.len();
+        match memchr::memrchr(b'\n', bytes) {
+            Some(index) => {
+                self.line += memchr::memchr_iter(b'\n', bytes).count();
+                self.column = text[index + 1..].chars().count() + 1;
+            }
+            None => self.column += text.chars().count(),
+        }
+    }
+}
+
+/// The elements and the fields an error was raised in, e.g.
+/// `/config/server[3]/@port` and `Config.servers.port`.
+///
+/// An index counts the elements read into the same `Vec` field, starting at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlPath {
+    pub elements: String,
+    pub fields: String,
+}
+
+impl std::fmt::Display for XmlPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (field {})", self.elements, self.fields)
+    }
+}
+
+impl XmlReadError {
+    pub(crate) fn new(error: XmlError, position: Option<XmlPosition>, path: Option<XmlPath>) -> Self {
+        XmlReadError {
+            error,
+            location: Some(Box::new((position, path))),
+        }
+    }
+
+    /// Returns the error without its location.
+    pub fn error(&self) -> &XmlError {
+        &self.error
+    }
+
+    /// Returns the error without its location.
+    pub fn into_error(self) -> XmlError {
+        self.error
+    }
+
+    /// Returns where in the input the error occurred, if known.
+    pub fn position(&self) -> Option<XmlPosition> {
+        self.location.as_ref().and_then(|location| location.0)
+    }
+
+    /// Returns the elements and fields the error was raised in, if known.
+    pub fn path(&self) -> Option<&XmlPath> {
+        self.location.as_ref().and_then(|location| location.1.as_ref())
+    }
+}
+
+impl From<XmlError> for XmlReadError {
+    fn from(error: XmlError) -> Self {
+        XmlReadError {
+            error,
+            location: None,
+        }
+    }
+}
+
+impl From<XmlReadError> for XmlError {
+    fn from(err: XmlReadError) -> Self {
+        err.error
+    }
+}
+
+impl Error for XmlReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl std::fmt::Display for XmlReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let Some(position) = self.position() {
+            write!(f, " at line {}, column {}", position.line, position.column)?;
+        }
+        if let Some(path) = self.path() {
+            write!(f, " in {}", path)?;
+        }
+        Ok(())
+    }
+}
+
 impl From<IOError> for XmlError {
     fn from(err: IOError) -> Self {
+        // `XmlWriter` wraps the errors it raises itself in I/O errors
+        if err.get_ref().map_or(false, |inner| inner.is::<XmlError>()) {
+            let inner = err.into_inner().unwrap();
+            return *inner.downcast::<XmlError>().unwrap();
+        }
         XmlError::IO(err)
     }
 }
@@ -78,7 +210,39 @@ impl std::fmt::Display for XmlError {
             }
             UnterminatedEntity { entity } => write!(f, "unterminated XML entity: {}", entity),
             UnrecognizedSymbol { symbol } => write!(f, "unrecognized XML symbol: {}", symbol),
+            EntityLimit { entity } => {
+                write!(f, "entity expansion limit exceeded by &{};", entity)
+            }
+            InvalidChar { character } => {
+                write!(f, "character {:?} is not allowed in XML", character)
+            }
+            UnsupportedEncoding { encoding } => write!(f, "unsupported XML encoding: {}", encoding),
+            MalformedInput { encoding } => write!(f, "malformed input for encoding {}", encoding),
             FromStr(e) => write!(f, "error parsing XML value: {}", e),
         }
     }
 }
+
+/// The error of the `FromStr` implementation derived by `XmlValue`, for a
+/// string that isn't the value of any variant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownValue {
+    pub value: String,
+    pub expected: &'static [&'static str],
+}
+
+impl Error for UnknownValue {}
+
+impl std::fmt::Display for UnknownValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown value {:?}, expected ", self.value)?;
+        for (index, expected) in self.expected.iter().enumerate() {
+            match index {
+                0 => write!(f, "{:?}", expected)?,
+                _ if index + 1 == self.expected.len() => write!(f, " or {:?}", expected)?,
+                _ => write!(f, ", {:?}", expected)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,6 +2,8 @@ use jetscii::{bytes, BytesConst};
 use lazy_static::lazy_static;
 use std::borrow::Cow;
 
+use crate::XmlEncoding;
+
 pub fn xml_escape(raw: &str) -> Cow<'_, str> {
     lazy_static! {
         static ref ESCAPE_BYTES: BytesConst = bytes!(b'<', b'>', b'&', b'\'', b'"');
@@ -48,6 +50,55 @@ pub fn xml_escape(raw: &str) -> Cow<'_, str> {
     }
 }
 
+/// Escapes text content for writing in `encoding`.
+///
+/// Besides the markup characters, carriage returns are written as character
+/// references so they aren't turned into line feeds when read, as are
+/// characters `encoding` can't represent.
+pub fn xml_escape_text(raw: &str, encoding: XmlEncoding) -> Cow<'_, str> {
+    escape(raw, encoding, false)
+}
+
+/// Escapes an attribute value for writing in `encoding`.
+///
+/// Like [`xml_escape_text`], also writing tabs and line feeds as character
+/// references, which attribute-value normalization would turn into spaces.
+pub fn xml_escape_attribute(raw: &str, encoding: XmlEncoding) -> Cow<'_, str> {
+    escape(raw, encoding, true)
+}
+
+fn escape(raw: &str, encoding: XmlEncoding, attribute: bool) -> Cow<'_, str> {
+    // multi-byte characters only need a look if they may be unrepresentable
+    let needs_escape = raw.bytes().any(|b| match b {
+        b'<' | b'>' | b'&' | b'\'' | b'"' | b'\r' => true,
+        b'\t' | b'\n' => attribute,
+        0x80.. => encoding != XmlEncoding::Utf8,
+        _ => false,
+    });
+
+    if !needs_escape {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut result = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '\'' => result.push_str("&apos;"),
+            '"' => result.push_str("&quot;"),
+            '\r' => result.push_str("&#xD;"),
+            '\t' if attribute => result.push_str("&#x9;"),
+            '\n' if attribute => result.push_str("&#xA;"),
+            c if !encoding.can_encode(c) => result.push_str(&format!("&#x{:X};", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    Cow::Owned(result)
+}
+
 #[test]
 fn test_escape() {
     assert_eq!(xml_escape("< < <"), "&lt; &lt; &lt;");
@@ -56,3 +107,17 @@ fn test_escape() {
         "&lt;script&gt;alert(&apos;Hello XSS&apos;)&lt;/script&gt;"
     );
 }
+
+#[test]
+fn test_escape_for() {
+    assert_eq!(xml_escape_text("caf\u{E9} <\u{20AC}>", XmlEncoding::Utf8), "caf\u{E9} &lt;\u{20AC}&gt;");
+    assert_eq!(xml_escape_text("caf\u{E9} <\u{20AC}>", XmlEncoding::Latin1), "caf\u{E9} &lt;&#x20AC;&gt;");
+    assert_eq!(xml_escape_attribute("caf\u{E9} <\u{1F600}>", XmlEncoding::Ascii), "caf&#xE9; &lt;&#x1F600;&gt;");
+}
+
+#[test]
+fn test_escape_whitespace() {
+    assert!(matches!(xml_escape_text("a\tb\nc", XmlEncoding::Utf8), Cow::Borrowed(_)));
+    assert_eq!(xml_escape_text("a\tb\r\nc", XmlEncoding::Utf8), "a\tb&#xD;\nc");
+    assert_eq!(xml_escape_attribute("a\tb\r\nc", XmlEncoding::Utf8), "a&#x9;b&#xD;&#xA;c");
+}
@@ -1,21 +1,204 @@
-use crate::{XmlReader, XmlResult};
-use std::io::{BufReader, Read};
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::marker::PhantomData;
+
+#[cfg(feature = "tokio")]
+use crate::XmlAsyncRead;
+use crate::dom::Element;
+use crate::utils::decode;
+use crate::{XmlArena, XmlError, XmlReadError, XmlReader, XmlResult};
 
 pub trait XmlRead<'a>: Sized {
     fn from_reader(reader: &mut XmlReader<'a>) -> XmlResult<Self>;
 
-    fn from_str(text: &'a str) -> XmlResult<Self> {
+    fn from_str(text: &'a str) -> Result<Self, XmlReadError> {
+        let mut reader = XmlReader::new(text);
+        Self::from_reader(&mut reader).map_err(|err| reader.locate(err))
+    }
+
+    /// Parses `text`, collecting errors instead of stopping at the first one.
+    ///
+    /// Child elements which fail to parse are skipped, and fields which are
+    /// missing or invalid fall back to `Default` if their type implements
+    /// it. Returns `None` if no value could be built at all.
+    fn from_str_lenient(text: &'a str) -> (Option<Self>, Vec<XmlReadError>) {
         let mut reader = XmlReader::new(text);
-        Self::from_reader(&mut reader)
+        reader.set_lenient(true);
+        let res = Self::from_reader(&mut reader);
+        match res {
+            Ok(value) => (Some(value), reader.take_errors()),
+            Err(err) => {
+                let err = reader.locate(err);
+                let mut errors = reader.take_errors();
+                errors.push(err);
+                (None, errors)
+            }
+        }
+    }
+
+    fn from_buffer<R: std::io::BufRead + 'a>(
+        mut buf: R,
+        buffer: &'a mut String,
+    ) -> Result<Self, XmlReadError> {
+        buf.read_to_string(buffer).map_err(XmlError::from)?;
+        Self::from_str(buffer)
     }
 
-    fn from_buffer<R: std::io::BufRead + 'a>(mut buf: R, buffer: &'a mut String) -> XmlResult<Self> {
-        buf.read_to_string(buffer)?;
-        let mut reader = XmlReader::new(buffer);
-        Self::from_reader(&mut reader)
+    /// Parses `bytes` in the encoding named by its byte order mark or XML
+    /// declaration, see [`decode`](crate::utils::decode).
+    ///
+    /// UTF-8 input is parsed in place, other encodings are transcoded into
+    /// `buffer` first.
+    fn from_bytes(bytes: &'a [u8], buffer: &'a mut String) -> Result<Self, XmlReadError> {
+        let text = match decode(bytes)? {
+            Cow::Borrowed(text) => text,
+            Cow::Owned(text) => {
+                *buffer = text;
+                buffer
+            }
+        };
+        Self::from_str(text)
     }
+
+    /// Builds the value from an untyped element, see
+    /// [`XmlReader::from_element`].
+    ///
+    /// Text and attribute values are borrowed from `element` where possible,
+    /// anything else is allocated in `arena`.
+    fn from_element(element: &'a Element, arena: &'a XmlArena) -> Result<Self, XmlReadError> {
+        let mut reader = XmlReader::from_element(element, arena);
+        Self::from_reader(&mut reader).map_err(|err| reader.locate(err))
+    }
+}
+
+/// Items of a `#[xml(mixed)]` field, implemented by `#[derive(XmlRead)]` for
+/// enums with a `#[xml(text)]` variant.
+pub trait XmlMixed<'a>: XmlRead<'a> {
+    /// Builds the item for a run of text between elements.
+    fn from_text(text: Cow<'a, str>) -> XmlResult<Self>;
+
+    /// Returns whether the element `tag` is read into an item.
+    fn is_element(reader: &mut XmlReader<'a>, tag: &'a str) -> bool;
 }
 
-pub trait XmlReadOwned: for<'s> XmlRead<'s> {}
+/// A comment or processing instruction kept by a `#[xml(comment)]` or
+/// `#[xml(pi)]` field together with its place among the child elements, so
+/// that it is written back there.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Positioned<T> {
+    /// The number of child elements before it.
+    pub position: usize,
+    pub value: T,
+}
+
+/// The fields of a `#[xml(flatten)]` field, implemented by
+/// `#[derive(XmlRead)]` for structs with `#[xml(fields)]`.
+///
+/// Their attributes and child elements are read while reading the parent
+/// element, keeping the fields read so far in a `Builder`.
+pub trait XmlReadFields<'a>: Sized {
+    type Builder;
+
+    /// Returns a builder without any field read yet.
+    fn builder() -> Self::Builder;
+
+    /// Returns whether the attribute `key` is read into one of the fields.
+    fn is_attribute(reader: &mut XmlReader<'a>, key: &'a str) -> bool;
+
+    /// Reads the attribute `key`, which [`XmlReadFields::is_attribute`] accepted.
+    fn read_attribute(
+        builder: &mut Self::Builder,
+        reader: &mut XmlReader<'a>,
+        key: &'a str,
+        value: Cow<'a, str>,
+    ) -> XmlResult<()>;
+
+    /// Returns whether the element `tag` is read into one of the fields.
+    fn is_element(reader: &mut XmlReader<'a>, tag: &'a str) -> bool;
+
+    /// Reads the element `tag`, which [`XmlReadFields::is_element`]
+    /// accepted, from its start tag on.
+    fn read_element(
+        builder: &mut Self::Builder,
+        reader: &mut XmlReader<'a>,
+        tag: &'a str,
+    ) -> XmlResult<()>;
+
+    /// Builds the value once the parent element has been read.
+    fn finish(builder: Self::Builder, reader: &mut XmlReader<'a>) -> XmlResult<Self>;
+}
+
+pub trait XmlReadOwned: for<'s> XmlRead<'s> {
+    /// Parses the input incrementally, without keeping all of it in memory.
+    fn from_stream<R: BufRead>(read: R) -> Result<Self, XmlReadError> {
+        let arena = XmlArena::new();
+        let mut reader = XmlReader::from_stream(read, &arena);
+        Self::from_reader(&mut reader).map_err(|err| reader.locate(err))
+    }
+
+    /// Like [`XmlReadOwned::from_stream`], calling `progress` with the number
+    /// of bytes consumed so far.
+    fn from_stream_with_progress<R: BufRead, F: FnMut(u64)>(
+        read: R,
+        progress: F,
+    ) -> Result<Self, XmlReadError> {
+        let arena = XmlArena::new();
+        let mut reader = XmlReader::from_stream_with_progress(read, &arena, progress);
+        Self::from_reader(&mut reader).map_err(|err| reader.locate(err))
+    }
+
+    /// Like [`XmlReadOwned::from_stream`], reading the input from a tokio
+    /// `AsyncRead` while it is parsed on a blocking thread.
+    #[cfg(feature = "tokio")]
+    fn from_async_read<R>(read: R) -> XmlAsyncRead<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+        Self: Send + 'static,
+    {
+        XmlAsyncRead::new(read)
+    }
+}
 
 impl<T> XmlReadOwned for T where T: for<'s> XmlRead<'s> {}
+
+/// Picks `Some(T::default())` if `T: Default` and `None` otherwise, for
+/// `(&DefaultOf::<T>::new()).get()` with both traits in scope.
+#[doc(hidden)]
+pub struct DefaultOf<T>(PhantomData<T>);
+
+impl<T> DefaultOf<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        DefaultOf(PhantomData)
+    }
+}
+
+impl<T> Clone for DefaultOf<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DefaultOf<T> {}
+
+#[doc(hidden)]
+pub trait WithDefault<T> {
+    fn get(self) -> Option<T>;
+}
+
+impl<T: Default> WithDefault<T> for &DefaultOf<T> {
+    fn get(self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait NoDefault<T> {
+    fn get(self) -> Option<T>;
+}
+
+impl<T> NoDefault<T> for DefaultOf<T> {
+    fn get(self) -> Option<T> {
+        None
+    }
+}
@@ -1,78 +1,635 @@
 use std::borrow::Cow;
-use std::iter::{Iterator, Peekable};
+use std::collections::VecDeque;
+use std::io::BufRead;
 
 use xmlparser::ElementEnd;
-use xmlparser::Error;
+use xmlparser::EntityDefinition;
+use xmlparser::StrSpan;
+use xmlparser::TextPos;
 use xmlparser::Token;
 use xmlparser::Tokenizer;
 
-use crate::xml_unescape::xml_unescape;
-use crate::{XmlError, XmlResult};
+use crate::dom::Element;
+use crate::xml_entity::Entities;
+use crate::xml_stream::XmlStream;
+use crate::xml_tree::XmlTree;
+use crate::{
+    EntityResolver, XmlArena, XmlEntityLimits, XmlError, XmlPath, XmlPosition, XmlReadError, XmlResult,
+};
+
+/// The namespace that the `xml` prefix is bound to by definition.
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// The namespace that the `xmlns` prefix is bound to by definition.
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// The XML Schema instance namespace, usually bound to `xsi`.
+pub const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
 
 /// Xml Reader
 ///
 /// It behaves almost exactly like `xmlparser::Tokenizer::from("...").peekable()`
 /// but with some helper functions.
+///
+/// The reader also keeps track of the `xmlns` and `xmlns:prefix` declarations
+/// in scope, so element and attribute names can be resolved to their
+/// namespace with [`XmlReader::resolve_element`] and
+/// [`XmlReader::resolve_attribute`].
+///
+/// Besides a string, the input can be any `BufRead`, see
+/// [`XmlReader::from_stream`], or an untyped element tree, see
+/// [`XmlReader::from_element`].
+///
+/// Entities declared in the internal subset of the document type declaration
+/// are expanded in text and attribute values, as are the ones supplied by an
+/// [`EntityResolver`].
 pub struct XmlReader<'a> {
-    tokenizer: Peekable<Tokenizer<'a>>,
+    source: Source<'a>,
+    lookahead: VecDeque<Entry<'a>>,
+    depth: usize,
+    namespaces: Vec<Namespace<'a>>,
+    position: XmlPosition,
+    path: Vec<PathNode<'a>>,
+    // where the error being returned was raised, taken when it is first
+    // unwound and dropped once another token is read
+    error_location: Option<(XmlPosition, Option<XmlPath>)>,
+    lenient: bool,
+    errors: Vec<XmlReadError>,
+    entities: Entities<'a>,
+}
+
+enum Source<'a> {
+    Str {
+        text: &'a str,
+        tokenizer: Tokenizer<'a>,
+        // position of the last token read from the tokenizer
+        cursor: XmlPosition,
+    },
+    Stream(Box<XmlStream<'a>>),
+    Tree(Box<XmlTree<'a>>),
+}
+
+impl<'a> Source<'a> {
+    // tokens read from a stream or a tree may refer to a payload instead of
+    // the input, which has to be moved somewhere before they are handed out
+    fn materialize(&self, entry: &mut Entry<'a>) {
+        match self {
+            Source::Str { .. } => {}
+            Source::Stream(stream) => stream.materialize(entry),
+            Source::Tree(tree) => tree.materialize(entry),
+        }
+    }
+}
+
+/// A token and, for tokens read from a stream or a tree, the text or
+/// attribute value it refers to.
+pub(crate) struct Entry<'a> {
+    pub(crate) token: Result<Token<'a>, XmlError>,
+    pub(crate) payload: Option<String>,
+    pub(crate) position: XmlPosition,
+}
+
+/// A breadcrumb left by derived `from_reader` implementations, so errors can
+/// tell where they were raised.
+enum PathNode<'a> {
+    Element { tag: &'a str, ty: &'static str },
+    // `index` is the position of the element in a `Vec` field, starting at 1
+    Field { name: &'static str, index: Option<usize> },
+    Attribute(&'a str),
+    // an element whose text is read into the current field
+    Text(&'a str),
+}
+
+/// A namespace declaration and the depth of the element it was declared on.
+struct Namespace<'a> {
+    depth: usize,
+    prefix: &'a str,
+    uri: &'a str,
 }
 
 impl<'a> XmlReader<'a> {
     #[inline]
     pub fn new(text: &'a str) -> XmlReader<'a> {
+        XmlReader::with_source(Source::Str {
+            text,
+            tokenizer: Tokenizer::from(text),
+            cursor: XmlPosition::START,
+        })
+    }
+
+    /// Creates a reader which pulls its input from `read` as needed instead
+    /// of requiring the whole document up front.
+    ///
+    /// Values returned by the helper functions are owned rather than
+    /// borrowed. Tokens returned by [`XmlReader::next`] and
+    /// [`XmlReader::peek`] are copied into `arena`, so they should be avoided
+    /// for text and attributes if memory use matters.
+    pub fn from_stream<R: BufRead + 'a>(read: R, arena: &'a XmlArena) -> XmlReader<'a> {
+        XmlReader::with_source(Source::Stream(Box::new(XmlStream::new(read, arena))))
+    }
+
+    /// Like [`XmlReader::from_stream`], calling `progress` with the total
+    /// number of bytes consumed from `read` whenever more input is read.
+    pub fn from_stream_with_progress<R, F>(
+        read: R,
+        arena: &'a XmlArena,
+        progress: F,
+    ) -> XmlReader<'a>
+    where
+        R: BufRead + 'a,
+        F: FnMut(u64) + 'a,
+    {
+        let mut stream = XmlStream::new(read, arena);
+        stream.set_progress(progress);
+        XmlReader::with_source(Source::Stream(Box::new(stream)))
+    }
+
+    /// Creates a reader which walks `element` instead of parsing text.
+    ///
+    /// Names, text and attribute values are borrowed from `element` where
+    /// possible, anything else is allocated in `arena`. Errors carry no
+    /// position.
+    pub fn from_element(element: &'a Element, arena: &'a XmlArena) -> XmlReader<'a> {
+        XmlReader::with_source(Source::Tree(Box::new(XmlTree::new(element, arena))))
+    }
+
+    fn with_source(source: Source<'a>) -> XmlReader<'a> {
         XmlReader {
-            tokenizer: Tokenizer::from(text).peekable(),
+            source,
+            lookahead: VecDeque::new(),
+            depth: 0,
+            namespaces: Vec::new(),
+            position: XmlPosition::START,
+            path: Vec::new(),
+            error_location: None,
+            lenient: false,
+            errors: Vec::new(),
+            entities: Entities::default(),
+        }
+    }
+
+    /// Expands references to entities which are neither predefined nor
+    /// declared in the document with `resolver`.
+    #[inline]
+    pub fn set_entity_resolver<R: EntityResolver + 'a>(&mut self, resolver: R) {
+        self.entities.set_resolver(Box::new(resolver));
+    }
+
+    /// Replaces the default limits on entity expansion.
+    #[inline]
+    pub fn set_entity_limits(&mut self, limits: XmlEntityLimits) {
+        self.entities.set_limits(limits);
+    }
+
+    /// Makes derived `from_reader` implementations collect the errors they
+    /// can recover from instead of returning them, see
+    /// [`XmlRead::from_str_lenient`](crate::XmlRead::from_str_lenient).
+    #[inline]
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    /// Returns the errors collected in lenient mode so far.
+    #[inline]
+    pub fn take_errors(&mut self) -> Vec<XmlReadError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn element_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Records `err` in lenient mode, returns it otherwise.
+    #[doc(hidden)]
+    pub fn recover(&mut self, err: XmlError) -> XmlResult<()> {
+        if !self.lenient || is_fatal(&err) {
+            return Err(err);
+        }
+        let err = self.locate(err);
+        self.errors.push(err);
+        Ok(())
+    }
+
+    /// Like [`XmlReader::recover`], then skips the rest of the element which
+    /// started at `depth`.
+    #[doc(hidden)]
+    pub fn recover_element(&mut self, err: XmlError, depth: usize) -> XmlResult<()> {
+        self.recover(err)?;
+        while self.depth > depth {
+            match self.next_entry() {
+                Some(entry) => {
+                    entry.token?;
+                }
+                None => return Err(XmlError::UnexpectedEof),
+            }
+        }
+        Ok(())
+    }
+
+    /// Records `err` and returns `default` in lenient mode if there is one,
+    /// returns `err` otherwise.
+    #[doc(hidden)]
+    pub fn recover_missing<T>(&mut self, err: XmlError, default: Option<T>) -> XmlResult<T> {
+        match default {
+            Some(default) if self.lenient => {
+                self.recover(err)?;
+                Ok(default)
+            }
+            _ => Err(err),
+        }
+    }
+
+    /// Returns the position of the token read last.
+    #[inline]
+    pub fn position(&self) -> XmlPosition {
+        self.position
+    }
+
+    /// Attaches where `err` was raised, or the position of the token read
+    /// last and the elements being read if it hasn't been unwound.
+    ///
+    /// Readers created by [`XmlReader::from_element`] leave out the position.
+    #[inline]
+    pub fn locate(&mut self, err: XmlError) -> XmlReadError {
+        let (position, path) = match self.error_location.take() {
+            Some(location) => location,
+            None => (self.position, self.path()),
+        };
+        let position = Some(position).filter(|_| !matches!(self.source, Source::Tree(_)));
+        XmlReadError::new(err, position, path)
+    }
+
+    fn path(&self) -> Option<XmlPath> {
+        let mut elements = String::new();
+        let mut fields = String::new();
+        let mut index = None;
+
+        for node in &self.path {
+            let step = match node {
+                PathNode::Element { tag, ty } => {
+                    if fields.is_empty() {
+                        fields.push_str(ty);
+                    }
+                    tag
+                }
+                PathNode::Field { name, index: i } => {
+                    fields.push('.');
+                    fields.push_str(name);
+                    index = *i;
+                    continue;
+                }
+                PathNode::Attribute(key) => {
+                    elements.push_str("/@");
+                    elements.push_str(key);
+                    continue;
+                }
+                PathNode::Text(tag) => tag,
+            };
+
+            elements.push('/');
+            elements.push_str(step);
+            if let Some(index) = index.take() {
+                elements.push_str(&format!("[{}]", index));
+            }
+        }
+
+        if elements.is_empty() {
+            None
+        } else {
+            Some(XmlPath { elements, fields })
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn path_depth(&self) -> usize {
+        self.path.len()
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn enter_element(&mut self, tag: &'a str, ty: &'static str) {
+        self.path.push(PathNode::Element { tag, ty });
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn enter_field(&mut self, name: &'static str, index: Option<usize>) {
+        self.path.push(PathNode::Field { name, index });
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn enter_attribute(&mut self, key: &'a str) {
+        self.path.push(PathNode::Attribute(key));
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn enter_text(&mut self, tag: &'a str) {
+        self.path.push(PathNode::Text(tag));
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    /// Remembers where `err` was raised and drops the breadcrumbs left since
+    /// `depth`.
+    #[doc(hidden)]
+    pub fn unwind(&mut self, err: XmlError, depth: usize) -> XmlError {
+        if self.error_location.is_none() {
+            self.error_location = Some((self.position, self.path()));
         }
+        self.path.truncate(depth);
+        err
     }
 
     #[inline]
-    pub fn next(&mut self) -> Option<Result<Token<'a>, Error>> {
-        self.tokenizer.next()
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<XmlResult<Token<'a>>> {
+        let mut entry = self.next_entry()?;
+        self.source.materialize(&mut entry);
+        Some(entry.token)
     }
 
     #[inline]
-    pub fn peek(&mut self) -> Option<&Result<Token<'a>, Error>> {
-        self.tokenizer.peek()
+    pub fn peek(&mut self) -> Option<&XmlResult<Token<'a>>> {
+        self.peek_entry(0)?;
+        let entry = self.lookahead.front_mut()?;
+        self.source.materialize(entry);
+        Some(&entry.token)
+    }
+
+    fn next_entry(&mut self) -> Option<Entry<'a>> {
+        let entry = match self.lookahead.pop_front() {
+            Some(entry) => entry,
+            None => self.source_next()?,
+        };
+
+        self.position = entry.position;
+        self.error_location = None;
+
+        match entry.token {
+            Ok(Token::ElementStart { .. }) => {
+                self.depth += 1;
+
+                // declarations apply to the element they appear on, so they
+                // are registered before its attributes are read
+                let mut index = 0;
+                while let Some(&Ok(Token::Attribute {
+                    prefix,
+                    local,
+                    value,
+                    ..
+                })) = self.peek_entry(index).map(|entry| &entry.token)
+                {
+                    if let Some(prefix) = declared_prefix(prefix, local) {
+                        self.namespaces.push(Namespace {
+                            depth: self.depth,
+                            prefix,
+                            uri: value.as_str(),
+                        });
+                    }
+                    index += 1;
+                }
+            }
+            Ok(Token::ElementEnd {
+                end: ElementEnd::Empty | ElementEnd::Close(_, _),
+                ..
+            }) => {
+                while matches!(self.namespaces.last(), Some(ns) if ns.depth >= self.depth) {
+                    self.namespaces.pop();
+                }
+                self.depth = self.depth.saturating_sub(1);
+            }
+            Ok(Token::EntityDeclaration {
+                name,
+                definition: EntityDefinition::EntityValue(value),
+                span,
+            }) => {
+                // parameter entities only apply within the DTD
+                if !span.as_str()["<!ENTITY".len()..].trim_start().starts_with('%') {
+                    self.entities.declare(name.as_str(), value.as_str());
+                }
+            }
+            _ => (),
+        }
+
+        Some(entry)
+    }
+
+    fn peek_entry(&mut self, index: usize) -> Option<&Entry<'a>> {
+        while self.lookahead.len() <= index {
+            let entry = self.source_next()?;
+            self.lookahead.push_back(entry);
+        }
+        self.lookahead.get(index)
+    }
+
+    fn source_next(&mut self) -> Option<Entry<'a>> {
+        match &mut self.source {
+            Source::Str {
+                text,
+                tokenizer,
+                cursor,
+            } => {
+                let token = tokenizer.next()?;
+                let start = match &token {
+                    Ok(token) => token.span().start(),
+                    Err(err) => error_offset(text, err.pos()),
+                };
+                if start > cursor.offset {
+                    cursor.advance(&text[cursor.offset..start]);
+                }
+                Some(Entry {
+                    token: token.map_err(XmlError::from),
+                    payload: None,
+                    position: *cursor,
+                })
+            }
+            Source::Stream(stream) => stream.next(),
+            Source::Tree(tree) => tree.next(),
+        }
+    }
+
+    /// Returns the namespace URI bound to `prefix` in the current scope.
+    ///
+    /// An empty prefix looks up the default namespace.
+    #[inline]
+    pub fn lookup_namespace(&self, prefix: &str) -> Option<&'a str> {
+        match prefix {
+            "xml" => return Some(XML_NAMESPACE),
+            "xmlns" => return Some(XMLNS_NAMESPACE),
+            _ => {}
+        }
+
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|ns| ns.prefix == prefix)
+            .map(|ns| ns.uri)
+            .filter(|uri| !uri.is_empty())
+    }
+
+    /// Splits an attribute key returned by [`XmlReader::find_attribute`]
+    /// into its namespace URI and local name.
+    ///
+    /// Unprefixed attributes are never in a namespace.
+    #[inline]
+    pub fn resolve_attribute(&self, key: &'a str) -> (Option<&'a str>, &'a str) {
+        match key.split_once(':') {
+            Some((prefix, local)) => (self.lookup_namespace(prefix), local),
+            None => (None, key),
+        }
+    }
+
+    /// Splits an element name returned by [`XmlReader::find_element_start`]
+    /// into its namespace URI and local name.
+    ///
+    /// Declarations on the element itself are taken into account even though
+    /// its start tag hasn't been consumed yet.
+    #[inline]
+    pub fn resolve_element(&mut self, name: &'a str) -> (Option<&'a str>, &'a str) {
+        self.resolve_qname(name)
+    }
+
+    /// Splits a qualified name found in the content of the element whose
+    /// start tag is next, such as the value of an `xsi:type` attribute, into
+    /// its namespace URI and local name.
+    ///
+    /// Like element names, unprefixed names are in the default namespace.
+    pub fn resolve_qname<'v>(&mut self, name: &'v str) -> (Option<&'a str>, &'v str) {
+        let (prefix, local) = name.split_once(':').unwrap_or(("", name));
+
+        if let Some(Ok(Token::ElementStart { .. })) = self.peek_entry(0).map(|entry| &entry.token) {
+            let mut index = 1;
+            while let Some(&Ok(Token::Attribute {
+                prefix: attr_prefix,
+                local: attr_local,
+                value,
+                ..
+            })) = self.peek_entry(index).map(|entry| &entry.token)
+            {
+                if declared_prefix(attr_prefix, attr_local) == Some(prefix) {
+                    return (Some(value.as_str()).filter(|uri| !uri.is_empty()), local);
+                }
+                index += 1;
+            }
+        }
+
+        (self.lookup_namespace(prefix), local)
+    }
+
+    /// Returns the value of the attribute `key` of the element whose start
+    /// tag is next, without consuming anything.
+    pub fn peek_attribute(&mut self, key: &str) -> XmlResult<Option<Cow<'a, str>>> {
+        if let Some(Ok(Token::ElementStart { .. })) = self.peek_entry(0).map(|entry| &entry.token) {
+            let mut index = 1;
+            while let Some(Entry {
+                token:
+                    Ok(Token::Attribute {
+                        prefix,
+                        local,
+                        value,
+                        span,
+                    }),
+                payload,
+                ..
+            }) = self.peek_entry(index)
+            {
+                if &span.as_str()[..qualified_len(*prefix, *local)] == key {
+                    let raw = raw(payload.clone(), *value);
+                    return unescape(&self.entities, raw).map(Some);
+                }
+                index += 1;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Like [`XmlReader::peek_attribute`], for the attribute `local` in
+    /// namespace `ns` whatever its prefix, including prefixes declared by the
+    /// start tag itself.
+    pub fn peek_attribute_ns(&mut self, ns: &str, local: &str) -> XmlResult<Option<Cow<'a, str>>> {
+        let mut candidates = Vec::new();
+        let mut declared = Vec::new();
+
+        if let Some(Ok(Token::ElementStart { .. })) = self.peek_entry(0).map(|entry| &entry.token) {
+            let mut index = 1;
+            while let Some(Entry {
+                token:
+                    Ok(Token::Attribute {
+                        prefix,
+                        local: attr_local,
+                        value,
+                        ..
+                    }),
+                payload,
+                ..
+            }) = self.peek_entry(index)
+            {
+                if let Some(declared_prefix) = declared_prefix(*prefix, *attr_local) {
+                    declared.push((declared_prefix, value.as_str()));
+                } else if !prefix.is_empty() && attr_local.as_str() == local {
+                    candidates.push((prefix.as_str(), raw(payload.clone(), *value)));
+                }
+                index += 1;
+            }
+        }
+
+        for (prefix, raw) in candidates {
+            let uri = match declared.iter().find(|(declared, _)| *declared == prefix) {
+                Some((_, uri)) => Some(*uri),
+                None => self.lookup_namespace(prefix),
+            };
+            if uri == Some(ns) {
+                return unescape(&self.entities, raw).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns whether the element whose start tag is next has
+    /// `xsi:nil="true"`, without consuming anything.
+    pub fn peek_nil(&mut self) -> bool {
+        matches!(
+            self.peek_attribute_ns(XSI_NAMESPACE, "nil"),
+            Ok(Some(value)) if matches!(value.trim(), "true" | "1")
+        )
     }
 
     #[inline]
     pub fn read_text(&mut self, end_tag: &str) -> XmlResult<Cow<'a, str>> {
         let mut res = Cow::Borrowed("");
 
-        while let Some(token) = self.next() {
-            match token? {
+        while let Some(Entry { token, payload, .. }) = self.next_entry() {
+            let text = match token? {
                 Token::ElementEnd {
                     end: ElementEnd::Open,
                     ..
                 }
-                | Token::Attribute { .. } => (),
-                Token::Text { text } => {
-                    let text = xml_unescape(text.as_str())?;
-                    if res.is_empty() {
-                        res = text;
-                    } else {
-                        res.to_mut().push_str(&text);
-                    }
-                }
-                Token::Cdata { text, .. } => {
-                    if res.is_empty() {
-                        res = Cow::Borrowed(text.as_str());
-                    } else {
-                        res.to_mut().push_str(&text);
-                    }
-                }
+                | Token::Attribute { .. }
+                | Token::Comment { .. }
+                | Token::ProcessingInstruction { .. } => continue,
+                Token::Text { text } => unescape(&self.entities, raw(payload, text))?,
+                Token::Cdata { text, .. } => raw(payload, text),
                 Token::ElementEnd {
-                    end: ElementEnd::Close(_, _),
-                    span,
+                    end: ElementEnd::Close(prefix, local),
+                    ..
                 } => {
-                    let span = span.as_str(); // </tag>
-                    let tag = &span[2..span.len() - 1]; // remove `</` and `>`
-                    if end_tag == tag {
+                    if is_name(prefix, local, end_tag) {
                         break;
                     } else {
                         return Err(XmlError::TagMismatch {
                             expected: end_tag.to_owned(),
-                            found: tag.to_owned(),
+                            found: qualified_name(prefix, local),
                         });
                     }
                 }
@@ -87,28 +644,115 @@ impl<'a> XmlReader<'a> {
                         token: format!("{:?}", token),
                     });
                 }
+            };
+
+            if res.is_empty() {
+                res = text;
+            } else {
+                res.to_mut().push_str(&text);
             }
         }
 
         Ok(res)
     }
 
+    /// Reads the text and CDATA up to the next element start or end tag,
+    /// for mixed content. Returns `None` if there is none.
+    #[doc(hidden)]
+    pub fn read_mixed_text(&mut self) -> XmlResult<Option<Cow<'a, str>>> {
+        let mut res: Option<Cow<'a, str>> = None;
+
+        while let Some(entry) = self.peek_entry(0) {
+            match &entry.token {
+                Ok(Token::Text { .. }) | Ok(Token::Cdata { .. }) => {
+                    // we have call .peek_entry() above, and it's safe to use unwrap
+                    let Entry { token, payload, .. } = self.next_entry().unwrap();
+                    let text = match token? {
+                        Token::Text { text } => unescape(&self.entities, raw(payload, text))?,
+                        Token::Cdata { text, .. } => raw(payload, text),
+                        _ => unreachable!(),
+                    };
+
+                    match &mut res {
+                        Some(res) => res.to_mut().push_str(&text),
+                        None => res = Some(text),
+                    }
+                }
+                Ok(Token::Comment { .. }) | Ok(Token::ProcessingInstruction { .. }) => {
+                    self.next_entry();
+                }
+                _ => break,
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Expands the references in the text of a token returned by `next`.
+    pub(crate) fn unescape(&self, raw: &'a str) -> XmlResult<Cow<'a, str>> {
+        self.entities.unescape(raw)
+    }
+
+    /// Returns the next comment or processing instruction before the next
+    /// element start or end tag, skipping text.
+    #[doc(hidden)]
+    pub fn find_misc(&mut self) -> XmlResult<Option<Token<'a>>> {
+        while let Some(entry) = self.peek_entry(0) {
+            match &entry.token {
+                Ok(Token::Comment { .. }) | Ok(Token::ProcessingInstruction { .. }) => {
+                    return self.next().transpose();
+                }
+                Ok(Token::Text { .. }) | Ok(Token::Cdata { .. }) => {
+                    self.next_entry();
+                }
+                _ => break,
+            }
+        }
+
+        Ok(None)
+    }
+
     #[inline]
     pub fn read_till_element_start(&mut self, end_tag: &str) -> XmlResult<()> {
-        while let Some(token) = self.next() {
-            match token? {
-                Token::ElementStart { span, .. } => {
+        self.skip_till_element_start(|_, name, _, _| name == end_tag)?;
+        Ok(())
+    }
+
+    /// Like [`XmlReader::read_till_element_start`], but matches the element
+    /// by namespace URI and local name rather than by its literal name.
+    ///
+    /// Returns the element name as written in the document, which is what
+    /// its end tag has to be compared against.
+    #[inline]
+    pub fn read_till_element_start_ns(&mut self, ns: &str, local: &str) -> XmlResult<&'a str> {
+        self.skip_till_element_start(|reader, _, prefix, name| {
+            name == local && reader.lookup_namespace(prefix) == Some(ns)
+        })?
+        .ok_or(XmlError::UnexpectedEof)
+    }
+
+    fn skip_till_element_start<F>(&mut self, is_start: F) -> XmlResult<Option<&'a str>>
+    where
+        F: Fn(&Self, &str, &str, &str) -> bool,
+    {
+        while let Some(entry) = self.next_entry() {
+            match entry.token? {
+                Token::ElementStart {
+                    prefix,
+                    local,
+                    span,
+                } => {
                     let tag = &span.as_str()[1..];
-                    if end_tag == tag {
-                        break;
+                    if is_start(self, tag, prefix.as_str(), local.as_str()) {
+                        return Ok(Some(tag));
                     } else {
                         self.read_to_end(tag)?;
                     }
                 }
-                Token::ElementEnd { .. }
-                | Token::Attribute { .. }
-                | Token::Text { .. }
-                | Token::Cdata { .. } => {
+                token @ Token::ElementEnd { .. }
+                | token @ Token::Attribute { .. }
+                | token @ Token::Text { .. }
+                | token @ Token::Cdata { .. } => {
                     return Err(XmlError::UnexpectedToken {
                         token: format!("{:?}", token),
                     });
@@ -116,20 +760,30 @@ impl<'a> XmlReader<'a> {
                 _ => (),
             }
         }
-        Ok(())
+        Ok(None)
     }
 
     #[inline]
     pub fn find_attribute(&mut self) -> XmlResult<Option<(&'a str, Cow<'a, str>)>> {
-        if let Some(token) = self.tokenizer.peek() {
-            match token {
-                Ok(Token::Attribute { span, value, .. }) => {
-                    let value = value.as_str();
-                    let span = span.as_str(); // key="value"
-                    let key = &span[0..span.len() - value.len() - 3]; // remove `="`, value and `"`
-                    let value = xml_unescape(value)?;
-                    self.next();
-                    return Ok(Some((key, value)));
+        if let Some(entry) = self.peek_entry(0) {
+            match &entry.token {
+                Ok(Token::Attribute { .. }) => {
+                    if let Some(Entry {
+                        token:
+                            Ok(Token::Attribute {
+                                prefix,
+                                local,
+                                value,
+                                span,
+                            }),
+                        payload,
+                        ..
+                    }) = self.next_entry()
+                    {
+                        let key = &span.as_str()[..qualified_len(prefix, local)];
+                        let value = unescape(&self.entities, raw(payload, value))?;
+                        return Ok(Some((key, value)));
+                    }
                 }
                 Ok(Token::ElementEnd {
                     end: ElementEnd::Open,
@@ -145,8 +799,8 @@ impl<'a> XmlReader<'a> {
                     })
                 }
                 Err(_) => {
-                    // we have call .peek() above, and it's safe to use unwrap
-                    self.next().unwrap()?;
+                    // we have call .peek_entry() above, and it's safe to use unwrap
+                    self.next_entry().unwrap().token?;
                 }
             }
         }
@@ -156,36 +810,34 @@ impl<'a> XmlReader<'a> {
 
     #[inline]
     pub fn find_element_start(&mut self, end_tag: Option<&str>) -> XmlResult<Option<&'a str>> {
-        while let Some(token) = self.tokenizer.peek() {
-            match token {
+        while let Some(entry) = self.peek_entry(0) {
+            match &entry.token {
                 Ok(Token::ElementStart { span, .. }) => {
                     return Ok(Some(&span.as_str()[1..]));
                 }
                 Ok(Token::ElementEnd {
-                    end: ElementEnd::Close(_, _),
-                    span,
+                    end: ElementEnd::Close(prefix, local),
+                    ..
                 }) if end_tag.is_some() => {
                     let end_tag = end_tag.unwrap();
-                    let span = span.as_str(); // </tag>
-                    let tag = &span[2..span.len() - 1]; // remove `</` and `>`
-                    if tag == end_tag {
-                        self.next();
+                    if is_name(*prefix, *local, end_tag) {
+                        self.next_entry();
                         return Ok(None);
                     } else {
                         return Err(XmlError::TagMismatch {
                             expected: end_tag.to_owned(),
-                            found: tag.to_owned(),
+                            found: qualified_name(*prefix, *local),
                         });
                     }
                 }
-                Ok(Token::ElementEnd { .. }) | Ok(Token::Attribute { .. }) => {
+                Ok(token @ Token::ElementEnd { .. }) | Ok(token @ Token::Attribute { .. }) => {
                     return Err(XmlError::UnexpectedToken {
                         token: format!("{:?}", token),
                     })
                 }
                 _ => {
-                    // we have call .peek() above, and it's safe to use unwrap
-                    self.next().unwrap()?;
+                    // we have call .peek_entry() above, and it's safe to use unwrap
+                    self.next_entry().unwrap().token?;
                 }
             }
         }
@@ -195,8 +847,8 @@ impl<'a> XmlReader<'a> {
 
     #[inline]
     pub fn read_to_end(&mut self, end_tag: &str) -> XmlResult<()> {
-        while let Some(token) = self.next() {
-            match token? {
+        while let Some(entry) = self.next_entry() {
+            match entry.token? {
                 // if this element is emtpy, just return
                 Token::ElementEnd {
                     end: ElementEnd::Empty,
@@ -218,11 +870,11 @@ impl<'a> XmlReader<'a> {
 
         let mut depth = 1;
 
-        while let Some(token) = self.next() {
-            match token? {
-                Token::ElementStart { span, .. } if end_tag == &span.as_str()[1..] => {
-                    while let Some(token) = self.next() {
-                        match token? {
+        while let Some(entry) = self.next_entry() {
+            match entry.token? {
+                Token::ElementStart { prefix, local, .. } if is_name(prefix, local, end_tag) => {
+                    while let Some(entry) = self.next_entry() {
+                        match entry.token? {
                             Token::ElementEnd {
                                 end: ElementEnd::Empty,
                                 ..
@@ -252,9 +904,9 @@ impl<'a> XmlReader<'a> {
                     }
                 }
                 Token::ElementEnd {
-                    end: ElementEnd::Close(_, _),
-                    span,
-                } if end_tag == &span.as_str()[2..span.as_str().len() - 1] => {
+                    end: ElementEnd::Close(prefix, local),
+                    ..
+                } if is_name(prefix, local, end_tag) => {
                     depth -= 1;
                     if depth == 0 {
                         return Ok(());
@@ -268,6 +920,86 @@ impl<'a> XmlReader<'a> {
     }
 }
 
+/// Returns the text of a Text or Cdata token or the value of an Attribute
+/// token, which is only part of the input for string backed readers.
+fn raw(payload: Option<String>, span: StrSpan<'_>) -> Cow<'_, str> {
+    match payload {
+        Some(payload) => Cow::Owned(payload),
+        None => Cow::Borrowed(span.as_str()),
+    }
+}
+
+fn unescape<'r>(entities: &Entities<'_>, raw: Cow<'r, str>) -> XmlResult<Cow<'r, str>> {
+    match raw {
+        Cow::Borrowed(raw) => entities.unescape(raw),
+        Cow::Owned(raw) => {
+            let unescaped = match entities.unescape(&raw)? {
+                Cow::Owned(unescaped) => Some(unescaped),
+                Cow::Borrowed(_) => None,
+            };
+            Ok(Cow::Owned(unescaped.unwrap_or(raw)))
+        }
+    }
+}
+
+// errors after which the input can't be read any further
+fn is_fatal(err: &XmlError) -> bool {
+    matches!(
+        err,
+        XmlError::IO(_) | XmlError::Parser(_) | XmlError::Utf8(_) | XmlError::UnexpectedEof
+    )
+}
+
+// converts the row and column of a parser error into a byte offset of `text`
+pub(crate) fn error_offset(text: &str, pos: TextPos) -> usize {
+    let line_start = match pos.row {
+        0 | 1 => 0,
+        row => memchr::memchr_iter(b'\n', text.as_bytes())
+            .nth(row as usize - 2)
+            .map_or(text.len(), |index| index + 1),
+    };
+
+    text[line_start..]
+        .char_indices()
+        .nth((pos.col as usize).saturating_sub(1))
+        .map_or(text.len(), |(index, _)| line_start + index)
+}
+
+/// Returns the prefix declared by an `xmlns` or `xmlns:prefix` attribute,
+/// with the empty string standing for the default namespace.
+pub(crate) fn declared_prefix<'a>(prefix: StrSpan<'a>, local: StrSpan<'a>) -> Option<&'a str> {
+    if prefix.as_str() == "xmlns" {
+        Some(local.as_str())
+    } else if prefix.is_empty() && local.as_str() == "xmlns" {
+        Some("")
+    } else {
+        None
+    }
+}
+
+pub(crate) fn is_name(prefix: StrSpan, local: StrSpan, name: &str) -> bool {
+    match name.split_once(':') {
+        Some((name_prefix, name_local)) => prefix == name_prefix && local == name_local,
+        None => prefix.is_empty() && local == name,
+    }
+}
+
+pub(crate) fn qualified_len(prefix: StrSpan, local: StrSpan) -> usize {
+    if prefix.is_empty() {
+        local.as_str().len()
+    } else {
+        prefix.as_str().len() + 1 + local.as_str().len()
+    }
+}
+
+pub(crate) fn qualified_name(prefix: StrSpan, local: StrSpan) -> String {
+    if prefix.is_empty() {
+        local.as_str().to_owned()
+    } else {
+        format!("{}:{}", prefix.as_str(), local.as_str())
+    }
+}
+
 #[test]
 fn read_text() -> XmlResult<()> {
     let mut reader = XmlReader::new("<parent></parent>");
@@ -340,6 +1072,123 @@ fn read_text() -> XmlResult<()> {
     assert_eq!(reader.read_text("parent")?, "\n  text1\n  text2\n  \n");
     assert!(reader.next().is_none());
 
+    reader = XmlReader::new("<parent>te<!-- comment -->x<?pi?>t</parent>");
+    assert!(reader.next().is_some()); // "<parent"
+    assert_eq!(reader.read_text("parent")?, "text");
+    assert!(reader.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn read_mixed_text() -> XmlResult<()> {
+    let mut reader = XmlReader::new("<p>a &amp; <!-- c --><![CDATA[<b>]]><i/>tail</p>");
+
+    assert!(reader.next().is_some()); // "<p"
+    assert!(reader.next().is_some()); // ">"
+    assert_eq!(reader.read_mixed_text()?.as_deref(), Some("a & <b>"));
+    assert_eq!(reader.read_mixed_text()?, None);
+    assert_eq!(reader.find_element_start(Some("p"))?, Some("i"));
+    assert!(reader.next().is_some()); // "<i"
+    reader.read_to_end("i")?;
+    assert_eq!(reader.read_mixed_text()?.as_deref(), Some("tail"));
+    assert_eq!(reader.find_element_start(Some("p"))?, None);
+
+    Ok(())
+}
+
+#[test]
+fn find_misc() -> XmlResult<()> {
+    let mut reader = XmlReader::new("<p> <!--a--> <?t b?><i/><!--c--></p>");
+
+    assert!(reader.next().is_some()); // "<p"
+    assert!(reader.next().is_some()); // ">"
+    assert!(matches!(reader.find_misc()?, Some(Token::Comment { text, .. }) if text.as_str() == "a"));
+    assert!(matches!(
+        reader.find_misc()?,
+        Some(Token::ProcessingInstruction { target, content: Some(content), .. })
+            if target.as_str() == "t" && content.as_str() == "b"
+    ));
+    assert!(reader.find_misc()?.is_none());
+    assert_eq!(reader.find_element_start(Some("p"))?, Some("i"));
+    assert!(reader.next().is_some()); // "<i"
+    reader.read_to_end("i")?;
+    assert!(matches!(reader.find_misc()?, Some(Token::Comment { text, .. }) if text.as_str() == "c"));
+    assert_eq!(reader.find_element_start(Some("p"))?, None);
+
+    Ok(())
+}
+
+#[test]
+fn peek_attribute() -> XmlResult<()> {
+    let mut reader = XmlReader::new(r#"<shape id="1" type="a &amp; b"/>"#);
+
+    assert_eq!(reader.peek_attribute("type")?, Some("a & b".into()));
+    assert_eq!(reader.peek_attribute("kind")?, None);
+
+    // nothing is consumed
+    assert_eq!(reader.find_element_start(None)?, Some("shape"));
+    reader.next();
+    assert_eq!(reader.find_attribute()?, Some(("id", "1".into())));
+    assert_eq!(reader.peek_attribute("type")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn peek_attribute_ns() -> XmlResult<()> {
+    let mut reader = XmlReader::new(
+        r#"<root xmlns:a="urn:a"><x a:type="1" type="2" xmlns:b="urn:b" b:type="3"/></root>"#,
+    );
+
+    reader.read_till_element_start("root")?;
+    while reader.find_attribute()?.is_some() {}
+    reader.next(); // ">"
+
+    assert_eq!(reader.find_element_start(Some("root"))?, Some("x"));
+    assert_eq!(reader.peek_attribute_ns("urn:a", "type")?, Some("1".into()));
+    assert_eq!(reader.peek_attribute_ns("urn:b", "type")?, Some("3".into()));
+    assert_eq!(reader.peek_attribute_ns("urn:c", "type")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn resolve_qname() -> XmlResult<()> {
+    let mut reader = XmlReader::new(
+        r#"<root xmlns:a="urn:a" xmlns="urn:d"><x type="a:T" xmlns:b="urn:b"/></root>"#,
+    );
+
+    reader.read_till_element_start("root")?;
+    while reader.find_attribute()?.is_some() {}
+    reader.next(); // ">"
+
+    assert_eq!(reader.find_element_start(Some("root"))?, Some("x"));
+    assert_eq!(reader.resolve_qname("a:T"), (Some("urn:a"), "T"));
+    assert_eq!(reader.resolve_qname("b:T"), (Some("urn:b"), "T"));
+    assert_eq!(reader.resolve_qname("T"), (Some("urn:d"), "T"));
+    assert_eq!(reader.resolve_qname("c:T"), (None, "T"));
+
+    Ok(())
+}
+
+#[test]
+fn peek_nil() -> XmlResult<()> {
+    let mut reader = XmlReader::new(
+        r#"<root xmlns:i="http://www.w3.org/2001/XMLSchema-instance"><a i:nil="true"/><b x:nil="1" xmlns:x="http://www.w3.org/2001/XMLSchema-instance"/><c i:nil="false"/><d nil="true"/></root>"#,
+    );
+
+    reader.read_till_element_start("root")?;
+    while reader.find_attribute()?.is_some() {}
+    reader.next(); // ">"
+
+    for (tag, nil) in [("a", true), ("b", true), ("c", false), ("d", false)] {
+        assert_eq!(reader.find_element_start(Some("root"))?, Some(tag));
+        assert_eq!(reader.peek_nil(), nil);
+        reader.next();
+        reader.read_to_end(tag)?;
+    }
+
     Ok(())
 }
 
@@ -430,3 +1279,119 @@ fn read_to_end() -> XmlResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn resolve_namespaces() -> XmlResult<()> {
+    let mut reader = XmlReader::new(
+        r#"<a:root xmlns:a="urn:a" xmlns="urn:default"><child b:attr="1" xmlns:b="urn:b" attr="2"/><a:child xmlns:a="urn:other"/></a:root>"#,
+    );
+
+    assert_eq!(reader.find_element_start(None)?, Some("a:root"));
+    assert_eq!(reader.resolve_element("a:root"), (Some("urn:a"), "root"));
+    reader.read_till_element_start_ns("urn:a", "root")?;
+    assert_eq!(reader.lookup_namespace(""), Some("urn:default"));
+    while reader.find_attribute()?.is_some() {}
+    reader.next(); // ">"
+
+    assert_eq!(reader.find_element_start(Some("a:root"))?, Some("child"));
+    assert_eq!(
+        reader.resolve_element("child"),
+        (Some("urn:default"), "child")
+    );
+    reader.next(); // "<child"
+    let (key, _) = reader.find_attribute()?.unwrap();
+    assert_eq!(key, "b:attr");
+    assert_eq!(reader.resolve_attribute(key), (Some("urn:b"), "attr"));
+    let (key, _) = reader.find_attribute()?.unwrap();
+    assert_eq!(reader.resolve_attribute(key), (Some(XMLNS_NAMESPACE), "b"));
+    let (key, _) = reader.find_attribute()?.unwrap();
+    assert_eq!(reader.resolve_attribute(key), (None, "attr"));
+    reader.next(); // "/>"
+    assert_eq!(reader.lookup_namespace("b"), None);
+
+    assert_eq!(reader.find_element_start(Some("a:root"))?, Some("a:child"));
+    assert_eq!(
+        reader.resolve_element("a:child"),
+        (Some("urn:other"), "child")
+    );
+    reader.next(); // "<a:child"
+    reader.next(); // "xmlns:a"
+    reader.next(); // "/>"
+    assert_eq!(reader.lookup_namespace("a"), Some("urn:a"));
+    assert_eq!(reader.lookup_namespace("xml"), Some(XML_NAMESPACE));
+
+    assert_eq!(reader.find_element_start(Some("a:root"))?, None);
+    assert_eq!(reader.lookup_namespace("a"), None);
+    assert!(reader.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn read_till_element_start_ns() -> XmlResult<()> {
+    let mut reader = XmlReader::new(r#"<y:tag xmlns:y="urn:y"/>"#);
+
+    assert_eq!(reader.read_till_element_start_ns("urn:y", "tag")?, "y:tag");
+    assert!(reader.next().is_some()); // "xmlns:y"
+    assert!(reader.next().is_some()); // "/>"
+    assert!(reader.next().is_none());
+
+    let mut reader = XmlReader::new(r#"<x:tag xmlns:x="urn:x"><y:tag/></x:tag>"#);
+
+    assert!(matches!(
+        reader.read_till_element_start_ns("urn:y", "tag"),
+        Err(XmlError::UnexpectedEof)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn read_stream() -> XmlResult<()> {
+    use crate::XmlArena;
+    use std::io::BufReader;
+
+    let text = "<?xml version=\"1.0\"?>\n<!-- prolog -->\n\
+                <root xmlns:a=\"urn:a\" attr=\"&lt;v&gt;\">\
+                <a:child>héllo wörld</a:child>\
+                <!-- comment -->\
+                <empty/>\
+                <skipped><x>y</x></skipped>\
+                <cdata><![CDATA[<ünïcode>]]></cdata>\
+                </root>";
+
+    // A one byte buffer splits every token and every multi-byte character.
+    let arena = XmlArena::new();
+    let mut reader = XmlReader::from_stream(BufReader::with_capacity(1, text.as_bytes()), &arena);
+
+    reader.read_till_element_start("root")?;
+    assert_eq!(
+        reader.find_attribute()?.unwrap(),
+        ("xmlns:a", "urn:a".into())
+    );
+    assert_eq!(reader.find_attribute()?.unwrap(), ("attr", "<v>".into()));
+    assert!(reader.find_attribute()?.is_none());
+    reader.next(); // ">"
+
+    assert_eq!(reader.find_element_start(Some("root"))?, Some("a:child"));
+    assert_eq!(reader.lookup_namespace("a"), Some("urn:a"));
+    reader.next();
+    assert_eq!(reader.read_text("a:child")?, "héllo wörld");
+
+    assert_eq!(reader.find_element_start(Some("root"))?, Some("empty"));
+    reader.next();
+    reader.read_to_end("empty")?;
+
+    assert_eq!(reader.find_element_start(Some("root"))?, Some("skipped"));
+    reader.next();
+    reader.read_to_end("skipped")?;
+
+    assert_eq!(reader.find_element_start(Some("root"))?, Some("cdata"));
+    reader.next();
+    assert_eq!(reader.read_text("cdata")?, "<ünïcode>");
+
+    assert_eq!(reader.find_element_start(Some("root"))?, None);
+    assert!(reader.next().is_none());
+
+    Ok(())
+}
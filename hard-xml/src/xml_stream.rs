@@ -0,0 +1,478 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::{BufRead, ErrorKind};
+
+use typed_arena::Arena;
+use xmlparser::{ElementEnd, EntityDefinition, ExternalId, StrSpan, Token, Tokenizer};
+
+use crate::xml_reader::{declared_prefix, error_offset, qualified_len, Entry};
+use crate::{XmlPosition, XmlResult};
+
+/// Storage for the strings an [`XmlReader`](crate::XmlReader) created by
+/// [`XmlReader::from_stream`](crate::XmlReader::from_stream) hands out.
+///
+/// Element names, attribute names and namespace URIs are interned, so parsing
+/// only grows the arena with the vocabulary of the document. Text and attribute
+/// values are returned as owned strings by the reader's helpers and only stored
+/// here when they are exposed through `XmlReader::next` or `XmlReader::peek`.
+#[derive(Default)]
+pub struct XmlArena {
+    bytes: Arena<u8>,
+}
+
+impl XmlArena {
+    pub fn new() -> Self {
+        XmlArena::default()
+    }
+
+    pub(crate) fn alloc(&self, text: &str) -> &str {
+        self.bytes.alloc_str(text)
+    }
+}
+
+/// Tokenizes input pulled from a `BufRead`, keeping only the unconsumed part
+/// of the input in memory.
+pub(crate) struct XmlStream<'a> {
+    read: Box<dyn BufRead + 'a>,
+    progress: Option<Box<dyn FnMut(u64) + 'a>>,
+    arena: &'a XmlArena,
+    names: HashSet<&'a str>,
+    // input which hasn't been turned into tokens yet
+    buffer: String,
+    // trailing bytes of an incomplete UTF-8 sequence
+    partial: Vec<u8>,
+    queue: VecDeque<Entry<'a>>,
+    consumed: u64,
+    // position of the start of the buffer
+    cursor: XmlPosition,
+    // whether the prolog is behind us, so the buffer can be tokenized as a fragment
+    in_content: bool,
+    eof: bool,
+}
+
+impl<'a> XmlStream<'a> {
+    pub(crate) fn new<R: BufRead + 'a>(read: R, arena: &'a XmlArena) -> Self {
+        XmlStream {
+            read: Box::new(read),
+            progress: None,
+            arena,
+            names: HashSet::new(),
+            buffer: String::new(),
+            partial: Vec::new(),
+            queue: VecDeque::new(),
+            consumed: 0,
+            cursor: XmlPosition::START,
+            in_content: false,
+            eof: false,
+        }
+    }
+
+    pub(crate) fn set_progress<F: FnMut(u64) + 'a>(&mut self, progress: F) {
+        self.progress = Some(Box::new(progress));
+    }
+
+    pub(crate) fn next(&mut self) -> Option<Entry<'a>> {
+        if self.queue.is_empty() {
+            self.fill_queue();
+        }
+        self.queue.pop_front()
+    }
+
+    /// Moves the text or attribute value of `entry` into the arena, so the
+    /// token can be handed out.
+    pub(crate) fn materialize(&self, entry: &mut Entry<'a>) {
+        materialize(self.arena, entry);
+    }
+
+    fn fill_queue(&mut self) {
+        loop {
+            let mut tokenizer = if self.in_content {
+                Tokenizer::from_fragment(&self.buffer, 0..self.buffer.len())
+            } else {
+                Tokenizer::from(self.buffer.as_str())
+            };
+
+            let mut pending = Vec::new();
+            let mut boundary = None;
+            let mut in_content = self.in_content;
+            let mut cursor = self.cursor;
+            let mut located = 0;
+
+            for token in &mut tokenizer {
+                let token = match token {
+                    Ok(token) => token,
+                    Err(err) => {
+                        // the error might be caused by input we haven't read yet
+                        if self.eof {
+                            let start = error_offset(&self.buffer, err.pos()).max(located);
+                            cursor.advance(&self.buffer[located..start]);
+                            pending.push(Entry {
+                                token: Err(err.into()),
+                                payload: None,
+                                position: cursor,
+                            });
+                        }
+                        break;
+                    }
+                };
+
+                let start = token.span().start();
+                let end = token.span().end();
+
+                // text or a tag name touching the end of the buffer might
+                // continue, other tokens are complete once they are returned
+                if !self.eof
+                    && end >= self.buffer.len()
+                    && matches!(token, Token::Text { .. } | Token::ElementStart { .. })
+                {
+                    break;
+                }
+
+                in_content |= matches!(token, Token::ElementStart { .. });
+
+                // the tokenizer can only be restarted where content is expected
+                let is_boundary = in_content
+                    && matches!(
+                        token,
+                        Token::ElementEnd { .. }
+                            | Token::Text { .. }
+                            | Token::Cdata { .. }
+                            | Token::Comment { .. }
+                            | Token::ProcessingInstruction { .. }
+                    );
+
+                cursor.advance(&self.buffer[located..start]);
+                located = start;
+
+                pending.push(convert(self.arena, &mut self.names, token, cursor));
+
+                if is_boundary {
+                    let mut after = cursor;
+                    after.advance(&self.buffer[start..end]);
+                    boundary = Some((end, pending.len(), after));
+                }
+            }
+
+            if self.eof {
+                cursor.advance(&self.buffer[located..]);
+                boundary = Some((self.buffer.len(), pending.len(), cursor));
+            }
+
+            if let Some((end, len, after)) = boundary {
+                pending.truncate(len);
+                self.queue.extend(pending);
+                self.buffer.drain(..end);
+                self.cursor = after;
+                self.in_content = in_content;
+                return;
+            }
+
+            if let Err(err) = self.fill() {
+                self.queue.push_back(Entry {
+                    token: Err(err),
+                    payload: None,
+                    position: self.cursor,
+                });
+                self.buffer.clear();
+                self.eof = true;
+                return;
+            }
+        }
+    }
+
+    // reads at least as much as is already buffered, so that a long token
+    // doesn't get tokenized over and over again
+    fn fill(&mut self) -> XmlResult<()> {
+        let target = self.buffer.len().max(1);
+        let mut read = 0;
+
+        while read < target {
+            let chunk = match self.read.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            if chunk.is_empty() {
+                self.eof = true;
+                if let Err(err) = std::str::from_utf8(&self.partial) {
+                    return Err(err.into());
+                }
+                break;
+            }
+
+            let len = chunk.len();
+            self.partial.extend_from_slice(chunk);
+            self.read.consume(len);
+            read += len;
+            self.consumed += len as u64;
+
+            let valid = match std::str::from_utf8(&self.partial) {
+                Ok(text) => text.len(),
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(err) => return Err(err.into()),
+            };
+
+            if let Ok(text) = std::str::from_utf8(&self.partial[..valid]) {
+                self.buffer.push_str(text);
+            }
+            self.partial.drain(..valid);
+        }
+
+        if read > 0 {
+            if let Some(progress) = &mut self.progress {
+                progress(self.consumed);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves the text or attribute value of `entry` into `arena`, so the token
+/// can be handed out.
+pub(crate) fn materialize<'a>(arena: &'a XmlArena, entry: &mut Entry<'a>) {
+    let payload = match entry.payload.take() {
+        Some(payload) => payload,
+        None => return,
+    };
+
+    match &mut entry.token {
+        Ok(Token::Text { text }) => *text = arena.alloc(&payload).into(),
+        Ok(Token::Cdata { text, span }) => {
+            let full = arena.alloc(&format!("<![CDATA[{}]]>", payload));
+            *span = full.into();
+            *text = full[9..full.len() - 3].into();
+        }
+        Ok(Token::Comment { text, span }) => {
+            let full = arena.alloc(&format!("<!--{}-->", payload));
+            *span = full.into();
+            *text = full[4..full.len() - 3].into();
+        }
+        Ok(Token::Attribute {
+            prefix,
+            local,
+            value,
+            span,
+        }) => {
+            let quote = if payload.contains('"') { '\'' } else { '"' };
+            let key_len = qualified_len(*prefix, *local);
+            let full = arena.alloc(&format!("{}={}{}{}", span.as_str(), quote, payload, quote));
+            *span = full.into();
+            *value = full[key_len + 2..full.len() - 1].into();
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn intern<'a>(arena: &'a XmlArena, names: &mut HashSet<&'a str>, name: &str) -> &'a str {
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name = arena.alloc(name);
+            names.insert(name);
+            name
+        }
+    }
+}
+
+// splits an interned qualified name into prefix and local name
+pub(crate) fn split(name: &str) -> (StrSpan<'_>, StrSpan<'_>) {
+    match name.split_once(':') {
+        Some((prefix, local)) => (prefix.into(), local.into()),
+        None => ("".into(), name.into()),
+    }
+}
+
+fn convert<'a>(
+    arena: &'a XmlArena,
+    names: &mut HashSet<&'a str>,
+    token: Token<'_>,
+    position: XmlPosition,
+) -> Entry<'a> {
+    let (token, payload) = match token {
+        Token::ElementStart { span, .. } => {
+            let span = intern(arena, names, span.as_str());
+            let (prefix, local) = split(&span[1..]);
+            let token = Token::ElementStart {
+                prefix,
+                local,
+                span: span.into(),
+            };
+            (token, None)
+        }
+        Token::ElementEnd { end, span } => {
+            let end = match end {
+                ElementEnd::Open => ElementEnd::Open,
+                ElementEnd::Empty => ElementEnd::Empty,
+                ElementEnd::Close(prefix, local) => ElementEnd::Close(
+                    intern(arena, names, prefix.as_str()).into(),
+                    intern(arena, names, local.as_str()).into(),
+                ),
+            };
+            let span = intern(arena, names, span.as_str());
+            let token = Token::ElementEnd {
+                end,
+                span: span.into(),
+            };
+            (token, None)
+        }
+        Token::Attribute {
+            prefix,
+            local,
+            value,
+            span,
+        } => {
+            let key = intern(arena, names, &span.as_str()[..qualified_len(prefix, local)]);
+            let (new_prefix, new_local) = split(key);
+
+            // namespace declarations are looked up by the reader, so they are
+            // treated like names
+            let (value, payload) = if declared_prefix(prefix, local).is_some() {
+                (intern(arena, names, value.as_str()), None)
+            } else {
+                ("", Some(value.as_str().to_owned()))
+            };
+
+            let token = Token::Attribute {
+                prefix: new_prefix,
+                local: new_local,
+                value: value.into(),
+                span: key.into(),
+            };
+            (token, payload)
+        }
+        Token::Text { text } => {
+            let token = Token::Text { text: "".into() };
+            (token, Some(text.as_str().to_owned()))
+        }
+        Token::Cdata { text, .. } => {
+            let token = Token::Cdata {
+                text: "".into(),
+                span: "".into(),
+            };
+            (token, Some(text.as_str().to_owned()))
+        }
+        Token::Comment { text, .. } => {
+            let token = Token::Comment {
+                text: "".into(),
+                span: "".into(),
+            };
+            (token, Some(text.as_str().to_owned()))
+        }
+        token => (remap(arena, token), None),
+    };
+
+    Entry {
+        token: Ok(token),
+        payload,
+        position,
+    }
+}
+
+// copies a token into the arena
+fn remap<'a>(arena: &'a XmlArena, token: Token<'_>) -> Token<'a> {
+    let whole = token.span();
+    let base = arena.alloc(whole.as_str());
+    let map = |span: StrSpan<'_>| -> StrSpan<'a> {
+        if span.is_empty() {
+            "".into()
+        } else {
+            base[span.start() - whole.start()..span.end() - whole.start()].into()
+        }
+    };
+    let map_id = |id: ExternalId<'_>| match id {
+        ExternalId::System(system) => ExternalId::System(map(system)),
+        ExternalId::Public(public, system) => ExternalId::Public(map(public), map(system)),
+    };
+
+    match token {
+        Token::Declaration {
+            version,
+            encoding,
+            standalone,
+            span,
+        } => Token::Declaration {
+            version: map(version),
+            encoding: encoding.map(map),
+            standalone,
+            span: map(span),
+        },
+        Token::ProcessingInstruction {
+            target,
+            content,
+            span,
+        } => Token::ProcessingInstruction {
+            target: map(target),
+            content: content.map(map),
+            span: map(span),
+        },
+        Token::Comment { text, span } => Token::Comment {
+            text: map(text),
+            span: map(span),
+        },
+        Token::DtdStart {
+            name,
+            external_id,
+            span,
+        } => Token::DtdStart {
+            name: map(name),
+            external_id: external_id.map(map_id),
+            span: map(span),
+        },
+        Token::EmptyDtd {
+            name,
+            external_id,
+            span,
+        } => Token::EmptyDtd {
+            name: map(name),
+            external_id: external_id.map(map_id),
+            span: map(span),
+        },
+        Token::EntityDeclaration {
+            name,
+            definition,
+            span,
+        } => Token::EntityDeclaration {
+            name: map(name),
+            definition: match definition {
+                EntityDefinition::EntityValue(value) => EntityDefinition::EntityValue(map(value)),
+                EntityDefinition::ExternalId(id) => EntityDefinition::ExternalId(map_id(id)),
+            },
+            span: map(span),
+        },
+        Token::DtdEnd { span } => Token::DtdEnd { span: map(span) },
+        Token::ElementStart {
+            prefix,
+            local,
+            span,
+        } => Token::ElementStart {
+            prefix: map(prefix),
+            local: map(local),
+            span: map(span),
+        },
+        Token::Attribute {
+            prefix,
+            local,
+            value,
+            span,
+        } => Token::Attribute {
+            prefix: map(prefix),
+            local: map(local),
+            value: map(value),
+            span: map(span),
+        },
+        Token::ElementEnd { end, span } => Token::ElementEnd {
+            end: match end {
+                ElementEnd::Open => ElementEnd::Open,
+                ElementEnd::Empty => ElementEnd::Empty,
+                ElementEnd::Close(prefix, local) => ElementEnd::Close(map(prefix), map(local)),
+            },
+            span: map(span),
+        },
+        Token::Text { text } => Token::Text { text: map(text) },
+        Token::Cdata { text, span } => Token::Cdata {
+            text: map(text),
+            span: map(span),
+        },
+    }
+}
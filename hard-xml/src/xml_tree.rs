@@ -0,0 +1,166 @@
+use std::collections::{HashSet, VecDeque};
+
+use xmlparser::{ElementEnd, StrSpan, Token};
+
+use crate::dom::{Element, Node};
+use crate::xml_escape::xml_escape;
+use crate::xml_reader::Entry;
+use crate::xml_stream::{intern, materialize, split};
+use crate::{XmlArena, XmlPosition};
+
+/// Turns an [`Element`] back into tokens, for an
+/// [`XmlReader`](crate::XmlReader) created by
+/// [`XmlReader::from_element`](crate::XmlReader::from_element).
+///
+/// Names, text and attribute values are borrowed from the element. Only start
+/// tags, and text or values which have to be escaped for the reader to
+/// unescape them, are copied into the arena.
+pub(crate) struct XmlTree<'a> {
+    arena: &'a XmlArena,
+    names: HashSet<&'a str>,
+    // the elements whose content is being walked and the index of their next
+    // child, `None` until their start tag has been queued
+    stack: Vec<(&'a Element, Option<usize>)>,
+    queue: VecDeque<Entry<'a>>,
+}
+
+impl<'a> XmlTree<'a> {
+    pub(crate) fn new(element: &'a Element, arena: &'a XmlArena) -> Self {
+        XmlTree {
+            arena,
+            names: HashSet::new(),
+            stack: vec![(element, None)],
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Option<Entry<'a>> {
+        // entering a child element queues nothing by itself
+        while self.queue.is_empty() && !self.stack.is_empty() {
+            self.fill_queue();
+        }
+        self.queue.pop_front()
+    }
+
+    pub(crate) fn materialize(&self, entry: &mut Entry<'a>) {
+        materialize(self.arena, entry);
+    }
+
+    fn fill_queue(&mut self) {
+        let (element, index) = match self.stack.last() {
+            Some(&top) => top,
+            None => return,
+        };
+
+        let index = match index {
+            Some(index) => index,
+            None => {
+                self.push_start(element);
+                if element.children.is_empty() {
+                    self.push(end(ElementEnd::Empty, element));
+                    self.stack.pop();
+                } else {
+                    self.push(end(ElementEnd::Open, element));
+                    self.set_index(0);
+                }
+                return;
+            }
+        };
+
+        let node = match element.children.get(index) {
+            Some(node) => node,
+            None => {
+                let (prefix, local) = split(&element.name);
+                self.push(end(ElementEnd::Close(prefix, local), element));
+                self.stack.pop();
+                return;
+            }
+        };
+        self.set_index(index + 1);
+
+        match node {
+            Node::Element(child) => self.stack.push((child, None)),
+            Node::Text(text) => {
+                let (text, payload) = escaped(text);
+                self.push((Token::Text { text }, payload));
+            }
+            Node::CData(text) => self.push((
+                Token::Cdata {
+                    text: text.as_str().into(),
+                    span: text.as_str().into(),
+                },
+                None,
+            )),
+            Node::Comment(text) => self.push((
+                Token::Comment {
+                    text: text.as_str().into(),
+                    span: text.as_str().into(),
+                },
+                None,
+            )),
+        }
+    }
+
+    fn push_start(&mut self, element: &'a Element) {
+        // the reader takes the name from the span of the start tag
+        let span = intern(self.arena, &mut self.names, &format!("<{}", element.name));
+        let (prefix, local) = split(&span[1..]);
+        self.push((
+            Token::ElementStart {
+                prefix,
+                local,
+                span: span.into(),
+            },
+            None,
+        ));
+
+        for (key, value) in &element.attributes {
+            let (prefix, local) = split(key);
+            // namespace declarations are taken as they are
+            let (value, payload) = if key == "xmlns" || prefix.as_str() == "xmlns" {
+                (value.as_str().into(), None)
+            } else {
+                escaped(value)
+            };
+            self.push((
+                Token::Attribute {
+                    prefix,
+                    local,
+                    value,
+                    span: key.as_str().into(),
+                },
+                payload,
+            ));
+        }
+    }
+
+    fn set_index(&mut self, index: usize) {
+        if let Some(top) = self.stack.last_mut() {
+            top.1 = Some(index);
+        }
+    }
+
+    fn push(&mut self, (token, payload): (Token<'a>, Option<String>)) {
+        self.queue.push_back(Entry {
+            token: Ok(token),
+            payload,
+            // there is no input to point into
+            position: XmlPosition::START,
+        });
+    }
+}
+
+fn end<'a>(end: ElementEnd<'a>, element: &'a Element) -> (Token<'a>, Option<String>) {
+    let span = element.name.as_str().into();
+    (Token::ElementEnd { end, span }, None)
+}
+
+// text is unescaped by the reader, so it is only borrowed if that leaves it
+// unchanged
+fn escaped(text: &str) -> (StrSpan<'_>, Option<String>) {
+    if text.contains('&') {
+        ("".into(), Some(xml_escape(text).into_owned()))
+    } else {
+        (text.into(), None)
+    }
+}
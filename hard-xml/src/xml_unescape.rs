@@ -5,6 +5,19 @@ use std::char;
 use crate::{XmlError, XmlResult};
 
 pub fn xml_unescape<'a>(raw: &'a str) -> XmlResult<Cow<'a, str>> {
+    unescape_with(raw, |entity, _| {
+        Err(XmlError::UnrecognizedSymbol {
+            symbol: String::from(entity),
+        })
+    })
+}
+
+/// Like [`xml_unescape`], calling `expand` for references to entities which
+/// aren't predefined.
+pub(crate) fn unescape_with<F>(raw: &str, mut expand: F) -> XmlResult<Cow<'_, str>>
+where
+    F: FnMut(&str, &mut String) -> XmlResult<()>,
+{
     let bytes = raw.as_bytes();
 
     if let Some(i) = memchr(b'&', bytes) {
@@ -15,7 +28,7 @@ pub fn xml_unescape<'a>(raw: &'a str) -> XmlResult<Cow<'a, str>> {
         let mut pos = i + 1;
 
         if let Some(i) = memchr(b';', &bytes[pos..]) {
-            recognize(&raw[pos..pos + i], &mut result)?;
+            recognize(&raw[pos..pos + i], &mut result, &mut expand)?;
 
             pos += i + 1;
         } else {
@@ -30,7 +43,7 @@ pub fn xml_unescape<'a>(raw: &'a str) -> XmlResult<Cow<'a, str>> {
             pos += i + 1;
 
             if let Some(i) = memchr(b';', &bytes[pos..]) {
-                recognize(&raw[pos..pos + i], &mut result)?;
+                recognize(&raw[pos..pos + i], &mut result, &mut expand)?;
 
                 pos += i + 1;
             } else {
@@ -48,7 +61,10 @@ pub fn xml_unescape<'a>(raw: &'a str) -> XmlResult<Cow<'a, str>> {
     }
 }
 
-fn recognize(entity: &str, result: &mut String) -> XmlResult<()> {
+fn recognize<F>(entity: &str, result: &mut String, expand: &mut F) -> XmlResult<()>
+where
+    F: FnMut(&str, &mut String) -> XmlResult<()>,
+{
     match entity {
         "quot" => result.push('"'),
         "apos" => result.push('\''),
@@ -61,7 +77,7 @@ fn recognize(entity: &str, result: &mut String) -> XmlResult<()> {
             } else if entity.starts_with('#') {
                 u32::from_str_radix(&entity[1..], 10).ok()
             } else {
-                None
+                return expand(entity, result);
             };
             match val.and_then(char::from_u32) {
                 Some(c) => result.push(c),
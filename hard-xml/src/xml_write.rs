@@ -1,6 +1,9 @@
 use std::io::Write;
 
-use crate::{XmlResult, XmlWriter};
+#[cfg(feature = "tokio")]
+use crate::XmlAsyncWrite;
+use crate::dom::Element;
+use crate::{XmlDeclaration, XmlEncoding, XmlError, XmlFormat, XmlNamespaces, XmlResult, XmlWriter};
 
 pub trait XmlWrite {
     fn to_writer<W: Write>(&self, writer: &mut XmlWriter<W>) -> XmlResult<()>;
@@ -12,4 +15,87 @@ pub trait XmlWrite {
 
         Ok(String::from_utf8(writer.inner)?)
     }
+
+    fn to_string_pretty(&self) -> XmlResult<String> {
+        let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+
+        self.to_writer(&mut writer)?;
+
+        Ok(String::from_utf8(writer.inner)?)
+    }
+
+    /// Writes the value after an XML declaration built from `declaration`.
+    fn to_string_with_declaration(&self, declaration: &XmlDeclaration) -> XmlResult<String> {
+        let mut writer = XmlWriter::new(Vec::new());
+
+        writer.write_declaration(declaration)?;
+        self.to_writer(&mut writer)?;
+
+        Ok(String::from_utf8(writer.inner)?)
+    }
+
+    /// Writes the value in `encoding`, after a declaration naming it.
+    ///
+    /// Characters which `encoding` can't represent are written as character
+    /// references.
+    fn to_bytes_with_encoding(&self, encoding: XmlEncoding) -> XmlResult<Vec<u8>> {
+        let mut writer = XmlWriter::new(Vec::new());
+
+        writer.set_encoding(encoding);
+        writer.write_root_declaration()?;
+        self.to_writer(&mut writer)?;
+
+        Ok(writer.inner)
+    }
+
+    /// Writes the value to a tokio `AsyncWrite`, then flushes `writer`.
+    #[cfg(feature = "tokio")]
+    fn to_async_writer<'w, W: tokio::io::AsyncWrite + Unpin>(
+        &'w self,
+        writer: &'w mut W,
+    ) -> XmlAsyncWrite<'w, Self, W> {
+        XmlAsyncWrite::new(self, writer)
+    }
+
+    /// Converts the value into an untyped element, building its nodes as
+    /// they are written.
+    ///
+    /// Processing instructions are left out, like when reading an element.
+    fn to_element(&self) -> XmlResult<Element> {
+        let mut writer = XmlWriter::tree();
+
+        self.to_writer(&mut writer)?;
+
+        writer.into_element().ok_or(XmlError::UnexpectedEof)
+    }
+
+    /// Collects the namespaces used by this type and its descendants, so the
+    /// root element can declare them once.
+    #[doc(hidden)]
+    fn collect_namespaces(_namespaces: &mut XmlNamespaces) {}
+}
+
+/// The fields of a `#[xml(flatten)]` field, implemented by
+/// `#[derive(XmlWrite)]` for structs with `#[xml(fields)]`.
+pub trait XmlWriteFields {
+    /// Writes the attributes of the fields into the start tag of the parent.
+    fn write_attributes<W: Write>(&self, writer: &mut XmlWriter<W>) -> XmlResult<()>;
+
+    /// Writes the child elements of the fields.
+    fn write_children<W: Write>(&self, writer: &mut XmlWriter<W>) -> XmlResult<()>;
+
+    /// Returns whether [`XmlWriteFields::write_children`] writes anything,
+    /// so that the parent can be written as an empty element otherwise.
+    fn has_children(&self) -> bool;
+
+    /// Collects the namespaces used by the fields, see
+    /// [`XmlWrite::collect_namespaces`].
+    #[doc(hidden)]
+    fn collect_namespaces(_namespaces: &mut XmlNamespaces) {}
+}
+
+/// Whether `value` equals its type's default, for `#[xml(skip_default)]`.
+#[doc(hidden)]
+pub fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
@@ -1,44 +1,698 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::Result;
+use std::fmt;
 use std::io::Write;
+use std::io::{Error, ErrorKind, Result};
 
-use crate::xml_escape::xml_escape;
+use crate::dom;
+use crate::xml_escape::{xml_escape_attribute, xml_escape_text};
+use crate::xml_reader::{XML_NAMESPACE, XSI_NAMESPACE};
+use crate::{XmlError, XmlResult, XmlWrite};
 
 pub struct XmlWriter<W: Write> {
     pub inner: W,
+    format: Option<XmlFormat>,
+    elements: Vec<Element>,
+    namespaces: Vec<Namespace>,
+    encoding: XmlEncoding,
+    invalid_chars: XmlInvalidChars,
+    // whether a top-level node has been written
+    started: bool,
+    // the elements being built instead of writing to `inner`, under a
+    // nameless element holding the finished ones
+    tree: Option<Vec<dom::Element>>,
+}
+
+/// Formatting options for pretty-printed output.
+#[derive(Clone, Debug)]
+pub struct XmlFormat {
+    /// Written once per nesting level before each start and end tag.
+    pub indent: String,
+    /// Written before each indented tag.
+    pub newline: String,
+    /// Keeps text-only elements on one line by writing line breaks in their
+    /// text as character references.
+    ///
+    /// Text is never reformatted otherwise, so multi-line text is written
+    /// verbatim when this is `false`.
+    pub inline_text: bool,
+}
+
+impl Default for XmlFormat {
+    fn default() -> Self {
+        XmlFormat {
+            indent: "  ".to_owned(),
+            newline: "\n".to_owned(),
+            inline_text: false,
+        }
+    }
+}
+
+/// Contents of the XML declaration written by
+/// [`XmlWriter::write_declaration`].
+#[derive(Clone, Debug)]
+pub struct XmlDeclaration {
+    /// Written as the `version` pseudo-attribute.
+    pub version: String,
+    /// Written as the `encoding` pseudo-attribute if set.
+    pub encoding: Option<String>,
+    /// Written as `standalone="yes"` or `standalone="no"` if set.
+    pub standalone: Option<bool>,
+}
+
+impl Default for XmlDeclaration {
+    fn default() -> Self {
+        XmlDeclaration {
+            version: "1.0".to_owned(),
+            encoding: Some("UTF-8".to_owned()),
+            standalone: None,
+        }
+    }
+}
+
+/// Character encoding of the output of an [`XmlWriter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XmlEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, which covers the first 256 code points.
+    Latin1,
+    /// US-ASCII, which covers the first 128 code points.
+    Ascii,
+}
+
+impl XmlEncoding {
+    /// Returns the name used in the XML declaration.
+    pub fn name(self) -> &'static str {
+        match self {
+            XmlEncoding::Utf8 => "UTF-8",
+            XmlEncoding::Latin1 => "ISO-8859-1",
+            XmlEncoding::Ascii => "US-ASCII",
+        }
+    }
+
+    /// Returns whether `c` can be written without a character reference.
+    pub fn can_encode(self, c: char) -> bool {
+        match self {
+            XmlEncoding::Utf8 => true,
+            XmlEncoding::Latin1 => (c as u32) < 0x100,
+            XmlEncoding::Ascii => c.is_ascii(),
+        }
+    }
+}
+
+/// What an [`XmlWriter`] does with characters XML 1.0 doesn't allow in text
+/// and attribute values, such as `\u{0}`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum XmlInvalidChars {
+    /// Fails with [`XmlError::InvalidChar`].
+    #[default]
+    Reject,
+    /// Writes U+FFFD REPLACEMENT CHARACTER instead.
+    Replace,
+}
+
+// encodes text written to `inner`, also as the target of `fmt::Write`
+struct Encoder<'w, W> {
+    inner: &'w mut W,
+    encoding: XmlEncoding,
+    error: Option<Error>,
+}
+
+impl<'w, W: Write> Encoder<'w, W> {
+    fn new(inner: &'w mut W, encoding: XmlEncoding) -> Self {
+        Encoder {
+            inner,
+            encoding,
+            error: None,
+        }
+    }
+
+    fn encode(&mut self, s: &str) -> Result<()> {
+        if self.encoding == XmlEncoding::Utf8 || s.is_ascii() {
+            return self.inner.write_all(s.as_bytes());
+        }
+
+        if let Some(c) = s.chars().find(|c| !self.encoding.can_encode(*c)) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{:?} can't be written in {}", c, self.encoding.name()),
+            ));
+        }
+
+        let bytes: Vec<u8> = s.chars().map(|c| c as u8).collect();
+        self.inner.write_all(&bytes)
+    }
+}
+
+impl<W: Write> fmt::Write for Encoder<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.encode(s).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+struct Element {
+    // qualified name, `None` if the name was written as given
+    name: Option<String>,
+    has_children: bool,
+    // number of child elements started so far
+    children: usize,
+    // comments, processing instructions and elements waiting for their place
+    // among the child elements
+    deferred: Vec<(usize, Misc)>,
+    has_text: bool,
+    // whitespace is significant, e.g. the element is part of mixed content
+    preserve: bool,
+}
+
+enum Misc {
+    Comment(String),
+    ProcessingInstruction(String, String),
+    Element(dom::Element),
+}
+
+struct Namespace {
+    depth: usize,
+    prefix: String,
+    uri: String,
+    // declared as the default namespace only because an element in it had no
+    // prefix, so unprefixed elements without a namespace shouldn't inherit it
+    implicit: bool,
 }
 
 impl<W: Write> XmlWriter<W> {
     pub fn new(inner: W) -> Self {
-        XmlWriter { inner }
+        XmlWriter {
+            inner,
+            format: None,
+            elements: Vec::new(),
+            namespaces: Vec::new(),
+            encoding: XmlEncoding::Utf8,
+            invalid_chars: XmlInvalidChars::Reject,
+            started: false,
+            tree: None,
+        }
+    }
+
+    /// Creates a writer which indents nested elements as described by `format`.
+    pub fn with_format(inner: W, format: XmlFormat) -> Self {
+        XmlWriter {
+            format: Some(format),
+            ..XmlWriter::new(inner)
+        }
     }
 
     pub fn into_inner(self) -> W {
         self.inner
     }
 
+    /// Writes the output in `encoding` instead of UTF-8.
+    ///
+    /// Characters in text and attribute values which `encoding` can't
+    /// represent are written as character references, anywhere else they are
+    /// an [`ErrorKind::InvalidData`] error.
+    pub fn set_encoding(&mut self, encoding: XmlEncoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn encoding(&self) -> XmlEncoding {
+        self.encoding
+    }
+
+    /// Chooses how characters XML 1.0 doesn't allow are handled, they are
+    /// rejected by default.
+    ///
+    /// Rejected characters are reported as an [`ErrorKind::InvalidData`]
+    /// error wrapping [`XmlError::InvalidChar`], which converting it to
+    /// [`XmlError`] unwraps.
+    pub fn set_invalid_chars(&mut self, invalid_chars: XmlInvalidChars) {
+        self.invalid_chars = invalid_chars;
+    }
+
+    /// Writes an XML declaration such as
+    /// `<?xml version="1.0" encoding="UTF-8"?>`.
+    ///
+    /// The declaration has to come first in a document, so this fails with
+    /// [`ErrorKind::InvalidInput`] once anything else has been written.
+    pub fn write_declaration(&mut self, declaration: &XmlDeclaration) -> Result<()> {
+        if self.started {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "XML declaration must be written before any element",
+            ));
+        }
+
+        self.started = true;
+
+        if self.tree.is_some() {
+            return Ok(());
+        }
+
+        write!(self, r#"<?xml version="{}""#, declaration.version)?;
+        if let Some(encoding) = &declaration.encoding {
+            write!(self, r#" encoding="{}""#, encoding)?;
+        }
+        if let Some(standalone) = declaration.standalone {
+            let standalone = if standalone { "yes" } else { "no" };
+            write!(self, r#" standalone="{}""#, standalone)?;
+        }
+        write!(self, "?>")
+    }
+
+    /// Writes the default declaration naming the output encoding unless
+    /// something has been written already, used by types with
+    /// `#[xml(declaration)]`.
+    #[doc(hidden)]
+    pub fn write_root_declaration(&mut self) -> Result<()> {
+        if self.started {
+            return Ok(());
+        }
+
+        self.write_declaration(&XmlDeclaration {
+            encoding: Some(self.encoding.name().to_owned()),
+            ..XmlDeclaration::default()
+        })
+    }
+
+    /// Writes the start of an element without a namespace.
+    ///
+    /// If the default namespace in scope was only declared for an element in
+    /// it, an unprefixed `tag` undeclares it with `xmlns=""`.
     pub fn write_element_start(&mut self, tag: &str) -> Result<()> {
-        write!(self.inner, "<{}", tag)
+        self.start_element(tag)?;
+
+        let implicit_default = self
+            .namespaces
+            .iter()
+            .rev()
+            .find(|ns| ns.prefix.is_empty())
+            .map_or(false, |ns| ns.implicit && !ns.uri.is_empty());
+
+        if implicit_default && !tag.contains(':') {
+            self.write_namespace("", "")?;
+        }
+
+        Ok(())
+    }
+
+    fn start_element(&mut self, tag: &str) -> Result<()> {
+        self.write_deferred(false)?;
+        self.push_element()?;
+        if let Some(tree) = &mut self.tree {
+            tree.push(dom::Element::new(tag));
+            return Ok(());
+        }
+        write!(self, "<{}", tag)
+    }
+
+    /// Writes the start of an element in namespace `ns`.
+    ///
+    /// With an explicit `prefix` that prefix is used and bound to `ns` if it
+    /// isn't already. Otherwise any prefix bound to `ns` in scope is reused,
+    /// falling back to declaring `ns` as the default namespace.
+    pub fn write_element_start_ns(&mut self, ns: &str, prefix: Option<&str>, local: &str) -> Result<()> {
+        let (prefix, implicit) = match prefix {
+            Some(prefix) => (prefix.to_owned(), false),
+            None => match self.lookup_prefix(ns, true) {
+                Some(prefix) => (prefix, false),
+                None => (String::new(), true),
+            },
+        };
+
+        let name = if prefix.is_empty() {
+            local.to_owned()
+        } else {
+            format!("{}:{}", prefix, local)
+        };
+
+        self.start_element(&name)?;
+        if let Some(element) = self.elements.last_mut() {
+            element.name = Some(name);
+        }
+        self.declare_namespace(&prefix, ns, implicit)
+    }
+
+    /// Declares `prefix` (or the default namespace if empty) on the element
+    /// being started.
+    ///
+    /// Declarations already in scope are dropped, as are conflicting ones for
+    /// a prefix that was declared on this element before.
+    pub fn write_namespace(&mut self, prefix: &str, uri: &str) -> Result<()> {
+        self.declare_namespace(prefix, uri, false)
+    }
+
+    fn declare_namespace(&mut self, prefix: &str, uri: &str, implicit: bool) -> Result<()> {
+        let depth = self.elements.len();
+
+        if self.lookup_namespace(prefix).unwrap_or_default() == uri
+            || self
+                .namespaces
+                .iter()
+                .any(|ns| ns.depth == depth && ns.prefix == prefix)
+        {
+            return Ok(());
+        }
+
+        self.namespaces.push(Namespace {
+            depth,
+            prefix: prefix.to_owned(),
+            uri: uri.to_owned(),
+            implicit,
+        });
+
+        if prefix.is_empty() {
+            self.write_attribute("xmlns", uri)
+        } else {
+            self.write_attribute(&format!("xmlns:{}", prefix), uri)
+        }
+    }
+
+    /// Declares the namespaces collected by `collect` if the element being
+    /// started is the root element, so that descendants can refer to them
+    /// without redeclaring.
+    pub fn write_namespace_declarations<F>(&mut self, collect: F) -> Result<()>
+    where
+        F: FnOnce(&mut XmlNamespaces),
+    {
+        if self.elements.len() != 1 {
+            return Ok(());
+        }
+
+        let mut namespaces = XmlNamespaces::default();
+        collect(&mut namespaces);
+
+        for (prefix, uri) in namespaces.declared {
+            self.write_namespace(prefix, uri)?;
+        }
+
+        for uri in namespaces.elements {
+            if self.lookup_prefix(uri, true).is_none() {
+                self.declare_namespace("", uri, true)?;
+            }
+        }
+
+        for uri in namespaces.attributes {
+            if self.lookup_prefix(uri, false).is_none() {
+                let prefix = self.generate_prefix();
+                self.write_namespace(&prefix, uri)?;
+            }
+        }
+
+        Ok(())
     }
 
     pub fn write_attribute(&mut self, key: &str, value: &str) -> Result<()> {
-        write!(self.inner, r#" {}="{}""#, key, xml_escape(value))
+        let value = self.check_chars(value)?;
+        if let Some(element) = self.tree.as_mut().and_then(|tree| tree.last_mut()) {
+            element.attributes.push((key.to_owned(), value.into_owned()));
+            return Ok(());
+        }
+        write!(self, r#" {}="{}""#, key, xml_escape_attribute(&value, self.encoding))
+    }
+
+    /// Writes an attribute as it was read, passing `xmlns` and `xmlns:prefix`
+    /// declarations to [`XmlWriter::write_namespace`] so they aren't repeated.
+    pub fn write_attribute_or_namespace(&mut self, key: &str, value: &str) -> Result<()> {
+        match key.strip_prefix("xmlns") {
+            Some("") => self.write_namespace("", value),
+            Some(prefix) if prefix.starts_with(':') => self.write_namespace(&prefix[1..], value),
+            _ => self.write_attribute(key, value),
+        }
+    }
+
+    /// Writes `xsi:nil="true"`, declaring the `xsi` prefix on the current
+    /// element if no prefix for the namespace is in scope.
+    pub fn write_nil(&mut self) -> Result<()> {
+        let prefix = match self.lookup_prefix(XSI_NAMESPACE, false) {
+            Some(prefix) => prefix,
+            None => {
+                self.write_namespace("xsi", XSI_NAMESPACE)?;
+                "xsi".to_owned()
+            }
+        };
+
+        self.write_attribute(&format!("{}:nil", prefix), "true")
+    }
+
+    /// Writes an attribute in namespace `ns`, declaring a prefix for it on
+    /// the current element if none is in scope.
+    pub fn write_attribute_ns(&mut self, ns: &str, key: &str, value: &str) -> Result<()> {
+        if ns.is_empty() {
+            return self.write_attribute(key, value);
+        }
+
+        let prefix = match self.lookup_prefix(ns, false) {
+            Some(prefix) => prefix,
+            None => {
+                let prefix = self.generate_prefix();
+                self.write_namespace(&prefix, ns)?;
+                prefix
+            }
+        };
+
+        self.write_attribute(&format!("{}:{}", prefix, key), value)
     }
 
     pub fn write_text(&mut self, content: &str) -> Result<()> {
-        write!(self.inner, "{}", xml_escape(content))
+        let content = self.check_chars(content)?;
+        let inline = self.mark_text();
+        if let Some(element) = self.tree.as_mut().and_then(|tree| tree.last_mut()) {
+            match element.children.last_mut() {
+                Some(dom::Node::Text(text)) => text.push_str(&content),
+                _ => element.children.push(dom::Node::Text(content.into_owned())),
+            }
+            return Ok(());
+        }
+        let content = xml_escape_text(&content, self.encoding);
+
+        if inline && content.contains(['\n', '\r']) {
+            let content = content.replace('\n', "&#10;").replace('\r', "&#13;");
+            write!(self, "{}", content)
+        } else {
+            write!(self, "{}", content)
+        }
     }
 
+    /// Writes `content` as CDATA, split into several sections where it
+    /// contains `]]>`.
     pub fn write_cdata_text(&mut self, content: &str) -> Result<()> {
-        write!(self.inner, "<![CDATA[{}]]>", content)
+        let content = &*self.check_chars(content)?;
+        self.mark_text();
+        if let Some(element) = self.tree.as_mut().and_then(|tree| tree.last_mut()) {
+            element.children.push(dom::Node::CData(content.to_owned()));
+            return Ok(());
+        }
+
+        // `]]>` would end the section early, so it's split between two
+        let mut start = 0;
+        for (index, _) in content.match_indices("]]>") {
+            self.write_cdata_section(&content[start..index + 2])?;
+            start = index + 2;
+        }
+        self.write_cdata_section(&content[start..])
+    }
+
+    /// Writes `content` as CDATA if it contains markup characters and fits
+    /// into a single section, as escaped text otherwise.
+    ///
+    /// Carriage returns and characters the encoding can't represent only
+    /// survive as character references, so they make it use text as well.
+    pub fn write_text_or_cdata(&mut self, content: &str) -> Result<()> {
+        let has_markup = content.contains(['<', '>', '&']);
+        let fits = !content.contains("]]>")
+            && !content.contains('\r')
+            && content.chars().all(|c| self.encoding.can_encode(c));
+
+        if has_markup && fits {
+            self.write_cdata_text(content)
+        } else {
+            self.write_text(content)
+        }
+    }
+
+    fn write_cdata_section(&mut self, content: &str) -> Result<()> {
+        // character references aren't recognized in CDATA sections, so they
+        // are written between them
+        let mut rest = content;
+        while let Some((index, c)) = rest
+            .char_indices()
+            .find(|(_, c)| !self.encoding.can_encode(*c))
+        {
+            if index > 0 {
+                write!(self, "<![CDATA[{}]]>", &rest[..index])?;
+            }
+            write!(self, "&#x{:X};", c as u32)?;
+            rest = &rest[index + c.len_utf8()..];
+        }
+
+        if rest.is_empty() && rest.len() != content.len() {
+            Ok(())
+        } else {
+            write!(self, "<![CDATA[{}]]>", rest)
+        }
+    }
+
+    /// Marks the content of the current element as mixed, so no line breaks
+    /// or indentation are added between its children.
+    pub fn preserve_whitespace(&mut self) {
+        if let Some(element) = self.elements.last_mut() {
+            element.preserve = true;
+        }
+    }
+
+    /// Writes `<!--content-->`.
+    ///
+    /// Comments can't contain `--` or end with `-`, this fails with
+    /// [`ErrorKind::InvalidInput`] for such content.
+    pub fn write_comment(&mut self, content: &str) -> Result<()> {
+        if content.contains("--") || content.ends_with('-') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "comment must not contain `--` or end with `-`",
+            ));
+        }
+
+        let content = self.check_chars(content)?;
+        self.push_node()?;
+        if let Some(element) = self.tree.as_mut().and_then(|tree| tree.last_mut()) {
+            element.children.push(dom::Node::Comment(content.into_owned()));
+            return Ok(());
+        }
+        write!(self, "<!--{}-->", content)
+    }
+
+    /// Writes `<?target content?>`, or `<?target?>` if `content` is empty.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if `content` contains `?>` or
+    /// `target` is the reserved name `xml`.
+    pub fn write_processing_instruction(&mut self, target: &str, content: &str) -> Result<()> {
+        if target.eq_ignore_ascii_case("xml") || content.contains("?>") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "processing instruction must not be named `xml` or contain `?>`",
+            ));
+        }
+
+        let content = self.check_chars(content)?;
+        self.push_node()?;
+        // element trees don't keep processing instructions
+        if self.tree.is_some() {
+            return Ok(());
+        }
+        if content.is_empty() {
+            write!(self, "<?{}?>", target)
+        } else {
+            write!(self, "<?{} {}?>", target, content)
+        }
+    }
+
+    /// Writes a comment before the child element at `position` of the
+    /// current element, or right away if that many children have been
+    /// written already.
+    ///
+    /// Comments whose child element is never written come before the end tag.
+    pub fn write_comment_at(&mut self, position: usize, content: &str) -> Result<()> {
+        match self.elements.last_mut() {
+            Some(element) if element.children < position => {
+                element.deferred.push((position, Misc::Comment(content.to_owned())));
+                Ok(())
+            }
+            _ => self.write_comment(content),
+        }
+    }
+
+    /// Writes a processing instruction at `position` among the children of
+    /// the current element, like [`XmlWriter::write_comment_at`].
+    pub fn write_processing_instruction_at(
+        &mut self,
+        position: usize,
+        target: &str,
+        content: &str,
+    ) -> Result<()> {
+        match self.elements.last_mut() {
+            Some(element) if element.children < position => {
+                let misc = Misc::ProcessingInstruction(target.to_owned(), content.to_owned());
+                element.deferred.push((position, misc));
+                Ok(())
+            }
+            _ => self.write_processing_instruction(target, content),
+        }
+    }
+
+    /// Writes `value` as the child element at `position` of the current
+    /// element, or right away if that many children have been written
+    /// already, like [`XmlWriter::write_comment_at`].
+    ///
+    /// Elements whose place is never reached come before the end tag.
+    pub fn write_element_at<T: XmlWrite>(&mut self, position: usize, value: &T) -> XmlResult<()> {
+        match self.elements.last() {
+            Some(element) if element.children < position => {
+                let misc = Misc::Element(value.to_element()?);
+                self.elements.last_mut().unwrap().deferred.push((position, misc));
+                Ok(())
+            }
+            _ => value.to_writer(self),
+        }
+    }
+
+    // writes the deferred nodes of the current element which come before its
+    // next child, or all of them at its end
+    fn write_deferred(&mut self, end: bool) -> Result<()> {
+        // a deferred element moves the place of the next child, so they are
+        // picked one at a time, comments before the element at their place
+        loop {
+            let element = match self.elements.last_mut() {
+                Some(element) => element,
+                None => return Ok(()),
+            };
+
+            let children = element.children;
+            let next = element
+                .deferred
+                .iter()
+                .enumerate()
+                .filter(|(_, (position, _))| end || *position <= children)
+                .min_by_key(|(_, (position, misc))| (*position, matches!(misc, Misc::Element(_))))
+                .map(|(index, _)| index);
+
+            let misc = match next {
+                Some(index) => element.deferred.remove(index).1,
+                None => return Ok(()),
+            };
+
+            match misc {
+                Misc::Comment(content) => self.write_comment(&content)?,
+                Misc::ProcessingInstruction(target, content) => {
+                    self.write_processing_instruction(&target, &content)?
+                }
+                Misc::Element(element) => element
+                    .to_writer(self)
+                    .map_err(|err| Error::new(ErrorKind::Other, err))?,
+            }
+        }
     }
 
     pub fn write_element_end_open(&mut self) -> Result<()> {
-        write!(self.inner, ">")
+        if self.tree.is_some() {
+            return Ok(());
+        }
+        write!(self, ">")
     }
 
     pub fn write_flatten_text(&mut self, tag: &str, content: &str, is_cdata: bool) -> Result<()> {
         self.write_element_start(tag)?;
+        self.write_flatten_text_content(tag, content, is_cdata)
+    }
+
+    pub fn write_flatten_text_ns(&mut self, ns: &str, tag: &str, content: &str, is_cdata: bool) -> Result<()> {
+        self.write_element_start_ns(ns, None, tag)?;
+        self.write_flatten_text_content(tag, content, is_cdata)
+    }
+
+    fn write_flatten_text_content(&mut self, tag: &str, content: &str, is_cdata: bool) -> Result<()> {
         self.write_element_end_open()?;
         if is_cdata {
             self.write_cdata_text(content)?;
@@ -57,10 +711,500 @@ impl<W: Write> XmlWriter<W> {
     }
 
     pub fn write_element_end_close(&mut self, tag: &str) -> Result<()> {
-        write!(self.inner, "</{}>", tag)
+        self.write_deferred(true)?;
+        let element = self.pop_element();
+
+        if let Some(element) = &element {
+            if element.has_children && !element.has_text && !element.preserve {
+                self.write_indent(self.elements.len())?;
+            }
+        }
+
+        if self.tree.is_some() {
+            self.finish_tree_element();
+            return Ok(());
+        }
+
+        match element.and_then(|element| element.name) {
+            Some(name) => write!(self, "</{}>", name),
+            None => write!(self, "</{}>", tag),
+        }
     }
 
     pub fn write_element_end_empty(&mut self) -> Result<()> {
-        write!(self.inner, "/>")
+        self.pop_element();
+        if self.tree.is_some() {
+            self.finish_tree_element();
+            return Ok(());
+        }
+        write!(self, "/>")
+    }
+
+    // moves the element just ended into the content of its parent
+    fn finish_tree_element(&mut self) {
+        if let Some(tree) = &mut self.tree {
+            if let (Some(element), Some(parent)) = (tree.pop(), tree.last_mut()) {
+                parent.children.push(dom::Node::Element(element));
+            }
+        }
+    }
+
+    fn push_element(&mut self) -> Result<()> {
+        let preserve = self.push_node()?;
+
+        if let Some(parent) = self.elements.last_mut() {
+            parent.children += 1;
+        }
+
+        self.elements.push(Element {
+            name: None,
+            has_children: false,
+            children: 0,
+            deferred: Vec::new(),
+            has_text: false,
+            preserve,
+        });
+
+        Ok(())
+    }
+
+    // starts a child node of the current element on a new line unless
+    // whitespace is significant there, returns whether it is
+    fn push_node(&mut self) -> Result<bool> {
+        let depth = self.elements.len();
+
+        let preserve = match self.elements.last_mut() {
+            Some(parent) => {
+                parent.has_children = true;
+                parent.preserve || parent.has_text
+            }
+            None => false,
+        };
+
+        if !preserve && (depth > 0 || self.started) {
+            self.write_indent(depth)?;
+        }
+
+        self.started = true;
+        Ok(preserve)
+    }
+
+    fn pop_element(&mut self) -> Option<Element> {
+        let depth = self.elements.len();
+        while matches!(self.namespaces.last(), Some(ns) if ns.depth >= depth) {
+            self.namespaces.pop();
+        }
+        self.elements.pop()
+    }
+
+    // marks the current element as containing text, returns whether line
+    // breaks in it should be escaped
+    fn mark_text(&mut self) -> bool {
+        match self.elements.last_mut() {
+            Some(element) => {
+                element.has_text = true;
+                !element.has_children && matches!(self.format, Some(XmlFormat { inline_text: true, .. }))
+            }
+            None => false,
+        }
+    }
+
+    // applies `invalid_chars` to the characters XML 1.0 doesn't allow
+    fn check_chars<'c>(&self, content: &'c str) -> Result<Cow<'c, str>> {
+        // U+FFFE and U+FFFF start with 0xEF in UTF-8
+        let suspicious = content
+            .bytes()
+            .any(|b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r')) || b == 0xEF);
+
+        let invalid = match suspicious.then(|| content.chars().find(|c| !is_xml_char(*c))) {
+            Some(Some(invalid)) => invalid,
+            _ => return Ok(Cow::Borrowed(content)),
+        };
+
+        match self.invalid_chars {
+            XmlInvalidChars::Reject => Err(Error::new(
+                ErrorKind::InvalidData,
+                XmlError::InvalidChar { character: invalid },
+            )),
+            XmlInvalidChars::Replace => Ok(Cow::Owned(
+                content
+                    .chars()
+                    .map(|c| if is_xml_char(c) { c } else { '\u{FFFD}' })
+                    .collect(),
+            )),
+        }
+    }
+
+    fn write_indent(&mut self, depth: usize) -> Result<()> {
+        if let Some(format) = &self.format {
+            let mut encoder = Encoder::new(&mut self.inner, self.encoding);
+            encoder.encode(&format.newline)?;
+            for _ in 0..depth {
+                encoder.encode(&format.indent)?;
+            }
+        }
+        Ok(())
+    }
+
+    // target of `write!`, which encodes the output
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        if self.encoding == XmlEncoding::Utf8 {
+            return self.inner.write_fmt(args);
+        }
+
+        let mut encoder = Encoder::new(&mut self.inner, self.encoding);
+        match fmt::Write::write_fmt(&mut encoder, args) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(encoder
+                .error
+                .unwrap_or_else(|| Error::new(ErrorKind::Other, "formatter error"))),
+        }
+    }
+
+    fn lookup_namespace(&self, prefix: &str) -> Option<&str> {
+        if prefix == "xml" {
+            return Some(XML_NAMESPACE);
+        }
+
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|ns| ns.prefix == prefix)
+            .map(|ns| ns.uri.as_str())
+    }
+
+    fn lookup_prefix(&self, uri: &str, allow_default: bool) -> Option<String> {
+        if uri == XML_NAMESPACE {
+            return Some("xml".to_owned());
+        }
+
+        self.namespaces
+            .iter()
+            .rev()
+            .filter(|ns| allow_default || !ns.prefix.is_empty())
+            .find(|ns| ns.uri == uri && self.lookup_namespace(&ns.prefix) == Some(uri))
+            .map(|ns| ns.prefix.clone())
+    }
+
+    fn generate_prefix(&self) -> String {
+        (0..)
+            .map(|i| format!("ns{}", i))
+            .find(|prefix| self.namespaces.iter().all(|ns| &ns.prefix != prefix))
+            .unwrap()
+    }
+}
+
+impl XmlWriter<std::io::Sink> {
+    /// Creates a writer which builds element trees instead of writing text,
+    /// see [`XmlWrite::to_element`](crate::XmlWrite::to_element).
+    pub(crate) fn tree() -> Self {
+        XmlWriter {
+            tree: Some(vec![dom::Element::default()]),
+            ..XmlWriter::new(std::io::sink())
+        }
+    }
+
+    /// Returns the first element written at the top level.
+    pub(crate) fn into_element(self) -> Option<dom::Element> {
+        let root = self.tree?.into_iter().next()?;
+        root.children.into_iter().find_map(|node| match node {
+            dom::Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+}
+
+// whether `c` matches the `Char` production of XML 1.0
+fn is_xml_char(c: char) -> bool {
+    !matches!(
+        c,
+        '\u{0}'..='\u{8}' | '\u{B}' | '\u{C}' | '\u{E}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}'
+    )
+}
+
+/// Namespaces used by a type and its descendants, collected by
+/// [`XmlWrite::collect_namespaces`](crate::XmlWrite::collect_namespaces).
+#[derive(Default)]
+pub struct XmlNamespaces {
+    declared: Vec<(&'static str, &'static str)>,
+    elements: Vec<&'static str>,
+    attributes: Vec<&'static str>,
+    visited: Vec<&'static str>,
+}
+
+impl XmlNamespaces {
+    /// Returns `false` if the type named `type_name` has been visited before.
+    pub fn visit(&mut self, type_name: &'static str) -> bool {
+        if self.visited.contains(&type_name) {
+            false
+        } else {
+            self.visited.push(type_name);
+            true
+        }
+    }
+
+    /// Adds a namespace that should be bound to `prefix`, or be the default
+    /// namespace if `prefix` is empty.
+    pub fn declare(&mut self, prefix: &'static str, uri: &'static str) {
+        if !self.declared.contains(&(prefix, uri)) {
+            self.declared.push((prefix, uri));
+        }
+    }
+
+    /// Adds a namespace used by elements, which may be the default namespace.
+    pub fn require_element(&mut self, uri: &'static str) {
+        if !self.elements.contains(&uri) {
+            self.elements.push(uri);
+        }
+    }
+
+    /// Adds a namespace used by attributes, which needs a non-empty prefix.
+    pub fn require_attribute(&mut self, uri: &'static str) {
+        if !self.attributes.contains(&uri) {
+            self.attributes.push(uri);
+        }
     }
 }
+
+#[test]
+fn write_namespaces() -> Result<()> {
+    let mut writer = XmlWriter::new(Vec::new());
+
+    writer.write_element_start_ns("urn:a", Some("a"), "root")?;
+    writer.write_namespace_declarations(|namespaces| {
+        namespaces.declare("a", "urn:a");
+        namespaces.declare("", "urn:b");
+        namespaces.require_element("urn:a");
+        namespaces.require_element("urn:d");
+        namespaces.require_attribute("urn:c");
+    })?;
+    writer.write_element_end_open()?;
+
+    writer.write_element_start_ns("urn:a", None, "child")?;
+    writer.write_attribute_ns("urn:c", "attr", "1")?;
+    writer.write_attribute_ns("urn:d", "attr", "2")?;
+    writer.write_element_end_empty()?;
+
+    writer.write_element_start_ns("urn:b", None, "child")?;
+    writer.write_namespace("a", "urn:e")?;
+    writer.write_element_end_open()?;
+    writer.write_element_start_ns("urn:a", None, "child")?;
+    writer.write_element_end_empty()?;
+    writer.write_element_end_close("child")?;
+
+    writer.write_element_start_ns("urn:d", None, "child")?;
+    writer.write_attribute_ns(XML_NAMESPACE, "lang", "en")?;
+    writer.write_element_end_empty()?;
+
+    writer.write_element_end_close("root")?;
+
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        concat!(
+            r#"<a:root xmlns:a="urn:a" xmlns="urn:b" xmlns:ns0="urn:c">"#,
+            r#"<a:child ns0:attr="1" xmlns:ns1="urn:d" ns1:attr="2"/>"#,
+            r#"<child xmlns:a="urn:e"><child xmlns="urn:a"/></child>"#,
+            r#"<child xmlns="urn:d" xml:lang="en"/>"#,
+            r#"</a:root>"#
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_formatted() -> Result<()> {
+    let mut writer = XmlWriter::with_format(
+        Vec::new(),
+        XmlFormat {
+            indent: "\t".to_owned(),
+            newline: "\r\n".to_owned(),
+            inline_text: true,
+        },
+    );
+
+    writer.write_element_start("root")?;
+    writer.write_element_end_open()?;
+    writer.write_element_start("empty")?;
+    writer.write_element_end_empty()?;
+    writer.write_flatten_text("text", "a\nb", false)?;
+    writer.write_element_start("mixed")?;
+    writer.write_element_end_open()?;
+    writer.write_text(" a ")?;
+    writer.write_flatten_text("b", "c", false)?;
+    writer.write_element_end_close("mixed")?;
+    writer.write_element_start("nested")?;
+    writer.write_element_end_open()?;
+    writer.write_flatten_text("cdata", "\n", true)?;
+    writer.write_element_end_close("nested")?;
+    writer.write_element_end_close("root")?;
+
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        concat!(
+            "<root>\r\n",
+            "\t<empty/>\r\n",
+            "\t<text>a&#10;b</text>\r\n",
+            "\t<mixed> a <b>c</b></mixed>\r\n",
+            "\t<nested>\r\n",
+            "\t\t<cdata><![CDATA[\n]]></cdata>\r\n",
+            "\t</nested>\r\n",
+            "</root>"
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_declaration() -> Result<()> {
+    let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+
+    writer.write_declaration(&XmlDeclaration {
+        standalone: Some(true),
+        ..XmlDeclaration::default()
+    })?;
+    writer.write_root_declaration()?;
+    writer.write_element_start("root")?;
+    writer.write_element_end_empty()?;
+
+    assert_eq!(
+        writer.write_declaration(&XmlDeclaration::default()).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            "\n",
+            "<root/>"
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_encoded() -> Result<()> {
+    let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+    writer.set_encoding(XmlEncoding::Latin1);
+
+    writer.write_root_declaration()?;
+    writer.write_element_start("root")?;
+    writer.write_attribute("name", "Jos\u{E9} \u{2603}")?;
+    writer.write_element_end_open()?;
+    writer.write_flatten_text("text", "\u{C5}\u{20AC}", false)?;
+    writer.write_flatten_text("cdata", "<\u{20AC}\u{20AC}>", true)?;
+    writer.write_element_end_close("root")?;
+
+    assert_eq!(
+        writer.into_inner(),
+        concat!(
+            r#"<?xml version="1.0" encoding="ISO-8859-1"?>"#,
+            "\n",
+            "<root name=\"Jos\u{E9} &#x2603;\">\n",
+            "  <text>\u{C5}&#x20AC;</text>\n",
+            "  <cdata><![CDATA[<]]>&#x20AC;&#x20AC;<![CDATA[>]]></cdata>\n",
+            "</root>"
+        )
+        .chars()
+        .map(|c| c as u8)
+        .collect::<Vec<_>>()
+    );
+
+    let mut writer = XmlWriter::new(Vec::new());
+    writer.set_encoding(XmlEncoding::Ascii);
+
+    assert_eq!(
+        writer.write_element_start("\u{E9}").unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_invalid_chars() -> Result<()> {
+    let mut writer = XmlWriter::new(Vec::new());
+
+    writer.write_element_start("root")?;
+    let err = writer.write_attribute("a", "\u{0}").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(matches!(
+        XmlError::from(err),
+        XmlError::InvalidChar { character: '\u{0}' }
+    ));
+
+    writer.set_invalid_chars(XmlInvalidChars::Replace);
+    writer.write_attribute("a", "\t\u{1}\n")?;
+    writer.write_element_end_open()?;
+    writer.write_text("\r\n\u{FFFF}")?;
+    writer.write_cdata_text("\u{1B}")?;
+    writer.write_element_end_close("root")?;
+
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        "<root a=\"&#x9;\u{FFFD}&#xA;\">&#xD;\n\u{FFFD}<![CDATA[\u{FFFD}]]></root>"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_cdata() -> Result<()> {
+    let mut writer = XmlWriter::new(Vec::new());
+
+    writer.write_element_start("root")?;
+    writer.write_element_end_open()?;
+    writer.write_cdata_text("a]]>b]]>")?;
+    writer.write_text_or_cdata("<b>")?;
+    writer.write_text_or_cdata("plain")?;
+    writer.write_text_or_cdata("<![CDATA[]]>")?;
+    writer.write_element_end_close("root")?;
+
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        concat!(
+            "<root>",
+            "<![CDATA[a]]]]><![CDATA[>b]]]]><![CDATA[>]]>",
+            "<![CDATA[<b>]]>",
+            "plain",
+            "&lt;![CDATA[]]&gt;",
+            "</root>"
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_misc() -> Result<()> {
+    let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+
+    writer.write_element_start("root")?;
+    writer.write_element_end_open()?;
+    writer.write_comment(" note ")?;
+    writer.write_processing_instruction("app", "a=\"1\"")?;
+    writer.write_processing_instruction("empty", "")?;
+    writer.write_element_end_close("root")?;
+
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        concat!(
+            "<root>\n",
+            "  <!-- note -->\n",
+            "  <?app a=\"1\"?>\n",
+            "  <?empty?>\n",
+            "</root>"
+        )
+    );
+
+    let mut writer = XmlWriter::new(Vec::new());
+    assert!(writer.write_comment("a--b").is_err());
+    assert!(writer.write_comment("a-").is_err());
+    assert!(writer.write_processing_instruction("XML", "").is_err());
+    assert!(writer.write_processing_instruction("t", "?>").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use hard_xml::dom::{Element, Node};
+use hard_xml::{Positioned, XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "item", strict(unknown_attribute, unknown_element))]
+struct Item<'a> {
+    #[xml(attr = "id")]
+    id: u32,
+    #[xml(any_attr)]
+    other_attrs: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    #[xml(flatten_text = "name")]
+    name: Cow<'a, str>,
+    #[xml(any_element)]
+    other_elements: Vec<Element>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "item")]
+struct OrderedItem<'a> {
+    #[xml(flatten_text = "name")]
+    name: Cow<'a, str>,
+    #[xml(child = "tag")]
+    tags: Vec<Tag<'a>>,
+    #[xml(any_element)]
+    other_elements: Vec<Positioned<Element>>,
+    #[xml(comment)]
+    comments: Vec<Positioned<String>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "tag")]
+struct Tag<'a> {
+    #[xml(prefix = "data")]
+    data: HashMap<Cow<'a, str>, Cow<'a, str>>,
+    #[xml(any_attr)]
+    other: HashMap<String, String>,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<item xmlns:v="urn:vendor" id="1" v:color="red &amp; blue"><v:price currency="EUR">10</v:price><name>a</name><v:note/></item>"#;
+    let item = Item::from_str(xml)?;
+
+    assert_eq!(item.id, 1);
+    assert_eq!(
+        item.other_attrs,
+        vec![
+            ("xmlns:v".into(), "urn:vendor".into()),
+            ("v:color".into(), "red & blue".into()),
+        ]
+    );
+    assert_eq!(item.name, "a");
+    assert_eq!(item.other_elements.len(), 2);
+    assert_eq!(item.other_elements[0].name, "v:price");
+    assert_eq!(item.other_elements[0].attribute("currency"), Some("EUR"));
+    assert_eq!(item.other_elements[0].text(), "10");
+    assert_eq!(item.other_elements[1], Element::new("v:note"));
+
+    // declared attributes come first, other elements go after the known ones
+    assert_eq!(
+        item.to_string()?,
+        r#"<item id="1" xmlns:v="urn:vendor" v:color="red &amp; blue"><name>a</name><v:price currency="EUR">10</v:price><v:note/></item>"#
+    );
+    assert_eq!(Item::from_str(&item.to_string()?)?, item);
+
+    // `Positioned` elements are written back where they were
+    let xml = r#"<item><v:price>10</v:price><tag/><!--c--><v:note/><name>a</name><v:end/></item>"#;
+    let item = OrderedItem::from_str(xml)?;
+    assert_eq!(
+        item.other_elements,
+        vec![
+            Positioned {
+                position: 0,
+                value: Element {
+                    name: "v:price".into(),
+                    attributes: vec![],
+                    children: vec![Node::Text("10".into())],
+                },
+            },
+            Positioned {
+                position: 2,
+                value: Element::new("v:note"),
+            },
+            Positioned {
+                position: 4,
+                value: Element::new("v:end"),
+            },
+        ]
+    );
+    assert_eq!(
+        item.comments,
+        vec![Positioned {
+            position: 2,
+            value: "c".into(),
+        }]
+    );
+    assert_eq!(item.to_string()?, xml);
+
+    // attributes claimed by a `prefix` field are left out
+    let tag = Tag::from_str(r#"<tag data:a="1" b="2"/>"#)?;
+    assert_eq!(tag.data, HashMap::from([("a".into(), "1".into())]));
+    assert_eq!(tag.other, HashMap::from([("b".to_owned(), "2".to_owned())]));
+
+    Ok(())
+}
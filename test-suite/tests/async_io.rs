@@ -0,0 +1,66 @@
+use hard_xml::{XmlRead, XmlReadOwned, XmlResult, XmlWrite};
+use tokio::io::AsyncWriteExt;
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "inventory")]
+struct Inventory {
+    #[xml(child = "item")]
+    items: Vec<Item>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "item")]
+struct Item {
+    #[xml(attr = "sku")]
+    sku: u32,
+    #[xml(text)]
+    name: String,
+}
+
+// the current-thread runtime, where nothing may block
+#[tokio::test]
+async fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let inventory = Inventory {
+        items: (0..2000)
+            .map(|sku| Item {
+                sku,
+                name: format!("item <{}>", sku),
+            })
+            .collect(),
+    };
+
+    // the output is much larger than the pipe, so both sides have to take turns
+    let (mut client, server) = tokio::io::duplex(64);
+
+    let read = tokio::spawn(Inventory::from_async_read(server));
+    inventory.to_async_writer(&mut client).await?;
+    drop(client);
+
+    assert_eq!(read.await.unwrap()?, inventory);
+
+    // parsing finishes at the end of the root element, without waiting for
+    // the end of the input
+    let (mut client, server) = tokio::io::duplex(64);
+    client.write_all(b"<inventory><item sku=\"1\">a</item></inventory>").await?;
+    assert_eq!(
+        Inventory::from_async_read(server).await?,
+        Inventory {
+            items: vec![Item {
+                sku: 1,
+                name: "a".into(),
+            }],
+        }
+    );
+
+    let (mut client, server) = tokio::io::duplex(64);
+    client.write_all(b"<inventory><item sku=\"x\">a</item></inventory>").await?;
+    drop(client);
+    assert!(Inventory::from_async_read(server).await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use hard_xml::{XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "script")]
+struct Script(#[xml(text, cdata = "auto")] String);
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "page")]
+struct Page {
+    #[xml(child = "script")]
+    script: Script,
+    #[xml(flatten_text = "style", cdata = "auto")]
+    styles: Vec<String>,
+    #[xml(flatten_text = "raw", cdata)]
+    raw: String,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let page = Page {
+        script: Script("if (a < b && c) {}".into()),
+        styles: vec!["p { color: red }".into(), "a > b".into(), "x]]>y <".into()],
+        raw: "]]>".into(),
+    };
+
+    let xml = page.to_string()?;
+    assert_eq!(
+        xml,
+        concat!(
+            "<page>",
+            "<script><![CDATA[if (a < b && c) {}]]></script>",
+            "<style>p { color: red }</style>",
+            "<style><![CDATA[a > b]]></style>",
+            "<style>x]]&gt;y &lt;</style>",
+            "<raw><![CDATA[]]]]><![CDATA[>]]></raw>",
+            "</page>"
+        )
+    );
+    assert_eq!(Page::from_str(&xml)?, page);
+
+    Ok(())
+}
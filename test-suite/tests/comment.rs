@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+
+use hard_xml::{Positioned, XmlFormat, XmlRead, XmlResult, XmlWrite, XmlWriter};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "config")]
+struct Config<'a> {
+    #[xml(comment)]
+    comments: Vec<Cow<'a, str>>,
+    #[xml(pi = "editor")]
+    editor: Option<Cow<'a, str>>,
+    #[xml(pi = "version")]
+    version: Option<u32>,
+    #[xml(child = "server")]
+    servers: Vec<Server<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "server")]
+struct Server<'a> {
+    #[xml(comment)]
+    note: Option<Cow<'a, str>>,
+    #[xml(flatten_text = "host")]
+    host: Cow<'a, str>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "settings")]
+struct Settings<'a> {
+    #[xml(comment)]
+    comments: Vec<Positioned<Cow<'a, str>>>,
+    #[xml(pi = "editor")]
+    editor: Option<Positioned<Cow<'a, str>>>,
+    #[xml(flatten_text = "timeout")]
+    timeout: u32,
+    #[xml(flatten_text = "retries")]
+    retries: u32,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<config>
+  <!-- servers used in production -->
+  <?editor mode="xml"?>
+  <?version 2?>
+  <?unknown?>
+  <server>
+    <!-- primary -->
+    <host>a<!-- not kept -->.example</host>
+    <!-- dropped, only the first one is kept -->
+  </server>
+  <!-- keep at least one -->
+  <server>
+    <host>b.example</host>
+  </server>
+</config>"#;
+
+    let config = Config::from_str(xml)?;
+
+    assert_eq!(
+        config,
+        Config {
+            comments: vec![" servers used in production ".into(), " keep at least one ".into()],
+            editor: Some(r#"mode="xml""#.into()),
+            version: Some(2),
+            servers: vec![
+                Server {
+                    note: Some(" primary ".into()),
+                    host: "a.example".into(),
+                },
+                Server {
+                    note: None,
+                    host: "b.example".into(),
+                },
+            ],
+        }
+    );
+
+    let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+    config.to_writer(&mut writer)?;
+    let written = String::from_utf8(writer.into_inner()).unwrap();
+
+    assert_eq!(
+        written,
+        r#"<config>
+  <!-- servers used in production -->
+  <!-- keep at least one -->
+  <?editor mode="xml"?>
+  <?version 2?>
+  <server>
+    <!-- primary -->
+    <host>a.example</host>
+  </server>
+  <server>
+    <host>b.example</host>
+  </server>
+</config>"#
+    );
+    assert_eq!(Config::from_str(&written)?, config);
+
+    let config = Config {
+        comments: vec!["a -- b".into()],
+        editor: None,
+        version: None,
+        servers: vec![],
+    };
+    assert!(config.to_string().is_err());
+
+    // positioned comments are written back between the same children
+    let xml = r#"<settings>
+  <!-- in seconds -->
+  <timeout>30</timeout>
+  <unknown/>
+  <!-- per request -->
+  <?editor fold?>
+  <retries>3</retries>
+  <!-- end -->
+</settings>"#;
+
+    let settings = Settings::from_str(xml)?;
+
+    assert_eq!(
+        settings,
+        Settings {
+            comments: vec![
+                Positioned {
+                    position: 0,
+                    value: " in seconds ".into(),
+                },
+                Positioned {
+                    position: 1,
+                    value: " per request ".into(),
+                },
+                Positioned {
+                    position: 2,
+                    value: " end ".into(),
+                },
+            ],
+            editor: Some(Positioned {
+                position: 1,
+                value: "fold".into(),
+            }),
+            timeout: 30,
+            retries: 3,
+        }
+    );
+
+    let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+    settings.to_writer(&mut writer)?;
+    let written = String::from_utf8(writer.into_inner()).unwrap();
+
+    assert_eq!(written, xml.replace("\n  <unknown/>", ""));
+    assert_eq!(Settings::from_str(&written)?, settings);
+
+    Ok(())
+}
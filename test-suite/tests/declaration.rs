@@ -0,0 +1,91 @@
+use hard_xml::{XmlDeclaration, XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+#[xml(tag = "feed", declaration)]
+struct Feed {
+    #[xml(child = "entry")]
+    entries: Vec<Entry>,
+}
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+#[xml(tag = "entry")]
+struct Entry {
+    #[xml(attr = "id")]
+    id: u32,
+}
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+#[xml(tag = "wrapper")]
+struct Wrapper {
+    #[xml(child = "feed")]
+    feed: Feed,
+}
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+enum Document {
+    #[xml(tag = "feed", declaration)]
+    Feed(Feed),
+    #[xml(tag = "entry", declaration)]
+    Entry {
+        #[xml(attr = "id")]
+        id: u32,
+    },
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let feed = Feed {
+        entries: vec![Entry { id: 1 }],
+    };
+
+    assert_eq!(
+        feed.to_string()?,
+        r#"<?xml version="1.0" encoding="UTF-8"?><feed><entry id="1"/></feed>"#
+    );
+
+    assert_eq!(
+        feed.to_string_pretty()?,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed>\n  <entry id=\"1\"/>\n</feed>"
+    );
+
+    assert_eq!(Feed::from_str(&feed.to_string()?)?, feed);
+
+    assert_eq!(
+        feed.to_string_with_declaration(&XmlDeclaration {
+            version: "1.1".into(),
+            encoding: None,
+            standalone: Some(false),
+        })?,
+        r#"<?xml version="1.1" standalone="no"?><feed><entry id="1"/></feed>"#
+    );
+
+    assert_eq!(
+        Entry { id: 2 }.to_string_with_declaration(&XmlDeclaration::default())?,
+        r#"<?xml version="1.0" encoding="UTF-8"?><entry id="2"/>"#
+    );
+
+    assert_eq!(
+        Wrapper {
+            feed: Feed { entries: vec![] }
+        }
+        .to_string()?,
+        r#"<wrapper><feed/></wrapper>"#
+    );
+
+    assert_eq!(
+        Document::Feed(Feed { entries: vec![] }).to_string()?,
+        r#"<?xml version="1.0" encoding="UTF-8"?><feed/>"#
+    );
+
+    assert_eq!(
+        Document::Entry { id: 3 }.to_string()?,
+        r#"<?xml version="1.0" encoding="UTF-8"?><entry id="3"/>"#
+    );
+
+    Ok(())
+}
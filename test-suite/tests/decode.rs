@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use hard_xml::{XmlError, XmlRead, XmlResult};
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "city")]
+struct City<'a> {
+    #[xml(attr = "country")]
+    country: Cow<'a, str>,
+    #[xml(text)]
+    name: Cow<'a, str>,
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let mut buffer = String::new();
+
+    let city = City::from_bytes(
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><city country=\"M\xE9xico\">Le\xF3n</city>",
+        &mut buffer,
+    )?;
+    assert_eq!(city.country, "México");
+    assert_eq!(city.name, "León");
+
+    let mut buffer = String::new();
+    let bytes = utf16le(r#"<?xml version="1.0" encoding="UTF-16"?><city country="日本">東京</city>"#);
+    assert_eq!(
+        City::from_bytes(&bytes, &mut buffer)?,
+        City {
+            country: "日本".into(),
+            name: "東京".into()
+        }
+    );
+
+    let mut buffer = String::new();
+    let city = City::from_bytes(
+        b"<?xml version='1.0' encoding='windows-1252'?><city country=\"\x80\">&amp;\x93x\x94</city>",
+        &mut buffer,
+    )?;
+    assert_eq!(city.country, "€");
+    assert_eq!(city.name, "&\u{201C}x\u{201D}");
+
+    // UTF-8 input is read in place
+    let mut buffer = String::new();
+    let city = City::from_bytes("\u{FEFF}<city country=\"ES\">Málaga</city>".as_bytes(), &mut buffer)?;
+    assert!(matches!(city.name, Cow::Borrowed("Málaga")));
+    assert!(buffer.is_empty());
+
+    let mut buffer = String::new();
+    assert!(matches!(
+        City::from_bytes(br#"<?xml version="1.0" encoding="x-unknown"?><city/>"#, &mut buffer)
+            .map_err(XmlError::from),
+        Err(XmlError::UnsupportedEncoding { .. })
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use hard_xml::{XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "connection")]
+struct Connection<'a> {
+    #[xml(default_str = "30", skip_default, attr = "timeout")]
+    timeout: u32,
+    #[xml(default_str = "px", attr = "unit")]
+    unit: Cow<'a, str>,
+    #[xml(default_str = "yes", attr = "secure")]
+    secure: bool,
+    #[xml(default = "default_host", skip_default, flatten_text = "host")]
+    host: Cow<'a, str>,
+    #[xml(default = "default_port", child = "port")]
+    port: Port,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "port")]
+struct Port(#[xml(attr = "number")] u16);
+
+fn default_host() -> Cow<'static, str> {
+    "localhost".into()
+}
+
+fn default_port() -> Port {
+    Port(80)
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let connection = Connection::from_str("<connection/>")?;
+    assert_eq!(
+        connection,
+        Connection {
+            timeout: 30,
+            unit: "px".into(),
+            secure: true,
+            host: "localhost".into(),
+            port: Port(80),
+        }
+    );
+    assert_eq!(
+        connection.to_string()?,
+        r#"<connection unit="px" secure="1"><port number="80"/></connection>"#
+    );
+
+    let connection = Connection::from_str(
+        r#"<connection timeout="5" unit="em" secure="no"><host>a.example</host></connection>"#,
+    )?;
+    assert_eq!(
+        connection,
+        Connection {
+            timeout: 5,
+            unit: "em".into(),
+            secure: false,
+            host: "a.example".into(),
+            port: Port(80),
+        }
+    );
+    assert_eq!(
+        connection.to_string()?,
+        r#"<connection timeout="5" unit="em" secure="0"><port number="80"/><host>a.example</host></connection>"#
+    );
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::borrow::Cow;
+
+use hard_xml::{UnknownValue, XmlError, XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "shape", discriminator = "type")]
+enum Shape<'a> {
+    #[xml(value = "circle")]
+    Circle {
+        #[xml(attr = "radius")]
+        radius: u32,
+    },
+    #[xml(value = "square", strict(unknown_attribute))]
+    Square {
+        #[xml(attr = "side")]
+        side: u32,
+        #[xml(flatten_text = "label")]
+        label: Option<Cow<'a, str>>,
+    },
+    Empty,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "drawing")]
+struct Drawing<'a> {
+    #[xml(child = "shape")]
+    shapes: Vec<Shape<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "animal", discriminator = "xsi:type")]
+enum Animal {
+    #[xml(value = "zoo:Cat", value_ns = "urn:zoo", strict(unknown_attribute))]
+    Cat {
+        #[xml(attr = "lives")]
+        lives: u32,
+    },
+    #[xml(value = "zoo:Dog", value_ns = "urn:zoo")]
+    Dog,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "zoo")]
+struct Zoo {
+    #[xml(child = "animal")]
+    animals: Vec<Animal>,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<drawing><shape type="circle" radius="2"/><shape type="square" side="3"><label>a</label></shape><shape type="Empty"/></drawing>"#;
+    let drawing = Drawing::from_str(xml)?;
+
+    assert_eq!(
+        drawing,
+        Drawing {
+            shapes: vec![
+                Shape::Circle { radius: 2 },
+                Shape::Square {
+                    side: 3,
+                    label: Some("a".into()),
+                },
+                Shape::Empty,
+            ]
+        }
+    );
+    assert_eq!(drawing.to_string()?, xml);
+
+    // the discriminator doesn't have to come first
+    assert_eq!(
+        Shape::from_str(r#"<shape side="1" type="square"/>"#)?,
+        Shape::Square {
+            side: 1,
+            label: None,
+        }
+    );
+
+    let err = Shape::from_str(r#"<shape type="triangle"/>"#).unwrap_err();
+    match err.error() {
+        XmlError::FromStr(err) => assert_eq!(
+            err.downcast_ref::<UnknownValue>(),
+            Some(&UnknownValue {
+                value: "triangle".into(),
+                expected: &["circle", "square", "Empty"],
+            })
+        ),
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    match Shape::from_str(r#"<shape radius="2"/>"#).unwrap_err().error() {
+        XmlError::MissingField { name, field } => {
+            assert_eq!(name, "Shape");
+            assert_eq!(field, "type");
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    // bad elements are skipped in lenient mode
+    let (drawing, errors) = Drawing::from_str_lenient(
+        r#"<drawing><shape type="triangle"/><shape radius="1"><label>a</label></shape><shape type="circle" radius="2"/></drawing>"#,
+    );
+    assert_eq!(
+        drawing,
+        Some(Drawing {
+            shapes: vec![Shape::Circle { radius: 2 }],
+        })
+    );
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(errors[0].error(), XmlError::FromStr(_)));
+    assert!(matches!(errors[1].error(), XmlError::MissingField { .. }));
+
+    // `xsi:type` and its value are matched by namespace, whatever their prefixes
+    assert_eq!(
+        Animal::from_str(
+            r#"<animal xmlns:i="http://www.w3.org/2001/XMLSchema-instance" xmlns:z="urn:zoo" i:type="z:Dog"/>"#
+        )?,
+        Animal::Dog
+    );
+    assert_eq!(
+        Zoo::from_str(
+            r#"<zoo xmlns:i="http://www.w3.org/2001/XMLSchema-instance" xmlns:z="urn:zoo"><animal lives="9" i:type="z:Cat"/></zoo>"#
+        )?,
+        Zoo {
+            animals: vec![Animal::Cat { lives: 9 }],
+        }
+    );
+
+    let err = Animal::from_str(
+        r#"<animal xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:zoo="urn:other" xsi:type="zoo:Dog"/>"#,
+    )
+    .unwrap_err();
+    match err.error() {
+        XmlError::FromStr(err) => assert_eq!(
+            err.downcast_ref::<UnknownValue>(),
+            Some(&UnknownValue {
+                value: "zoo:Dog".into(),
+                expected: &["zoo:Cat", "zoo:Dog"],
+            })
+        ),
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    match Animal::from_str(r#"<animal type="zoo:Cat"/>"#).unwrap_err().error() {
+        XmlError::MissingField { name, field } => {
+            assert_eq!(name, "Animal");
+            assert_eq!(field, "xsi:type");
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    let zoo = Zoo {
+        animals: vec![Animal::Cat { lives: 9 }, Animal::Dog],
+    };
+    let xml = concat!(
+        r#"<zoo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:zoo="urn:zoo">"#,
+        r#"<animal xsi:type="zoo:Cat" lives="9"/><animal xsi:type="zoo:Dog"/>"#,
+        r#"</zoo>"#
+    );
+    assert_eq!(zoo.to_string()?, xml);
+    assert_eq!(Zoo::from_str(xml)?, zoo);
+
+    assert_eq!(
+        Animal::Dog.to_string()?,
+        r#"<animal xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xmlns:zoo="urn:zoo" xsi:type="zoo:Dog"/>"#
+    );
+
+    Ok(())
+}
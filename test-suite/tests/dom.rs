@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+
+use hard_xml::dom::{Element, Node};
+use hard_xml::{XmlArena, XmlError, XmlRead, XmlReadOwned, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "package")]
+struct Package {
+    #[xml(attr = "name")]
+    name: String,
+    #[xml(flatten_text = "version")]
+    version: String,
+    #[xml(child = "extension")]
+    extension: Option<Element>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "note")]
+struct Note<'a> {
+    #[xml(attr = "to")]
+    to: Cow<'a, str>,
+    #[xml(text)]
+    text: Cow<'a, str>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "entry", ns = "urn:e")]
+struct Entry {
+    #[xml(attr = "id", ns = "urn:ids")]
+    id: u32,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<package name="a"><extension><flag on="yes"/>text &amp; more</extension><version>1.0</version></package>"#;
+
+    // peek at the document before picking a type
+    let root = Element::from_str(xml)?;
+    assert_eq!(root.name, "package");
+    assert_eq!(root.attribute("name"), Some("a"));
+    assert_eq!(root.child("version").map(Element::text), Some("1.0".into()));
+    assert_eq!(Element::from_stream(xml.as_bytes())?, root);
+
+    let arena = XmlArena::new();
+    let package = Package::from_element(&root, &arena)?;
+    assert_eq!(package.name, "a");
+    assert_eq!(package.version, "1.0");
+
+    let extension = package.extension.as_ref().unwrap();
+    assert_eq!(extension.child("flag").unwrap().attribute("on"), Some("yes"));
+    assert_eq!(extension.text(), "text & more");
+
+    assert_eq!(package.to_element()?, root);
+    assert_eq!(package.to_string()?, xml);
+
+    // mutation
+    let mut root = root;
+    root.set_attribute("name", "b");
+    root.remove_child("extension");
+    let mut version = root.remove_child("version").unwrap();
+    version.children = vec![Node::Text("2.0".into())];
+    root.push_child(version);
+
+    assert_eq!(
+        Package::from_element(&root, &arena)?,
+        Package {
+            name: "b".into(),
+            version: "2.0".into(),
+            extension: None,
+        }
+    );
+
+    // values are borrowed from the element unless they have to be unescaped
+    let note = Element::from_str(r#"<note to="a &amp; b">hello</note>"#)?;
+    let read = Note::from_element(&note, &arena)?;
+    assert!(matches!(read.text, Cow::Borrowed("hello")));
+    assert_eq!(read.to, "a & b");
+    assert_eq!(read.to_element()?, note);
+
+    // namespace declarations are kept as attributes
+    let entry = Entry { id: 1 }.to_element()?;
+    assert_eq!(entry.name, "entry");
+    assert_eq!(entry.attribute("xmlns"), Some("urn:e"));
+    assert_eq!(entry.attribute("ns0:id"), Some("1"));
+    assert_eq!(Entry::from_element(&entry, &arena)?, Entry { id: 1 });
+    assert_eq!(Element::from_str(&entry.to_string()?)?, entry);
+
+    // elements are built without a position to report
+    let err = Package::from_element(&Element::new("package"), &arena).unwrap_err();
+    assert!(matches!(err.error(), XmlError::MissingField { .. }));
+    assert_eq!(err.position(), None);
+
+    Ok(())
+}
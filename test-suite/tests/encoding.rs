@@ -0,0 +1,70 @@
+use hard_xml::{XmlEncoding, XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+#[xml(tag = "partner")]
+struct Partner {
+    #[xml(attr = "name")]
+    name: String,
+    #[xml(flatten_text = "note")]
+    note: String,
+    #[xml(flatten_text = "raw", cdata)]
+    raw: String,
+}
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+#[xml(tag = "feed", declaration)]
+struct Feed {
+    #[xml(child = "partner")]
+    partners: Vec<Partner>,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let partner = Partner {
+        name: "Müller & Söhne".into(),
+        note: "Preis: 5 €".into(),
+        raw: "<€>".into(),
+    };
+
+    let latin1 = partner.to_bytes_with_encoding(XmlEncoding::Latin1)?;
+    assert_eq!(
+        latin1,
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\
+          <partner name=\"M\xFCller &amp; S\xF6hne\">\
+          <note>Preis: 5 &#x20AC;</note>\
+          <raw><![CDATA[<]]>&#x20AC;<![CDATA[>]]></raw>\
+          </partner>"
+    );
+    assert_eq!(Partner::from_bytes(&latin1, &mut String::new())?, partner);
+
+    let ascii = partner.to_bytes_with_encoding(XmlEncoding::Ascii)?;
+    assert_eq!(
+        ascii,
+        b"<?xml version=\"1.0\" encoding=\"US-ASCII\"?>\
+          <partner name=\"M&#xFC;ller &amp; S&#xF6;hne\">\
+          <note>Preis: 5 &#x20AC;</note>\
+          <raw><![CDATA[<]]>&#x20AC;<![CDATA[>]]></raw>\
+          </partner>"
+    );
+    assert_eq!(Partner::from_bytes(&ascii, &mut String::new())?, partner);
+
+    // `#[xml(declaration)]` names the encoding as well
+    let feed = Feed {
+        partners: vec![partner],
+    };
+    let ascii = feed.to_bytes_with_encoding(XmlEncoding::Ascii)?;
+    assert!(ascii.starts_with(b"<?xml version=\"1.0\" encoding=\"US-ASCII\"?><feed><partner "));
+    assert_eq!(Feed::from_bytes(&ascii, &mut String::new())?, feed);
+
+    assert_eq!(
+        feed.to_bytes_with_encoding(XmlEncoding::Utf8)?,
+        feed.to_string()?.into_bytes()
+    );
+
+    Ok(())
+}
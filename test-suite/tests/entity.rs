@@ -0,0 +1,79 @@
+use std::borrow::Cow;
+use std::io::BufReader;
+
+use hard_xml::{
+    HtmlEntities, XmlArena, XmlEntityLimits, XmlError, XmlRead, XmlReader, XmlResult,
+};
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "product")]
+struct Product<'a> {
+    #[xml(attr = "vendor")]
+    vendor: Cow<'a, str>,
+    #[xml(flatten_text = "name")]
+    name: Cow<'a, str>,
+}
+
+const DOCUMENT: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE product [
+    <!ENTITY % internal "ignored">
+    <!ENTITY company "Acme">
+    <!ENTITY product "&company; Rocket&#x2122;">
+]>
+<product vendor="&company;"><name>&product; &nbsp;&eacute;dition</name></product>"#;
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    // without a resolver only the declared entities are known
+    assert!(matches!(
+        Product::from_str(DOCUMENT).unwrap_err().error(),
+        XmlError::UnrecognizedSymbol { symbol } if symbol == "nbsp"
+    ));
+
+    let expected = Product {
+        vendor: "Acme".into(),
+        name: "Acme Rocket\u{2122} \u{A0}\u{E9}dition".into(),
+    };
+
+    let mut reader = XmlReader::new(DOCUMENT);
+    reader.set_entity_resolver(HtmlEntities);
+    assert_eq!(Product::from_reader(&mut reader)?, expected);
+
+    let arena = XmlArena::new();
+    let mut reader = XmlReader::from_stream(BufReader::with_capacity(4, DOCUMENT.as_bytes()), &arena);
+    reader.set_entity_resolver(HtmlEntities);
+    assert_eq!(Product::from_reader(&mut reader)?, expected);
+
+    let laughs = r#"<!DOCTYPE product [
+        <!ENTITY lol "lol">
+        <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+        <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+        <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+        <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+        <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+        <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+    ]>
+    <product vendor="&lol6;"><name/></product>"#;
+
+    assert!(matches!(
+        Product::from_str(laughs).unwrap_err().error(),
+        XmlError::EntityLimit { .. }
+    ));
+
+    let mut reader = XmlReader::new(laughs);
+    reader.set_entity_limits(XmlEntityLimits {
+        max_depth: 2,
+        ..XmlEntityLimits::default()
+    });
+    assert!(matches!(
+        Product::from_reader(&mut reader).unwrap_err(),
+        XmlError::EntityLimit { entity } if entity == "lol4"
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use hard_xml::{XmlError, XmlInvalidChars, XmlRead, XmlResult, XmlWrite, XmlWriter};
+
+#[derive(XmlRead, XmlWrite, PartialEq, Debug)]
+#[xml(tag = "note")]
+struct Note {
+    #[xml(attr = "title")]
+    title: String,
+    #[xml(text)]
+    body: String,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let note = Note {
+        title: "tab\there\r\nnext \"line\"".into(),
+        body: "keep\tthe\r\nbreaks <&>".into(),
+    };
+
+    let xml = note.to_string()?;
+    assert_eq!(
+        xml,
+        "<note title=\"tab&#x9;here&#xD;&#xA;next &quot;line&quot;\">keep\tthe&#xD;\nbreaks &lt;&amp;&gt;</note>"
+    );
+    assert_eq!(Note::from_str(&xml)?, note);
+
+    let invalid = Note {
+        title: "bell\u{7}".into(),
+        body: "nul\u{0}".into(),
+    };
+
+    assert!(matches!(
+        invalid.to_string().unwrap_err(),
+        XmlError::InvalidChar { character: '\u{7}' }
+    ));
+
+    let mut writer = XmlWriter::new(Vec::new());
+    writer.set_invalid_chars(XmlInvalidChars::Replace);
+    invalid.to_writer(&mut writer)?;
+    assert_eq!(
+        String::from_utf8(writer.into_inner())?,
+        "<note title=\"bell\u{FFFD}\">nul\u{FFFD}</note>"
+    );
+
+    Ok(())
+}
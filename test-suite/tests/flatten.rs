@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+use hard_xml::{XmlError, XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(fields)]
+struct Common<'a> {
+    #[xml(attr = "id")]
+    id: Cow<'a, str>,
+    #[xml(attr = "version", default)]
+    version: u32,
+    #[xml(child = "header")]
+    header: Option<Header<'a>>,
+    #[xml(flatten_text = "note")]
+    notes: Vec<Cow<'a, str>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "header")]
+struct Header<'a> {
+    #[xml(attr = "author")]
+    author: Cow<'a, str>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "document", strict(unknown_attribute, unknown_element))]
+struct Document<'a> {
+    #[xml(flatten)]
+    common: Common<'a>,
+    #[xml(attr = "title")]
+    title: Cow<'a, str>,
+    #[xml(child = "section")]
+    sections: Vec<Section<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "section")]
+struct Section<'a> {
+    #[xml(flatten)]
+    common: Common<'a>,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = concat!(
+        r#"<document id="d" version="2" title="Report">"#,
+        r#"<header author="a"/><section id="s1"/><note>draft</note>"#,
+        r#"<section id="s2"><note>one</note><note>two</note></section>"#,
+        r#"</document>"#
+    );
+    let document = Document::from_str(xml)?;
+
+    assert_eq!(
+        document,
+        Document {
+            common: Common {
+                id: "d".into(),
+                version: 2,
+                header: Some(Header { author: "a".into() }),
+                notes: vec!["draft".into()],
+            },
+            title: "Report".into(),
+            sections: vec![
+                Section {
+                    common: Common {
+                        id: "s1".into(),
+                        version: 0,
+                        header: None,
+                        notes: vec![],
+                    },
+                },
+                Section {
+                    common: Common {
+                        id: "s2".into(),
+                        version: 0,
+                        header: None,
+                        notes: vec!["one".into(), "two".into()],
+                    },
+                },
+            ],
+        }
+    );
+
+    // the flattened fields are written where the field is declared
+    assert_eq!(
+        document.to_string()?,
+        concat!(
+            r#"<document id="d" version="2" title="Report">"#,
+            r#"<header author="a"/><note>draft</note>"#,
+            r#"<section id="s1" version="0"/>"#,
+            r#"<section id="s2" version="0"><note>one</note><note>two</note></section>"#,
+            r#"</document>"#
+        )
+    );
+
+    match Section::from_str("<section/>").unwrap_err().error() {
+        XmlError::MissingField { name, field } => {
+            assert_eq!(name, "Common");
+            assert_eq!(field, "id");
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+
+    Ok(())
+}
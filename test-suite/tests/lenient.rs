@@ -0,0 +1,106 @@
+use hard_xml::{XmlError, XmlRead, XmlResult};
+use std::str::FromStr;
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "config", strict(unknown_element))]
+struct Config {
+    #[xml(attr = "version")]
+    version: u32,
+    #[xml(child = "server")]
+    servers: Vec<Server>,
+    #[xml(flatten_text = "timeout")]
+    timeout: Option<u32>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "server")]
+struct Server {
+    #[xml(attr = "name")]
+    name: String,
+    #[xml(attr = "cert")]
+    cert: Cert,
+}
+
+#[derive(PartialEq, Debug)]
+struct Cert(String);
+
+impl FromStr for Cert {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_suffix(".pem") {
+            Some(name) => Ok(Cert(name.into())),
+            None => Err(format!("{:?} is not a pem file", s)),
+        }
+    }
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let (config, errors) = Config::from_str_lenient(
+        r#"<config version="two">
+            <server name="a" cert="a.pem"/>
+            <server name="b" cert="b.crt"><nested><x/></nested></server>
+            <proxy/>
+            <server cert="c.pem"/>
+            <timeout>soon</timeout>
+        </config>"#,
+    );
+
+    assert_eq!(
+        config,
+        Some(Config {
+            version: 0,
+            servers: vec![
+                Server {
+                    name: "a".into(),
+                    cert: Cert("a".into()),
+                },
+                Server {
+                    name: "".into(),
+                    cert: Cert("c".into()),
+                },
+            ],
+            timeout: None,
+        })
+    );
+
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|err| {
+            let kind = match err.error() {
+                XmlError::FromStr(_) => "FromStr",
+                XmlError::MissingField { .. } => "MissingField",
+                XmlError::UnknownField { .. } => "UnknownField",
+                _ => "Other",
+            };
+            (kind, err.path().unwrap().elements.as_str())
+        })
+        .collect();
+
+    assert_eq!(
+        errors,
+        vec![
+            ("FromStr", "/config/@version"),
+            ("FromStr", "/config/server[2]/@cert"),
+            ("MissingField", "/config/server[2]"),
+            ("UnknownField", "/config"),
+            ("MissingField", "/config/server[3]"),
+            ("FromStr", "/config/timeout"),
+        ]
+    );
+
+    // errors the input can't be read past aren't recovered from
+    let (config, errors) = Config::from_str_lenient(r#"<config version="1"><server name="a" <"#);
+    assert_eq!(config, None);
+    assert!(matches!(errors.as_slice(), [err] if matches!(err.error(), XmlError::Parser(_))));
+
+    assert!(Config::from_str(r#"<config version="two"/>"#).is_err());
+
+    Ok(())
+}
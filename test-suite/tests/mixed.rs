@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+use hard_xml::{XmlFormat, XmlRead, XmlResult, XmlWrite, XmlWriter};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "p")]
+struct Para<'a> {
+    #[xml(attr = "id")]
+    id: Option<Cow<'a, str>>,
+    #[xml(mixed)]
+    content: Vec<Inline<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+enum Inline<'a> {
+    #[xml(text)]
+    Text(Cow<'a, str>),
+    #[xml(tag = "b")]
+    Bold(Bold<'a>),
+    #[xml(tag = "a")]
+    Link {
+        #[xml(attr = "href")]
+        href: Cow<'a, str>,
+        #[xml(text)]
+        text: Cow<'a, str>,
+    },
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "b")]
+struct Bold<'a> {
+    #[xml(mixed)]
+    content: Vec<Inline<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "doc")]
+struct Doc<'a> {
+    #[xml(child = "p")]
+    paras: Vec<Para<'a>>,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<p>Hello <b>world</b>, see <a href="x">link</a>.</p>"#;
+    let para = Para::from_str(xml)?;
+
+    assert_eq!(
+        para,
+        Para {
+            id: None,
+            content: vec![
+                Inline::Text("Hello ".into()),
+                Inline::Bold(Bold {
+                    content: vec![Inline::Text("world".into())]
+                }),
+                Inline::Text(", see ".into()),
+                Inline::Link {
+                    href: "x".into(),
+                    text: "link".into()
+                },
+                Inline::Text(".".into()),
+            ],
+        }
+    );
+    assert_eq!(para.to_string()?, xml);
+
+    // adjacent text, entities and CDATA form a single item, unknown elements
+    // and comments are skipped
+    assert_eq!(
+        Para::from_str(r#"<p id="1"><b/>a &amp; <!-- c --><![CDATA[<b>]]><i>x</i> b<b>c<b>d</b></b></p>"#)?,
+        Para {
+            id: Some("1".into()),
+            content: vec![
+                Inline::Bold(Bold { content: vec![] }),
+                Inline::Text("a & <b>".into()),
+                Inline::Text(" b".into()),
+                Inline::Bold(Bold {
+                    content: vec![
+                        Inline::Text("c".into()),
+                        Inline::Bold(Bold {
+                            content: vec![Inline::Text("d".into())]
+                        }),
+                    ]
+                }),
+            ],
+        }
+    );
+
+    // no whitespace is added to mixed content when pretty printing
+    let doc = Doc {
+        paras: vec![
+            Para::from_str("<p><b>bold</b> text</p>")?,
+            Para::from_str("<p></p>")?,
+        ],
+    };
+    let mut writer = XmlWriter::with_format(Vec::new(), XmlFormat::default());
+    doc.to_writer(&mut writer)?;
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        "<doc>\n  <p><b>bold</b> text</p>\n  <p/>\n</doc>"
+    );
+
+    Ok(())
+}
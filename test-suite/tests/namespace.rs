@@ -0,0 +1,114 @@
+use hard_xml::{XmlRead, XmlResult};
+use std::borrow::Cow;
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "feed", ns = "urn:feed")]
+struct Feed<'a> {
+    #[xml(attr = "lang", ns = "http://www.w3.org/XML/1998/namespace")]
+    lang: Option<Cow<'a, str>>,
+    #[xml(attr = "version")]
+    version: Cow<'a, str>,
+    #[xml(flatten_text = "title", ns = "urn:feed")]
+    title: Cow<'a, str>,
+    #[xml(child = "item", ns = "urn:feed")]
+    items: Vec<Item<'a>>,
+    #[xml(child = "link", ns = "urn:ext")]
+    links: Vec<Link<'a>>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "item", ns = "urn:feed")]
+struct Item<'a> {
+    #[xml(attr = "id", ns = "urn:ext")]
+    id: Option<Cow<'a, str>>,
+    #[xml(text)]
+    text: Cow<'a, str>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "link", ns = "urn:ext")]
+struct Link<'a> {
+    #[xml(attr = "href")]
+    href: Cow<'a, str>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+enum Entry<'a> {
+    #[xml(tag = "item", ns = "urn:feed")]
+    Item(Item<'a>),
+    #[xml(tag = "link", ns = "urn:ext")]
+    Link(Link<'a>),
+}
+
+fn expected() -> Feed<'static> {
+    Feed {
+        lang: Some("en".into()),
+        version: "1".into(),
+        title: "title".into(),
+        items: vec![
+            Item {
+                id: Some("1".into()),
+                text: "first".into(),
+            },
+            Item {
+                id: None,
+                text: "second".into(),
+            },
+        ],
+        links: vec![Link {
+            href: "http://example.com".into(),
+        }],
+    }
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    assert_eq!(
+        Feed::from_str(
+            r#"<feed xmlns="urn:feed" xmlns:e="urn:ext" xml:lang="en" version="1"><title>title</title><item e:id="1">first</item><item>second</item><e:link href="http://example.com"/></feed>"#
+        )?,
+        expected()
+    );
+
+    // same document with the prefixes swapped around
+    assert_eq!(
+        Feed::from_str(
+            r#"<f:feed xmlns:f="urn:feed" xml:lang="en" version="1"><f:title>title</f:title><f:item xmlns:x="urn:ext" x:id="1">first</f:item><item xmlns="urn:feed">second</item><link xmlns="urn:ext" href="http://example.com"/></f:feed>"#
+        )?,
+        expected()
+    );
+
+    // elements with the right local name in another namespace are skipped
+    assert_eq!(
+        Feed::from_str(
+            r#"<feed xmlns="urn:feed" version="1"><title>title</title><item xmlns="urn:other">ignored</item><link href="ignored"/></feed>"#
+        )?,
+        Feed {
+            lang: None,
+            version: "1".into(),
+            title: "title".into(),
+            items: vec![],
+            links: vec![],
+        }
+    );
+
+    assert_eq!(
+        Entry::from_str(r#"<a:link xmlns:a="urn:ext" href="x"/>"#)?,
+        Entry::Link(Link { href: "x".into() })
+    );
+
+    assert_eq!(
+        Entry::from_str(r#"<b:item xmlns:b="urn:feed">y</b:item>"#)?,
+        Entry::Item(Item {
+            id: None,
+            text: "y".into()
+        })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use hard_xml::{XmlRead, XmlResult, XmlWrite};
+use std::borrow::Cow;
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "document", ns = "urn:doc", prefix = "w", default_ns = "urn:meta")]
+struct Document<'a> {
+    #[xml(attr = "lang", ns = "http://www.w3.org/XML/1998/namespace")]
+    lang: Option<Cow<'a, str>>,
+    #[xml(flatten_text = "title")]
+    title: Cow<'a, str>,
+    #[xml(child = "p", ns = "urn:doc")]
+    paragraphs: Vec<Paragraph<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "p", ns = "urn:doc")]
+struct Paragraph<'a> {
+    #[xml(attr = "id", ns = "urn:ids")]
+    id: Option<Cow<'a, str>>,
+    #[xml(child = "r", child = "other", ns = "urn:doc")]
+    runs: Vec<Run<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+enum Run<'a> {
+    #[xml(tag = "r", ns = "urn:doc")]
+    Text {
+        #[xml(text)]
+        text: Cow<'a, str>,
+    },
+    #[xml(tag = "other", ns = "urn:doc")]
+    Other {
+        #[xml(attr = "id", ns = "urn:ids")]
+        id: Cow<'a, str>,
+    },
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "root", ns = "urn:x")]
+struct Root {
+    #[xml(child = "plain")]
+    plain: Vec<Plain>,
+    #[xml(child = "item", ns = "urn:x")]
+    items: Vec<Item>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "plain")]
+struct Plain {
+    #[xml(attr = "a")]
+    a: u32,
+    #[xml(child = "item", ns = "urn:x")]
+    items: Vec<Item>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "item", ns = "urn:x")]
+struct Item;
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let doc = Document {
+        lang: Some("en".into()),
+        title: "title".into(),
+        paragraphs: vec![
+            Paragraph {
+                id: Some("1".into()),
+                runs: vec![
+                    Run::Text { text: "a".into() },
+                    Run::Other { id: "2".into() },
+                ],
+            },
+            Paragraph {
+                id: None,
+                runs: vec![],
+            },
+        ],
+    };
+
+    let xml = doc.to_string()?;
+
+    assert_eq!(
+        xml,
+        concat!(
+            r#"<w:document xmlns:w="urn:doc" xmlns="urn:meta" xmlns:ns0="urn:ids" xml:lang="en">"#,
+            r#"<w:p ns0:id="1"><w:r>a</w:r><w:other ns0:id="2"/></w:p>"#,
+            r#"<w:p/>"#,
+            r#"<title>title</title>"#,
+            r#"</w:document>"#
+        )
+    );
+
+    assert_eq!(Document::from_str(&xml)?, doc);
+
+    // writing a child on its own declares what it needs
+    assert_eq!(
+        (Paragraph {
+            id: Some("1".into()),
+            runs: vec![Run::Text { text: "a".into() }],
+        })
+        .to_string()?,
+        r#"<p xmlns="urn:doc" xmlns:ns0="urn:ids" ns0:id="1"><r>a</r></p>"#
+    );
+
+    assert_eq!(
+        (Run::Other { id: "2".into() }).to_string()?,
+        r#"<other xmlns="urn:doc" xmlns:ns0="urn:ids" ns0:id="2"/>"#
+    );
+
+    // elements without a namespace don't inherit one declared for their parent
+    let root = Root {
+        plain: vec![Plain {
+            a: 1,
+            items: vec![Item],
+        }],
+        items: vec![Item],
+    };
+    let xml = root.to_string()?;
+    assert_eq!(
+        xml,
+        concat!(
+            r#"<root xmlns="urn:x">"#,
+            r#"<plain xmlns="" a="1"><item xmlns="urn:x"/></plain>"#,
+            r#"<item/>"#,
+            r#"</root>"#
+        )
+    );
+    assert_eq!(Root::from_str(&xml)?, root);
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use hard_xml::{XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "order")]
+struct Order {
+    #[xml(child = "price", nillable)]
+    price: Option<Price>,
+    #[xml(child = "discount", nillable = "explicit")]
+    discount: Option<Discount>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "price")]
+struct Price {
+    #[xml(attr = "amount")]
+    amount: u32,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "discount")]
+struct Discount {
+    #[xml(attr = "percent")]
+    percent: u32,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let order = Order::from_str(
+        r#"<order xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"><price xsi:nil="true"/><discount xsi:nil="true"></discount></order>"#,
+    )?;
+    assert_eq!(
+        order,
+        Order {
+            price: None,
+            discount: None,
+        }
+    );
+    // the `xsi` prefix is only declared where a nil element is written
+    assert_eq!(
+        order.to_string()?,
+        r#"<order><discount xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:nil="true"/></order>"#
+    );
+
+    // any prefix bound to the namespace works, and `xsi:nil="false"` is ignored
+    let order = Order::from_str(
+        r#"<order><price i:nil="false" amount="3" xmlns:i="http://www.w3.org/2001/XMLSchema-instance"/><discount percent="5"/></order>"#,
+    )?;
+    assert_eq!(
+        order,
+        Order {
+            price: Some(Price { amount: 3 }),
+            discount: Some(Discount { percent: 5 }),
+        }
+    );
+    assert_eq!(
+        order.to_string()?,
+        r#"<order><price amount="3"/><discount percent="5"/></order>"#
+    );
+
+    // a prefix in scope is reused
+    let mut writer = hard_xml::XmlWriter::new(Vec::new());
+    writer.write_element_start("wrapper")?;
+    writer.write_namespace("i", "http://www.w3.org/2001/XMLSchema-instance")?;
+    writer.write_element_end_open()?;
+    Order {
+        price: None,
+        discount: None,
+    }
+    .to_writer(&mut writer)?;
+    writer.write_element_end_close("wrapper")?;
+    assert_eq!(
+        String::from_utf8(writer.into_inner()).unwrap(),
+        r#"<wrapper xmlns:i="http://www.w3.org/2001/XMLSchema-instance"><order><discount i:nil="true"/></order></wrapper>"#
+    );
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use hard_xml::{XmlError, XmlRead, XmlResult};
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "config")]
+struct Config {
+    #[xml(child = "server")]
+    servers: Vec<Server>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "server")]
+struct Server {
+    #[xml(attr = "name")]
+    name: String,
+    #[xml(child = "tls")]
+    tls: Option<Tls>,
+    #[xml(flatten_text = "alias")]
+    aliases: Vec<u8>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "tls")]
+struct Tls {
+    #[xml(attr = "port")]
+    port: u16,
+    #[xml(text)]
+    cert: String,
+}
+
+fn path(xml: &str) -> (String, String) {
+    let err = Config::from_str(xml).unwrap_err();
+    let path = err.path().unwrap();
+    assert!(err.to_string().ends_with(&format!(" in {}", path)));
+    (path.elements.clone(), path.fields.clone())
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    assert_eq!(
+        path(
+            r#"<config><server name="a"/><server name="b"/><server name="c"><tls port="x">c.pem</tls></server></config>"#
+        ),
+        ("/config/server[3]/tls/@port".into(), "Config.servers.tls.port".into())
+    );
+
+    assert_eq!(
+        path(r#"<config><server name="a"><alias>1</alias><alias>y</alias></server></config>"#),
+        ("/config/server[1]/alias[2]".into(), "Config.servers.aliases".into())
+    );
+
+    let err = Config::from_str(r#"<config><server name="a"/><server/></config>"#).unwrap_err();
+    assert!(matches!(
+        err.error(),
+        XmlError::MissingField { name, field } if name == "Server" && field == "name"
+    ));
+    assert_eq!(err.path().unwrap().elements, "/config/server[2]");
+    assert_eq!(
+        err.to_string(),
+        r#"missing field in XML of "Server": "name" at line 1, column 34 in /config/server[2] (field Config.servers)"#
+    );
+
+    let err = Tls::from_str(r#"<tls port="1"><a/></tls>"#).unwrap_err();
+    assert_eq!(err.path().unwrap().fields, "Tls.cert");
+
+    Ok(())
+}
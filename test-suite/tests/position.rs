@@ -0,0 +1,63 @@
+use hard_xml::{XmlError, XmlRead, XmlReadError, XmlReadOwned, XmlResult};
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "list")]
+struct List {
+    #[xml(child = "item")]
+    items: Vec<Item>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "item")]
+struct Item {
+    #[xml(attr = "id")]
+    id: usize,
+    #[xml(flatten_text = "name")]
+    name: String,
+}
+
+fn locate(xml: &str) -> (XmlReadError, Option<(usize, usize, usize)>) {
+    let err = List::from_str(xml).unwrap_err();
+    let position = err.position().map(|pos| (pos.offset, pos.line, pos.column));
+
+    // the stream backend reports the same position
+    let streamed = List::from_stream(std::io::BufReader::with_capacity(3, xml.as_bytes()))
+        .unwrap_err()
+        .position()
+        .map(|pos| (pos.offset, pos.line, pos.column));
+    assert_eq!(position, streamed);
+
+    (err, position)
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let (err, position) = locate(
+        "<list>\n  <item id=\"1\"><name>a</name></item>\n  <item id=\"x\"><name>b</name></item>\n</list>",
+    );
+    assert!(matches!(err.error(), XmlError::FromStr(_)));
+    assert_eq!(position, Some((52, 3, 9)));
+    assert!(err.to_string().contains(" at line 3, column 9 "));
+
+    let (err, position) = locate("<list>\n  <item id=\"1\"></item>\n</list>");
+    assert!(matches!(
+        err.error(),
+        XmlError::MissingField { name, field } if name == "Item" && field == "name"
+    ));
+    assert_eq!(position, Some((22, 2, 16)));
+
+    let (err, position) = locate("<list>\n  <item id=\"1\"><name>ä</nome></item>\n</list>");
+    assert!(matches!(err.error(), XmlError::TagMismatch { .. }));
+    assert_eq!(position, Some((30, 2, 23)));
+
+    let (err, position) = locate("<list>\n  <item id=\"1\"><name>a</name></item>\n  <item id=\"2\" <");
+    assert!(matches!(err.error(), XmlError::Parser(_)));
+    assert_eq!(position.map(|(_, line, _)| line), Some(3));
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use hard_xml::{XmlFormat, XmlRead, XmlResult, XmlWrite, XmlWriter};
+use std::borrow::Cow;
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "config")]
+struct Config<'a> {
+    #[xml(attr = "version")]
+    version: Cow<'a, str>,
+    #[xml(child = "server")]
+    servers: Vec<Server<'a>>,
+    #[xml(flatten_text = "motd")]
+    motd: Option<Cow<'a, str>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "server")]
+struct Server<'a> {
+    #[xml(attr = "name")]
+    name: Cow<'a, str>,
+    #[xml(child = "alias")]
+    aliases: Vec<Alias<'a>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "alias")]
+struct Alias<'a> {
+    #[xml(text)]
+    text: Cow<'a, str>,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let config = Config {
+        version: "1".into(),
+        servers: vec![
+            Server {
+                name: "a".into(),
+                aliases: vec![
+                    Alias { text: " a1 ".into() },
+                    Alias {
+                        text: "line\nbreak".into(),
+                    },
+                ],
+            },
+            Server {
+                name: "b".into(),
+                aliases: vec![],
+            },
+        ],
+        motd: Some("hello".into()),
+    };
+
+    let xml = config.to_string_pretty()?;
+
+    assert_eq!(
+        xml,
+        r#"<config version="1">
+  <server name="a">
+    <alias> a1 </alias>
+    <alias>line
+break</alias>
+  </server>
+  <server name="b"/>
+  <motd>hello</motd>
+</config>"#
+    );
+
+    assert_eq!(Config::from_str(&xml)?, config);
+
+    let mut writer = XmlWriter::with_format(
+        Vec::new(),
+        XmlFormat {
+            indent: "\t".into(),
+            newline: "\r\n".into(),
+            inline_text: true,
+        },
+    );
+
+    config.servers[0].to_writer(&mut writer)?;
+
+    let xml = String::from_utf8(writer.into_inner())?;
+
+    assert_eq!(
+        xml,
+        "<server name=\"a\">\r\n\t<alias> a1 </alias>\r\n\t<alias>line&#10;break</alias>\r\n</server>"
+    );
+
+    assert_eq!(Server::from_str(&xml)?, config.servers[0]);
+
+    Ok(())
+}
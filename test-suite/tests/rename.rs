@@ -0,0 +1,72 @@
+use std::borrow::Cow;
+
+use hard_xml::{XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "server", rename_all = "camelCase")]
+struct Server<'a> {
+    #[xml(attr)]
+    host_name: Cow<'a, str>,
+    #[xml(attr = "port")]
+    port_number: u16,
+    #[xml(attr)]
+    r#type: Option<Cow<'a, str>>,
+    #[xml(flatten_text)]
+    admin_email: Vec<Cow<'a, str>>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+enum Setting<'a> {
+    #[xml(tag = "limit", rename_all = "kebab-case")]
+    Limit {
+        #[xml(attr)]
+        max_connections: u32,
+        #[xml(flatten_text)]
+        idle_timeout: Option<Cow<'a, str>>,
+    },
+    #[xml(tag = "log")]
+    Log {
+        #[xml(attr)]
+        log_level: Cow<'a, str>,
+    },
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<server hostName="a.example" port="80" type="web"><adminEmail>a@example.com</adminEmail></server>"#;
+    let server = Server::from_str(xml)?;
+
+    assert_eq!(
+        server,
+        Server {
+            host_name: "a.example".into(),
+            port_number: 80,
+            r#type: Some("web".into()),
+            admin_email: vec!["a@example.com".into()],
+        }
+    );
+    assert_eq!(server.to_string()?, xml);
+
+    let xml = r#"<limit max-connections="10"><idle-timeout>5s</idle-timeout></limit>"#;
+    let limit = Setting::from_str(xml)?;
+
+    assert_eq!(
+        limit,
+        Setting::Limit {
+            max_connections: 10,
+            idle_timeout: Some("5s".into()),
+        }
+    );
+    assert_eq!(limit.to_string()?, xml);
+
+    // without `rename_all` the field name is used as is
+    let xml = r#"<log log_level="debug"/>"#;
+    assert_eq!(Setting::from_str(xml)?.to_string()?, xml);
+
+    Ok(())
+}
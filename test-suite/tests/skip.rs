@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+
+use hard_xml::{XmlRead, XmlResult, XmlWrite};
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "feature")]
+struct Feature<'a> {
+    #[xml(attr = "name")]
+    name: Cow<'a, str>,
+    #[xml(default, skip_default, attr = "enabled")]
+    enabled: bool,
+    #[xml(default, skip_default, attr = "weight")]
+    weight: u32,
+    #[xml(skip_serializing_if = "str::is_empty", default, flatten_text = "note")]
+    note: Cow<'a, str>,
+    #[xml(skip_serializing_if = "Vec::is_empty", child = "feature")]
+    children: Vec<Feature<'a>>,
+    #[xml(skip)]
+    cache: Option<usize>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "limit")]
+struct Limit(
+    #[xml(attr = "max")] u32,
+    #[xml(skip)] Vec<u32>,
+    #[xml(default, skip_default, attr = "min")] u32,
+);
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let feature = Feature {
+        name: "a".into(),
+        enabled: false,
+        weight: 0,
+        note: "".into(),
+        children: vec![],
+        cache: Some(1),
+    };
+    assert_eq!(feature.to_string()?, r#"<feature name="a"/>"#);
+
+    // skipped fields are filled in from `Default`
+    assert_eq!(
+        Feature::from_str(r#"<feature name="a"><cache>1</cache></feature>"#)?,
+        Feature { cache: None, ..feature }
+    );
+
+    let feature = Feature {
+        name: "b".into(),
+        enabled: true,
+        weight: 2,
+        note: "new".into(),
+        children: vec![Feature::from_str(r#"<feature name="c"/>"#)?],
+        cache: None,
+    };
+    let xml = r#"<feature name="b" enabled="1" weight="2"><feature name="c"/><note>new</note></feature>"#;
+    assert_eq!(feature.to_string()?, xml);
+    assert_eq!(Feature::from_str(xml)?, feature);
+
+    assert_eq!(Limit(3, vec![1], 0).to_string()?, r#"<limit max="3"/>"#);
+    assert_eq!(Limit(3, vec![1], 2).to_string()?, r#"<limit max="3" min="2"/>"#);
+    assert_eq!(Limit::from_str(r#"<limit max="3"/>"#)?, Limit(3, vec![], 0));
+
+    Ok(())
+}
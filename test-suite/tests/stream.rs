@@ -0,0 +1,69 @@
+use hard_xml::{XmlRead, XmlReadOwned, XmlResult};
+use std::io::BufReader;
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "export")]
+struct Export {
+    #[xml(attr = "name")]
+    name: String,
+    #[xml(child = "record")]
+    records: Vec<Record>,
+}
+
+#[derive(XmlRead, PartialEq, Debug)]
+#[xml(tag = "record")]
+struct Record {
+    #[xml(attr = "id")]
+    id: usize,
+    #[xml(text)]
+    text: String,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let mut xml =
+        String::from(r#"<?xml version="1.0" encoding="UTF-8"?><export name="dump &amp; co">"#);
+    for id in 0..1000 {
+        xml.push_str(&format!(
+            r#"<record id="{}">entrée <![CDATA[#{}]]></record>"#,
+            id, id
+        ));
+    }
+    xml.push_str("<!-- end --></export>");
+
+    let expected = Export {
+        name: "dump & co".into(),
+        records: (0..1000)
+            .map(|id| Record {
+                id,
+                text: format!("entrée #{}", id),
+            })
+            .collect(),
+    };
+
+    assert_eq!(Export::from_str(&xml)?, expected);
+    assert_eq!(Export::from_stream(xml.as_bytes())?, expected);
+    assert_eq!(
+        Export::from_stream(BufReader::with_capacity(7, xml.as_bytes()))?,
+        expected
+    );
+
+    let mut consumed = Vec::new();
+    assert_eq!(
+        Export::from_stream_with_progress(BufReader::with_capacity(64, xml.as_bytes()), |n| {
+            consumed.push(n)
+        })?,
+        expected
+    );
+    assert!(consumed.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(consumed.last(), Some(&(xml.len() as u64)));
+
+    assert!(Export::from_stream(&b"<export name=\"a\"><record id=\"1\">"[..]).is_err());
+
+    Ok(())
+}
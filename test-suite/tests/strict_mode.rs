@@ -28,20 +28,28 @@ fn test() -> XmlResult<()> {
 
 #[test]
 fn test_unknown_attribute() -> XmlResult<()> {
+    let xml = r#"<root attr="test" version="2.0"><data>This is some test data!</data></root>"#;
     assert!(matches!(
-        Root::from_str(r#"<root attr="test" version="2.0"><data>This is some test data!</data></root>"#),
+        Root::from_str(xml).map_err(XmlError::from),
         Err(XmlError::UnknownField { name, field }) if name == "Root" && field == "attr"
     ));
 
+    let position = Root::from_str(xml).unwrap_err().position();
+    assert_eq!(position.map(|pos| (pos.line, pos.column)), Some((1, 7)));
+
     Ok(())
 }
 
 #[test]
 fn test_unknown_child() -> XmlResult<()> {
+    let xml = r#"<root version="2.0"><data>This is some test data!</data><child /></root>"#;
     assert!(matches!(
-        Root::from_str(r#"<root version="2.0"><data>This is some test data!</data><child /></root>"#),
+        Root::from_str(xml).map_err(XmlError::from),
         Err(XmlError::UnknownField { name, field }) if name == "Root" && field == "child"
     ));
 
+    let position = Root::from_str(xml).unwrap_err().position();
+    assert_eq!(position.map(|pos| (pos.line, pos.column)), Some((1, 57)));
+
     Ok(())
 }
@@ -0,0 +1,85 @@
+use hard_xml::{UnknownValue, XmlError, XmlRead, XmlResult, XmlValue, XmlWrite};
+
+#[derive(XmlValue, PartialEq, Debug)]
+enum Mode {
+    #[xml(value = "fast")]
+    Fast,
+    #[xml(value = "agile")]
+    Agile,
+    Cruise,
+}
+
+#[derive(XmlValue, PartialEq, Debug)]
+enum Color {
+    #[xml(value = "red")]
+    Red,
+    #[xml(other, value = "unknown")]
+    Other,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "spaceship")]
+struct Spaceship {
+    #[xml(attr = "mode")]
+    mode: Mode,
+    #[xml(attr = "color")]
+    color: Option<Color>,
+    #[xml(flatten_text = "fallback")]
+    fallback: Vec<Mode>,
+}
+
+#[derive(XmlWrite, XmlRead, PartialEq, Debug)]
+#[xml(tag = "mode")]
+struct ModeText {
+    #[xml(text)]
+    mode: Mode,
+}
+
+#[test]
+fn test() -> XmlResult<()> {
+    let _ = env_logger::builder()
+        .is_test(true)
+        .format_timestamp(None)
+        .try_init();
+
+    let xml = r#"<spaceship mode="fast" color="red"><fallback>agile</fallback><fallback>Cruise</fallback></spaceship>"#;
+    let spaceship = Spaceship::from_str(xml)?;
+
+    assert_eq!(
+        spaceship,
+        Spaceship {
+            mode: Mode::Fast,
+            color: Some(Color::Red),
+            fallback: vec![Mode::Agile, Mode::Cruise],
+        }
+    );
+    assert_eq!(spaceship.to_string()?, xml);
+
+    assert_eq!(ModeText::from_str("<mode>agile</mode>")?.mode, Mode::Agile);
+
+    // values without a variant go to the `other` one
+    let spaceship = Spaceship::from_str(r#"<spaceship mode="agile" color="blue"/>"#)?;
+    assert_eq!(spaceship.color, Some(Color::Other));
+    assert_eq!(
+        spaceship.to_string()?,
+        r#"<spaceship mode="agile" color="unknown"/>"#
+    );
+
+    let err = Spaceship::from_str(r#"<spaceship mode="slow"/>"#).unwrap_err();
+    match err.error() {
+        XmlError::FromStr(err) => assert_eq!(
+            err.downcast_ref::<UnknownValue>(),
+            Some(&UnknownValue {
+                value: "slow".into(),
+                expected: &["fast", "agile", "Cruise"],
+            })
+        ),
+        err => panic!("unexpected error: {:?}", err),
+    }
+    assert_eq!(
+        err.error().to_string(),
+        r#"error parsing XML value: unknown value "slow", expected "fast", "agile" or "Cruise""#
+    );
+
+    Ok(())
+}